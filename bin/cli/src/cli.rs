@@ -1,5 +1,7 @@
 use structopt::StructOpt;
-use sc_cli::{RunCmd, KeySubcommand, SignCmd, VanityCmd, VerifyCmd};
+use sc_cli::{RunCmd, KeySubcommand, SignCmd, VanityCmd, VerifyCmd, SharedParams, PruningParams, CliConfiguration};
+use uniarts_primitives::BlockNumber;
+use std::path::PathBuf;
 
 #[derive(Debug, StructOpt)]
 pub struct Cli {
@@ -8,6 +10,129 @@ pub struct Cli {
 
 	#[structopt(flatten)]
 	pub run: RunCmd,
+
+	#[structopt(flatten)]
+	pub eth: EthRpcParams,
+}
+
+/// Ethereum RPC limits and pool policy.
+#[derive(Debug, StructOpt)]
+pub struct EthRpcParams {
+	/// Maximum number of blocks an `eth_getLogs` query can span.
+	#[structopt(long, default_value = "10000")]
+	pub max_past_logs_range: u32,
+
+	/// Maximum number of logs an `eth_getLogs` query can return.
+	#[structopt(long, default_value = "10000")]
+	pub max_logs_count: usize,
+
+	/// Minimum gas price increase, in percent, of a transaction replacing a
+	/// pooled one with the same sender and nonce.
+	#[structopt(long, default_value = "10")]
+	pub tx_replacement_bump: u32,
+
+	/// Maximum number of blocks a `trace_filter` query can span.
+	#[structopt(long, default_value = "1000")]
+	pub max_trace_range: u32,
+
+	/// Number of blocks whose decoded Ethereum data is cached by the RPC, 0 to
+	/// disable the cache.
+	#[structopt(long, default_value = "50")]
+	pub eth_block_cache_size: usize,
+
+	/// Maximum number of installed `eth_newFilter` filters.
+	#[structopt(long, default_value = "500")]
+	pub max_filters: usize,
+
+	/// Maximum number of filters installed by a single WebSocket or IPC
+	/// connection. Filters installed over HTTP only count towards `--max-filters`.
+	#[structopt(long, default_value = "100")]
+	pub max_filters_per_connection: usize,
+
+	/// Maximum number of results a filter can return at once. Polls return the
+	/// logs of the first blocks up to it and resume from the next block.
+	#[structopt(long, default_value = "10000")]
+	pub max_filter_results: usize,
+
+	/// Time, in seconds, after which a filter which isn't polled is uninstalled.
+	#[structopt(long, default_value = "300")]
+	pub filter_lifetime: u64,
+
+	/// RPC methods which are served, all of them by default. A trailing `*`
+	/// matches a prefix, e.g. `eth_*`.
+	#[structopt(long, use_delimiter = true)]
+	pub rpc_allow_methods: Vec<String>,
+
+	/// RPC methods which are not served, taking precedence over the allowed ones.
+	#[structopt(long, use_delimiter = true)]
+	pub rpc_deny_methods: Vec<String>,
+
+	/// File of `allow <method>` and `deny <method>` lines, added to the
+	/// `--rpc-allow-methods` and `--rpc-deny-methods` lists.
+	#[structopt(long, parse(from_os_str))]
+	pub rpc_policy_file: Option<PathBuf>,
+
+	/// RPC calls per second allowed to a WebSocket or IPC connection, not to an
+	/// IP address: use a reverse proxy to limit IP addresses. Unlimited by default.
+	#[structopt(long)]
+	pub rpc_rate_limit: Option<u32>,
+
+	/// RPC calls per second allowed to all the HTTP requests together, whose
+	/// clients can't be told apart. Unlimited by default.
+	#[structopt(long)]
+	pub rpc_http_rate_limit: Option<u32>,
+
+	/// RPC calls a connection, or the HTTP requests, can make at once before
+	/// being rate limited.
+	#[structopt(long, default_value = "100")]
+	pub rpc_rate_burst: u32,
+
+	/// Time, in milliseconds, after which `eth_call`, `eth_estimateGas` and
+	/// the `trace_*` calls are answered with an error. Unlimited by default.
+	#[structopt(long)]
+	pub evm_call_timeout: Option<u64>,
+
+	/// Maximum number of EVM calls executing at once, when `--evm-call-timeout` is set.
+	#[structopt(long, default_value = "16")]
+	pub max_concurrent_evm_calls: usize,
+
+	/// Sign transactions and messages with the Ethereum accounts of the node's
+	/// keystore, and serve the `personal_*` methods managing them. Accounts are
+	/// unlocked with the keystore password (`--password`), without which they
+	/// can't be created nor unlocked.
+	#[structopt(long)]
+	pub enable_keystore_signer: bool,
+}
+
+impl EthRpcParams {
+	pub fn eth_rpc_config(&self) -> Result<uniarts_service::service::fuxi::EthRpcConfig, String> {
+		let mut rpc_guard = uniarts_service::service::fuxi::RpcGuardConfig {
+			allow: self.rpc_allow_methods.clone(),
+			deny: self.rpc_deny_methods.clone(),
+			rate_limit: self.rpc_rate_limit,
+			http_rate_limit: self.rpc_http_rate_limit,
+			rate_burst: self.rpc_rate_burst,
+			evm_time_budget: self.evm_call_timeout.map(std::time::Duration::from_millis),
+			max_concurrent_evm_calls: self.max_concurrent_evm_calls,
+		};
+		if let Some(path) = &self.rpc_policy_file {
+			rpc_guard.load_policy(path)?;
+		}
+
+		Ok(uniarts_service::service::fuxi::EthRpcConfig {
+			max_past_logs_range: self.max_past_logs_range,
+			max_logs_count: self.max_logs_count,
+			tx_replacement_bump: self.tx_replacement_bump,
+			max_trace_range: self.max_trace_range,
+			block_cache_size: self.eth_block_cache_size,
+			max_filters: self.max_filters,
+			max_filters_per_connection: self.max_filters_per_connection,
+			max_filter_results: self.max_filter_results,
+			filter_lifetime: std::time::Duration::from_secs(self.filter_lifetime),
+			enable_keystore_signer: self.enable_keystore_signer,
+			rpc_guard,
+		})
+	}
 }
 
 #[derive(Debug, StructOpt)]
@@ -44,4 +169,38 @@ pub enum Subcommand {
 
 	/// Revert the chain to a previous state.
 	Revert(sc_cli::RevertCmd),
+
+	/// Rebuild the Frontier block and transaction mappings from the block digests,
+	/// and the Ethereum blocks, receipts and statuses from the block state.
+	FrontierReindex(FrontierReindexCmd),
+}
+
+/// The `frontier-reindex` command.
+#[derive(Debug, StructOpt)]
+pub struct FrontierReindexCmd {
+	/// First block to re-index.
+	#[structopt(long, default_value = "0")]
+	pub from: BlockNumber,
+
+	/// Last block to re-index, the best block by default.
+	#[structopt(long)]
+	pub to: Option<BlockNumber>,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub shared_params: SharedParams,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub pruning_params: PruningParams,
+}
+
+impl CliConfiguration for FrontierReindexCmd {
+	fn shared_params(&self) -> &SharedParams {
+		&self.shared_params
+	}
+
+	fn pruning_params(&self) -> Option<&PruningParams> {
+		Some(&self.pruning_params)
+	}
 }
@@ -267,6 +267,24 @@ pub fn run() -> sc_cli::Result<()> {
 				unreachable!()
 			}
 		},
+		Some(Subcommand::FrontierReindex(cmd)) => {
+			let runner = cli.create_runner(cmd)?;
+			let chain_spec = &runner.config().chain_spec;
+
+			set_default_ss58_version(chain_spec);
+
+			if chain_spec.is_fuxi_network() {
+				runner.sync_run(|mut config| {
+					uniarts_service::service::fuxi::frontier_reindex::<
+						fuxi_runtime::RuntimeApi,
+						FuxiExecutor,
+					>(&mut config, cmd.from, cmd.to)
+						.map_err(sc_cli::Error::Service)
+				})
+			} else {
+				Err(sc_cli::Error::Input("frontier-reindex is only available on fuxi networks".into()))
+			}
+		},
 		None => {
 			let runner = cli.create_runner(&cli.run)?;
 			let chain_spec = &runner.config().chain_spec;
@@ -293,12 +311,13 @@ pub fn run() -> sc_cli::Result<()> {
 					}.map_err(sc_cli::Error::Service)
 				})
 			} else if chain_spec.is_fuxi_network() {
+				let eth_config = cli.eth.eth_rpc_config().map_err(sc_cli::Error::Input)?;
 				runner.run_node_until_exit(|config| async move {
 					match config.role {
 						Role::Light => {
 							uniarts_service::service::fuxi::fuxi_new_light(config)
 						},
-						_ => uniarts_service::service::fuxi::fuxi_new_full(config).map(|(task_manager, _)| task_manager),
+						_ => uniarts_service::service::fuxi::fuxi_new_full(config, eth_config).map(|(task_manager, _)| task_manager),
 					}.map_err(sc_cli::Error::Service)
 				})
 			} else {
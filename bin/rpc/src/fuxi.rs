@@ -5,7 +5,7 @@
 
 #![warn(missing_docs)]
 
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
 use uniarts_primitives::{OpaqueBlock as Block, AccountId, Balance, Index, BlockNumber, Nonce, Hash};
 use sp_api::ProvideRuntimeApi;
@@ -16,6 +16,7 @@ use sp_transaction_pool::TransactionPool;
 
 use sc_client_api::{
     backend::{StorageProvider, Backend, StateBackend, AuxStore},
+    ProofProvider, BlockBackend,
     client::BlockchainEvents
 };
 use sc_transaction_graph::{ChainApi, Pool};
@@ -24,6 +25,56 @@ use sp_runtime::traits::BlakeTwo256;
 use sc_network::NetworkService;
 use jsonrpc_pubsub::manager::SubscriptionManager;
 use fc_rpc_core::types::{PendingTransactions, FilterPool};
+use crate::middleware::{RpcGuard, RpcGuardConfig};
+
+/// Limits and pool policy of the Ethereum RPC.
+#[derive(Clone, Debug)]
+pub struct EthRpcConfig {
+    /// Maximum number of blocks an `eth_getLogs` query can span.
+    pub max_past_logs_range: u32,
+    /// Maximum number of logs an `eth_getLogs` query can return.
+    pub max_logs_count: usize,
+    /// Minimum gas price increase, in percent, of a transaction replacing a
+    /// pooled one with the same sender and nonce.
+    pub tx_replacement_bump: u32,
+    /// Maximum number of blocks a `trace_filter` query can span.
+    pub max_trace_range: u32,
+    /// Number of blocks whose decoded Ethereum data is cached by the RPC, 0 to
+    /// disable the cache.
+    pub block_cache_size: usize,
+    /// Maximum number of installed filters.
+    pub max_filters: usize,
+    /// Maximum number of filters installed by a single WebSocket or IPC
+    /// connection. Filters installed over HTTP only count towards `max_filters`.
+    pub max_filters_per_connection: usize,
+    /// Maximum number of results a filter can return at once. Polls return the
+    /// logs of the first blocks up to it and resume from the next block.
+    pub max_filter_results: usize,
+    /// Time after which a filter which isn't polled is uninstalled.
+    pub filter_lifetime: Duration,
+    /// Whether to sign with the Ethereum accounts of the node's keystore.
+    pub enable_keystore_signer: bool,
+    /// Access policy of the RPC methods.
+    pub rpc_guard: RpcGuardConfig,
+}
+
+impl Default for EthRpcConfig {
+    fn default() -> Self {
+        Self {
+            max_past_logs_range: 10_000,
+            max_logs_count: 10_000,
+            tx_replacement_bump: 10,
+            max_trace_range: 1_000,
+            block_cache_size: 50,
+            max_filters: 500,
+            max_filters_per_connection: 100,
+            max_filter_results: 10_000,
+            filter_lifetime: Duration::from_secs(300),
+            enable_keystore_signer: false,
+            rpc_guard: RpcGuardConfig::default(),
+        }
+    }
+}
 
 /// Full client dependencies.
 pub struct FullDeps<C, P, A: ChainApi> {
@@ -39,12 +90,24 @@ pub struct FullDeps<C, P, A: ChainApi> {
     pub is_authority: bool,
     /// Whether to enable dev signer
     pub enable_dev_signer: bool,
+    /// Signer of the Ethereum accounts of the keystore
+    pub keystore_signer: Option<fc_rpc::EthKeystoreSigner>,
     /// Network service
     pub network: Arc<NetworkService<Block, Hash>>,
+    /// Network and sync status reported by the Ethereum RPC.
+    pub network_status: Arc<fc_rpc::EthNetworkStatus>,
     /// Ethereum pending transactions.
     pub pending_transactions: PendingTransactions,
     /// EthFilterApi pool.
     pub filter_pool: Option<FilterPool>,
+    /// Frontier mapping database.
+    pub frontier_backend: Arc<fc_db::Backend<Block>>,
+    /// Cache of the Ethereum data of recent blocks.
+    pub block_data_cache: Arc<fc_rpc::EthBlockDataCache<Block>>,
+    /// Ethereum RPC limits.
+    pub eth_config: EthRpcConfig,
+    /// Access policy enforced on the RPC methods.
+    pub rpc_guard: Arc<RpcGuard>,
     /// Manual seal command sink
     pub command_sink: Option<futures::channel::mpsc::Sender<sc_consensus_manual_seal::rpc::EngineCommand<Hash>>>,
 }
@@ -68,8 +131,8 @@ pub fn create_full<C, P, BE, A>(
 ) -> jsonrpc_core::IoHandler<sc_rpc::Metadata> where
     BE: Backend<Block> + 'static,
     BE::State: StateBackend<BlakeTwo256>,
-    C: ProvideRuntimeApi<Block> + StorageProvider<Block, BE> + AuxStore,
-    C: BlockchainEvents<Block>,
+    C: ProvideRuntimeApi<Block> + StorageProvider<Block, BE> + AuxStore + ProofProvider<Block>,
+    C: BlockchainEvents<Block> + BlockBackend<Block>,
     C: HeaderBackend<Block> + HeaderMetadata<Block, Error=BlockChainError> + 'static,
     C: Send + Sync + 'static,
     C::Api: substrate_frame_rpc_system::AccountNonceApi<Block, AccountId, Index>,
@@ -78,6 +141,7 @@ pub fn create_full<C, P, BE, A>(
     C::Api: pallet_contracts_rpc::ContractsRuntimeApi<Block, AccountId, Balance, BlockNumber>,
     C::Api: BlockBuilder<Block>,
     C::Api: fp_rpc::EthereumRuntimeRPCApi<Block>,
+    C::Api: fp_rpc::EvmAccountApi<Block, AccountId>,
     P: TransactionPool<Block=Block> + 'static,
     A: ChainApi<Block = Block> + 'static,
 {
@@ -88,7 +152,8 @@ pub fn create_full<C, P, BE, A>(
     use fc_rpc::{
         EthApi, EthApiServer, EthFilterApi, EthFilterApiServer, NetApi, NetApiServer,
         EthPubSubApi, EthPubSubApiServer, Web3Api, Web3ApiServer, EthDevSigner, EthSigner,
-        HexEncodedIdProvider,
+        HexEncodedIdProvider, Personal, PersonalApiServer, TxPool, TxPoolApiServer, Trace,
+        TraceApiServer, EvmAccount, EvmAccountApiServer, EvmProof, EvmProofApiServer,
     };
 
     let mut io = jsonrpc_core::IoHandler::default();
@@ -99,10 +164,16 @@ pub fn create_full<C, P, BE, A>(
         deny_unsafe,
         is_authority,
         network,
+        network_status,
         pending_transactions,
         filter_pool,
+        frontier_backend,
+        block_data_cache,
+        eth_config,
+        rpc_guard,
         command_sink,
         enable_dev_signer,
+        keystore_signer,
     } = deps;
 
     io.extend_with(
@@ -117,15 +188,28 @@ pub fn create_full<C, P, BE, A>(
     if enable_dev_signer {
         signers.push(Box::new(EthDevSigner::new()) as Box<dyn EthSigner>);
     }
+    if let Some(keystore_signer) = keystore_signer {
+        signers.push(Box::new(keystore_signer.clone()) as Box<dyn EthSigner>);
+        io.extend_with(
+            PersonalApiServer::to_delegate(Personal::new(keystore_signer, deny_unsafe))
+        );
+    }
     io.extend_with(
         EthApiServer::to_delegate(EthApi::new(
             client.clone(),
             pool.clone(),
-            graph,
+            graph.clone(),
             fuxi_runtime::TransactionConverter,
             network.clone(),
+            network_status.clone(),
             pending_transactions.clone(),
             signers,
+            frontier_backend.clone(),
+            block_data_cache.clone(),
+            eth_config.max_past_logs_range,
+            eth_config.max_logs_count,
+            eth_config.tx_replacement_bump,
+            deny_unsafe,
             is_authority,
         ))
     );
@@ -135,15 +219,39 @@ pub fn create_full<C, P, BE, A>(
             EthFilterApiServer::to_delegate(EthFilterApi::new(
                 client.clone(),
                 filter_pool.clone(),
-                500 as usize, // max stored filters
+                block_data_cache.clone(),
+                eth_config.max_filters,
+                eth_config.max_filters_per_connection,
+                eth_config.max_filter_results,
             ))
         );
     }
 
+    io.extend_with(
+        TxPoolApiServer::to_delegate(TxPool::new(client.clone(), graph))
+    );
+
+    io.extend_with(
+        TraceApiServer::to_delegate(Trace::new(
+            client.clone(),
+            frontier_backend.clone(),
+            eth_config.max_trace_range,
+        ))
+    );
+
+    io.extend_with(
+        EvmAccountApiServer::to_delegate(EvmAccount::<_, _, AccountId>::new(client.clone()))
+    );
+
+    io.extend_with(
+        EvmProofApiServer::to_delegate(EvmProof::new(client.clone()))
+    );
+
     io.extend_with(
         NetApiServer::to_delegate(NetApi::new(
             client.clone(),
             network.clone(),
+            network_status.clone(),
         ))
     );
 
@@ -157,11 +265,12 @@ pub fn create_full<C, P, BE, A>(
         EthPubSubApiServer::to_delegate(EthPubSubApi::new(
             pool.clone(),
             client.clone(),
-            network.clone(),
+            network_status,
             SubscriptionManager::<HexEncodedIdProvider>::with_id_provider(
                 HexEncodedIdProvider::default(),
                 Arc::new(subscription_task_executor)
             ),
+            block_data_cache,
         ))
     );
 
@@ -175,7 +284,7 @@ pub fn create_full<C, P, BE, A>(
     // `YourRpcStruct` should have a reference to a client, which is needed
     // to call into the runtime.
     // `io.extend_with(YourRpcTrait::to_delegate(YourRpcStruct::new(ReferenceToClient, ...)));`
-    io
+    rpc_guard.apply(io)
 }
 
 /// Instantiate all RPC extensions for light node.
@@ -184,10 +293,12 @@ pub fn create_light<C, P, F>(deps: LightDeps<C, F, P>) -> jsonrpc_core::IoHandle
         C: Send + Sync + 'static,
         C: sp_blockchain::HeaderBackend<Block>,
         F: sc_client_api::light::Fetcher<Block> + 'static,
-        P: sp_transaction_pool::TransactionPool + 'static,
+        P: sp_transaction_pool::TransactionPool<Block = Block> + 'static,
 {
     // --- substrate ---
     use substrate_frame_rpc_system::{LightSystem, SystemApi};
+    // --- frontier ---
+    use fc_rpc::{LightEthApi, EthLightApiServer};
 
     let LightDeps {
         client,
@@ -198,7 +309,16 @@ pub fn create_light<C, P, F>(deps: LightDeps<C, F, P>) -> jsonrpc_core::IoHandle
     let mut io = jsonrpc_core::IoHandler::default();
 
     io.extend_with(SystemApi::<Hash, AccountId, Nonce>::to_delegate(
-        LightSystem::new(client, remote_blockchain, fetcher, pool),
+        LightSystem::new(client.clone(), remote_blockchain.clone(), fetcher.clone(), pool.clone()),
+    ));
+    io.extend_with(EthLightApiServer::to_delegate(
+        LightEthApi::new(
+            client,
+            remote_blockchain,
+            fetcher,
+            pool,
+            fuxi_runtime::TransactionConverter,
+        ),
     ));
 
     io
@@ -6,6 +6,7 @@
 #![warn(missing_docs)]
 pub mod pangu;
 pub mod fuxi;
+pub mod middleware;
 
 
 /// A type representing all RPC extensions.
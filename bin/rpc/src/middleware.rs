@@ -0,0 +1,389 @@
+//! Access policy of the RPC methods: allow and deny lists, rate limits,
+//! execution time budgets of the EVM calls and per method metrics.
+//!
+//! Rate limits are not per IP address. `sc_service` starts the RPC servers
+//! with its own metadata extractors, and `sc_rpc::Metadata` only holds the
+//! session of a WebSocket or IPC connection: neither the address of the peer
+//! nor the headers of HTTP requests reach the handlers, and the extractors of
+//! the HTTP and WebSocket servers don't get the peer address either. So each
+//! WebSocket or IPC session has its own rate limit bucket, and HTTP requests
+//! share a single bucket with its own rate. Limits per IP address are left to
+//! a reverse proxy in front of the node.
+
+use std::{
+    collections::HashMap,
+    path::Path,
+    sync::{Arc, Mutex, atomic::{AtomicUsize, Ordering}},
+    time::{Duration, Instant},
+};
+
+use futures::{FutureExt, TryFutureExt};
+use jsonrpc_core::{
+    futures::Future, BoxFuture, Error, ErrorCode, MetaIoHandler, Params, RemoteProcedure, RpcMethod,
+    Value,
+};
+use jsonrpc_pubsub::PubSubMetadata;
+use prometheus_endpoint::{register, CounterVec, Opts, PrometheusError, Registry, U64};
+use sc_rpc::Metadata;
+
+use crate::RpcExtension;
+
+/// Methods executing EVM code, which are bounded by the EVM time budget.
+pub const EVM_METHODS: &[&str] = &[
+    "eth_call",
+    "eth_estimateGas",
+    "trace_block",
+    "trace_transaction",
+    "trace_filter",
+];
+
+/// Error code of the EVM calls which exceeded their time budget.
+const TIMED_OUT: i64 = -32006;
+/// Error code of the EVM calls refused because too many are running.
+const BUSY: i64 = -32007;
+/// Error code of the calls refused by the rate limit, distinct from the
+/// `-32005` of the queries exceeding a limit of the node.
+const RATE_LIMITED: i64 = -32008;
+
+/// Access policy of the RPC methods.
+#[derive(Clone, Debug)]
+pub struct RpcGuardConfig {
+    /// Methods which are served, all of them when empty. A trailing `*`
+    /// matches any method with the given prefix, e.g. `eth_*`.
+    pub allow: Vec<String>,
+    /// Methods which are not served, taking precedence over `allow`.
+    pub deny: Vec<String>,
+    /// Calls per second allowed to a WebSocket or IPC connection, unlimited
+    /// when `None`.
+    pub rate_limit: Option<u32>,
+    /// Calls per second allowed to all the HTTP requests together, unlimited
+    /// when `None`.
+    pub http_rate_limit: Option<u32>,
+    /// Calls a connection, or the HTTP requests, can make at once before
+    /// being rate limited.
+    pub rate_burst: u32,
+    /// Time after which an EVM call is answered with an error, unlimited when `None`.
+    pub evm_time_budget: Option<Duration>,
+    /// Maximum number of EVM calls executing at once.
+    pub max_concurrent_evm_calls: usize,
+}
+
+impl Default for RpcGuardConfig {
+    fn default() -> Self {
+        Self {
+            allow: Vec::new(),
+            deny: Vec::new(),
+            rate_limit: None,
+            http_rate_limit: None,
+            rate_burst: 100,
+            evm_time_budget: None,
+            max_concurrent_evm_calls: 16,
+        }
+    }
+}
+
+impl RpcGuardConfig {
+    /// Adds the allow and deny lists of a policy file. Each line is either
+    /// empty, a `#` comment, `allow <method>` or `deny <method>`.
+    pub fn load_policy(&mut self, path: &Path) -> Result<(), String> {
+        let policy = std::fs::read_to_string(path)
+            .map_err(|e| format!("failed to read RPC policy {}: {}", path.display(), e))?;
+        for (index, line) in policy.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let mut words = line.split_whitespace();
+            match (words.next(), words.next(), words.next()) {
+                (Some("allow"), Some(method), None) => self.allow.push(method.to_string()),
+                (Some("deny"), Some(method), None) => self.deny.push(method.to_string()),
+                _ => return Err(format!(
+                    "invalid RPC policy {} at line {}: {}", path.display(), index + 1, line,
+                )),
+            }
+        }
+        Ok(())
+    }
+
+    fn matches(patterns: &[String], method: &str) -> bool {
+        patterns.iter().any(|pattern| match pattern.strip_suffix('*') {
+            Some(prefix) => method.starts_with(prefix),
+            None => pattern == method,
+        })
+    }
+
+    /// Whether a method is served under this policy.
+    pub fn is_allowed(&self, method: &str) -> bool {
+        (self.allow.is_empty() || Self::matches(&self.allow, method)) &&
+            !Self::matches(&self.deny, method)
+    }
+}
+
+struct TokenBucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    fn take(&mut self, rate: f64, burst: f64) -> bool {
+        let now = Instant::now();
+        self.tokens = (self.tokens + now.duration_since(self.updated).as_secs_f64() * rate).min(burst);
+        self.updated = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Enforces an `RpcGuardConfig` on the methods of an RPC handler.
+pub struct RpcGuard {
+    config: RpcGuardConfig,
+    /// Rate limit buckets of the connections, HTTP requests use `None`.
+    buckets: Arc<Mutex<HashMap<Option<usize>, TokenBucket>>>,
+    evm_calls: AtomicUsize,
+    calls: Option<CounterVec<U64>>,
+}
+
+impl RpcGuard {
+    /// Create a guard enforcing `config`, counting the calls by method and
+    /// outcome in `registry` if any.
+    pub fn new(config: RpcGuardConfig, registry: Option<&Registry>) -> Result<Self, PrometheusError> {
+        let calls = match registry {
+            Some(registry) => Some(register(
+                CounterVec::new(
+                    Opts::new("uniarts_rpc_calls_total", "Number of RPC calls, by method and outcome"),
+                    &["method", "outcome"],
+                )?,
+                registry,
+            )?),
+            None => None,
+        };
+        Ok(Self {
+            config,
+            buckets: Arc::new(Mutex::new(HashMap::new())),
+            evm_calls: AtomicUsize::new(0),
+            calls,
+        })
+    }
+
+    /// Wraps the methods of a handler allowed by the policy, dropping the others.
+    pub fn apply(self: &Arc<Self>, io: RpcExtension) -> RpcExtension {
+        let mut guarded = RpcExtension::default();
+        guarded.extend_with(MetaIoHandler::from(io).into_iter().filter_map(|(name, procedure)| {
+            if !self.config.is_allowed(&name) {
+                return None;
+            }
+            let procedure = match procedure {
+                RemoteProcedure::Method(method) => RemoteProcedure::Method(Arc::new(GuardedMethod {
+                    evm: EVM_METHODS.contains(&name.as_str()),
+                    name: name.clone(),
+                    method,
+                    guard: self.clone(),
+                })),
+                RemoteProcedure::Alias(target) if !self.config.is_allowed(&target) => return None,
+                procedure => procedure,
+            };
+            Some((name, procedure))
+        }));
+        guarded
+    }
+
+    fn record(&self, method: &str, outcome: &str) {
+        if let Some(calls) = &self.calls {
+            calls.with_label_values(&[method, outcome]).inc();
+        }
+    }
+
+    /// Takes a token from the bucket of the connection of a call.
+    fn acquire(&self, meta: &Metadata) -> bool {
+        let session = meta.session();
+        let key = session.as_ref().map(|session| Arc::as_ptr(session) as usize);
+        let rate = match (key, self.config.rate_limit, self.config.http_rate_limit) {
+            (Some(_), Some(rate), _) | (None, _, Some(rate)) => rate as f64,
+            _ => return true,
+        };
+        let burst = self.config.rate_burst.max(1) as f64;
+
+        let mut buckets = match self.buckets.lock() {
+            Ok(buckets) => buckets,
+            Err(_) => return true,
+        };
+        if !buckets.contains_key(&key) {
+            // Forget the bucket of a connection once it is closed.
+            if let Some(session) = session {
+                let buckets = self.buckets.clone();
+                session.on_drop(move || {
+                    if let Ok(mut buckets) = buckets.lock() {
+                        buckets.remove(&key);
+                    }
+                });
+            }
+        }
+        buckets
+            .entry(key)
+            .or_insert_with(|| TokenBucket { tokens: burst, updated: Instant::now() })
+            .take(rate, burst)
+    }
+
+    /// Executes an EVM call on its own thread, answering with an error if it
+    /// doesn't complete within `budget`. The thread keeps counting against
+    /// `max_concurrent_evm_calls` until the call completes.
+    fn call_with_budget(
+        self: &Arc<Self>,
+        method: Arc<dyn RpcMethod<Metadata>>,
+        params: Params,
+        meta: Metadata,
+        budget: Duration,
+    ) -> BoxFuture<Value> {
+        if self.evm_calls.fetch_add(1, Ordering::SeqCst) >= self.config.max_concurrent_evm_calls {
+            self.evm_calls.fetch_sub(1, Ordering::SeqCst);
+            return Box::new(jsonrpc_core::futures::future::err(
+                server_error(BUSY, "too many EVM calls in progress"),
+            ));
+        }
+
+        let (sender, receiver) = futures::channel::oneshot::channel();
+        let guard = self.clone();
+        let spawned = std::thread::Builder::new()
+            .name("rpc-evm-call".into())
+            .spawn(move || {
+                let result = method.call(params, meta).wait();
+                guard.evm_calls.fetch_sub(1, Ordering::SeqCst);
+                let _ = sender.send(result);
+            });
+        if let Err(e) = spawned {
+            self.evm_calls.fetch_sub(1, Ordering::SeqCst);
+            return Box::new(jsonrpc_core::futures::future::err(
+                server_error(BUSY, format!("failed to start EVM call: {}", e)),
+            ));
+        }
+
+        let timeout = futures_timer::Delay::new(budget);
+        Box::new(
+            futures::future::select(receiver, timeout)
+                .map(|either| match either {
+                    futures::future::Either::Left((Ok(result), _)) => result,
+                    futures::future::Either::Left((Err(_), _)) =>
+                        Err(Error::internal_error()),
+                    futures::future::Either::Right(_) =>
+                        Err(server_error(TIMED_OUT, "EVM call exceeded its time budget")),
+                })
+                .boxed()
+                .compat()
+        )
+    }
+}
+
+fn server_error<T: ToString>(code: i64, message: T) -> Error {
+    Error {
+        code: ErrorCode::ServerError(code),
+        message: message.to_string(),
+        data: None,
+    }
+}
+
+fn outcome(result: &Result<Value, Error>) -> &'static str {
+    match result {
+        Ok(_) => "success",
+        Err(err) if err.code == ErrorCode::ServerError(TIMED_OUT) => "timeout",
+        Err(err) if err.code == ErrorCode::ServerError(BUSY) => "busy",
+        Err(_) => "error",
+    }
+}
+
+struct GuardedMethod {
+    name: String,
+    method: Arc<dyn RpcMethod<Metadata>>,
+    guard: Arc<RpcGuard>,
+    evm: bool,
+}
+
+impl RpcMethod<Metadata> for GuardedMethod {
+    fn call(&self, params: Params, meta: Metadata) -> BoxFuture<Value> {
+        if !self.guard.acquire(&meta) {
+            self.guard.record(&self.name, "rate_limited");
+            return Box::new(jsonrpc_core::futures::future::err(
+                server_error(RATE_LIMITED, "rate limit exceeded"),
+            ));
+        }
+
+        let future = match (self.evm, self.guard.config.evm_time_budget) {
+            (true, Some(budget)) =>
+                self.guard.call_with_budget(self.method.clone(), params, meta, budget),
+            _ => self.method.call(params, meta),
+        };
+        let guard = self.guard.clone();
+        let name = self.name.clone();
+        Box::new(future.then(move |result| {
+            guard.record(&name, outcome(&result));
+            result
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(allow: &[&str], deny: &[&str]) -> RpcGuardConfig {
+        RpcGuardConfig {
+            allow: allow.iter().map(|method| method.to_string()).collect(),
+            deny: deny.iter().map(|method| method.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn allow_and_deny_lists_match_methods() {
+        let open = config(&[], &[]);
+        assert!(open.is_allowed("eth_getLogs"));
+
+        let policy = config(&["eth_*", "net_version"], &["eth_getLogs"]);
+        assert!(policy.is_allowed("eth_call"));
+        assert!(policy.is_allowed("net_version"));
+        assert!(!policy.is_allowed("net_peerCount"));
+        assert!(!policy.is_allowed("eth_getLogs"));
+        assert!(!policy.is_allowed("engine_createBlock"));
+    }
+
+    #[test]
+    fn http_requests_have_their_own_rate_limit() {
+        let guard = RpcGuard::new(RpcGuardConfig {
+            rate_limit: None,
+            http_rate_limit: Some(1),
+            rate_burst: 1,
+            ..Default::default()
+        }, None).unwrap();
+        let http = Metadata::default();
+        assert!(guard.acquire(&http));
+        assert!(!guard.acquire(&http));
+
+        let unlimited = RpcGuard::new(RpcGuardConfig {
+            rate_limit: Some(1),
+            rate_burst: 1,
+            ..Default::default()
+        }, None).unwrap();
+        assert!(unlimited.acquire(&http));
+        assert!(unlimited.acquire(&http));
+    }
+
+    #[test]
+    fn rate_limited_code_differs_from_limit_exceeded() {
+        assert_ne!(server_error(RATE_LIMITED, "").code, ErrorCode::ServerError(-32005));
+    }
+
+    #[test]
+    fn token_bucket_refills_over_time() {
+        let mut bucket = TokenBucket { tokens: 2.0, updated: Instant::now() };
+        assert!(bucket.take(1.0, 2.0));
+        assert!(bucket.take(1.0, 2.0));
+        assert!(!bucket.take(1.0, 2.0));
+
+        bucket.updated -= Duration::from_secs(1);
+        assert!(bucket.take(1.0, 2.0));
+        assert!(!bucket.take(1.0, 2.0));
+    }
+}
@@ -66,6 +66,7 @@ pub trait RuntimeEvmApiCollection:
     + sp_offchain::OffchainWorkerApi<Block>
     + sp_session::SessionKeys<Block>
     + fp_rpc::EthereumRuntimeRPCApi<Block>
+    + fp_rpc::EvmAccountApi<Block, AccountId>
     + sp_transaction_pool::runtime_api::TaggedTransactionQueue<Block>
     + frame_system_rpc_runtime_api::AccountNonceApi<Block, AccountId, Nonce>
     + pallet_transaction_payment_rpc_runtime_api::TransactionPaymentApi<Block, Balance>
@@ -87,6 +88,7 @@ impl<Api> RuntimeEvmApiCollection for Api
         + sp_offchain::OffchainWorkerApi<Block>
         + sp_session::SessionKeys<Block>
         + fp_rpc::EthereumRuntimeRPCApi<Block>
+        + fp_rpc::EvmAccountApi<Block, AccountId>
         + frame_system_rpc_runtime_api::AccountNonceApi<Block, AccountId, Nonce>
         + pallet_transaction_payment_rpc_runtime_api::TransactionPaymentApi<Block, Balance>
         + pallet_staking_rpc::StakingRuntimeApi<Block, AccountId, Balance>
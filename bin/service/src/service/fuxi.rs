@@ -10,6 +10,8 @@ pub use sc_service::{
 pub use crate::chain_spec::FuxiChainSpec;
 pub use fuxi_runtime;
 use uniarts_rpc::fuxi::FullDeps;
+pub use uniarts_rpc::fuxi::EthRpcConfig;
+pub use uniarts_rpc::middleware::RpcGuardConfig;
 
 use std::{sync::{Arc, Mutex}, time::Duration, collections::{HashMap, BTreeMap}};
 use sc_client_api::{ExecutorProvider, RemoteBackend, StateBackendFor, BlockchainEvents};
@@ -24,11 +26,25 @@ use sp_consensus::import_queue::BasicQueue;
 use sp_runtime::traits::BlakeTwo256;
 use sp_trie::PrefixedMemoryDB;
 
-use fc_rpc_core::types::{FilterPool, PendingTransactions};
+use fc_rpc_core::types::{FilterPool, FilterType, PendingTransactions};
 use fc_consensus::FrontierBlockImport;
 use fuxi_runtime::opaque::Block;
+use uniarts_primitives::BlockNumber;
 use sc_telemetry::TelemetrySpan;
 
+/// Open the Frontier mapping database next to the Substrate database, in
+/// `chains/<chain id>/frontier`. Nodes without an on-disk database keep it in memory.
+fn open_frontier_backend(config: &Configuration) -> Result<Arc<fc_db::Backend<Block>>, String> {
+    let path = config.database.path()
+        .and_then(|path| path.parent())
+        .map(|chain_dir| chain_dir.join("frontier"));
+
+    Ok(Arc::new(match path {
+        Some(path) => fc_db::Backend::open(&fc_db::DatabaseSettings { path, cache_size: 128 })?,
+        None => fc_db::Backend::new_in_memory(),
+    }))
+}
+
 // Our native executor instance.
 native_executor_instance!(
 	pub FuxiExecutor,
@@ -56,7 +72,7 @@ pub fn new_partial<RuntimeApi, Executor>(config: &mut Configuration) -> Result<s
             AuraPair>,
             sc_finality_grandpa::LinkHalf<Block, FullClient<RuntimeApi, Executor>, FullSelectChain>
         ),
-        PendingTransactions, Option<FilterPool>
+        PendingTransactions, Option<FilterPool>, Arc<fc_db::Backend<Block>>
     )
 >, ServiceError>
     where
@@ -95,9 +111,12 @@ pub fn new_partial<RuntimeApi, Executor>(config: &mut Configuration) -> Result<s
         client.clone(), &(client.clone() as Arc<_>), select_chain.clone(),
     )?;
 
+    let frontier_backend = open_frontier_backend(config)?;
+
     let frontier_block_import = FrontierBlockImport::new(
         grandpa_block_import.clone(),
         client.clone(),
+        frontier_backend.clone(),
         true
     );
 
@@ -119,12 +138,16 @@ pub fn new_partial<RuntimeApi, Executor>(config: &mut Configuration) -> Result<s
     Ok(sc_service::PartialComponents {
         client, backend, task_manager, import_queue, keystore_container, select_chain, transaction_pool,
         inherent_data_providers,
-        other: ((aura_block_import, grandpa_link), pending_transactions, filter_pool),
+        other: ((aura_block_import, grandpa_link), pending_transactions, filter_pool, frontier_backend),
     })
 }
 
 /// Builds a new service for a full client.
-pub fn new_full<RuntimeApi, Executor>(mut config: Configuration, enable_dev_signer: bool,) -> Result<(TaskManager, Arc<FullClient<RuntimeApi, Executor>>), ServiceError>
+pub fn new_full<RuntimeApi, Executor>(
+    mut config: Configuration,
+    enable_dev_signer: bool,
+    eth_config: EthRpcConfig,
+) -> Result<(TaskManager, Arc<FullClient<RuntimeApi, Executor>>), ServiceError>
     where
         Executor: 'static + NativeExecutionDispatch,
         RuntimeApi: 'static + Send + Sync + ConstructRuntimeApi<Block, FullClient<RuntimeApi, Executor>>,
@@ -139,7 +162,7 @@ pub fn new_full<RuntimeApi, Executor>(mut config: Configuration, enable_dev_sign
         select_chain,
         transaction_pool,
         inherent_data_providers,
-        other: ((block_import, grandpa_link), pending_transactions, filter_pool),
+        other: ((block_import, grandpa_link), pending_transactions, filter_pool, frontier_backend),
     } = new_partial(&mut config)?;
 
     if let Some(url) = &config.keystore_remote {
@@ -186,12 +209,58 @@ pub fn new_full<RuntimeApi, Executor>(mut config: Configuration, enable_dev_sign
     let is_authority = role.is_authority();
     let subscription_task_executor = sc_rpc::SubscriptionTaskExecutor::new(task_manager.spawn_handle());
 
+    // Ethereum accounts of the node's keystore, for the signing RPCs.
+    let keystore_signer = keystore_container.local_keystore()
+        .filter(|_| eth_config.enable_keystore_signer)
+        .map(|keystore| {
+            let password = match &config.keystore {
+                KeystoreConfig::Path { password, .. } => password.clone(),
+                KeystoreConfig::InMemory => None,
+            };
+            fc_rpc::EthKeystoreSigner::new(keystore, password)
+        });
+
+    let block_data_cache = Arc::new(fc_rpc::EthBlockDataCache::new(
+        frontier_backend.clone(),
+        eth_config.block_cache_size,
+        prometheus_registry.as_ref(),
+    ).map_err(|e| ServiceError::Other(format!("failed to register RPC cache metrics: {:?}", e)))?);
+
+    let rpc_guard = Arc::new(uniarts_rpc::middleware::RpcGuard::new(
+        eth_config.rpc_guard.clone(),
+        prometheus_registry.as_ref(),
+    ).map_err(|e| ServiceError::Other(format!("failed to register RPC metrics: {:?}", e)))?);
+    if eth_config.rpc_guard.rate_limit.is_some() || eth_config.rpc_guard.http_rate_limit.is_some() {
+        log::warn!(
+            "RPC rate limits apply per WebSocket or IPC connection and to all the HTTP requests \
+            together, not per IP address: limit clients by IP address with a reverse proxy",
+        );
+    }
+
+    // Network and sync status of the Ethereum RPC, refreshed from the network worker.
+    let network_status = Arc::new(fc_rpc::EthNetworkStatus::new());
+    task_manager.spawn_handle().spawn(
+        "frontier-network-status",
+        network_status.clone().status_task(
+            client.clone(),
+            network_status_sinks.status_stream(Duration::from_secs(1)),
+        ),
+    );
+    task_manager.spawn_handle().spawn(
+        "frontier-network-state",
+        network_status.clone().state_task(
+            network_status_sinks.state_stream(Duration::from_secs(5)),
+        ),
+    );
+
     let rpc_extensions_builder = {
         let client = client.clone();
         let pool = transaction_pool.clone();
         let network = network.clone();
         let pending = pending_transactions.clone();
         let filter_pool = filter_pool.clone();
+        let frontier_backend = frontier_backend.clone();
+        let eth_config = eth_config.clone();
 
         Box::new(move |deny_unsafe, _| {
             let deps = FullDeps {
@@ -201,9 +270,15 @@ pub fn new_full<RuntimeApi, Executor>(mut config: Configuration, enable_dev_sign
                 deny_unsafe,
                 is_authority,
                 enable_dev_signer,
+                keystore_signer: keystore_signer.clone(),
                 network: network.clone(),
+                network_status: network_status.clone(),
                 pending_transactions: pending.clone(),
                 filter_pool: filter_pool.clone(),
+                frontier_backend: frontier_backend.clone(),
+                block_data_cache: block_data_cache.clone(),
+                eth_config: eth_config.clone(),
+                rpc_guard: rpc_guard.clone(),
                 command_sink: Some(command_sink.clone())
             };
 
@@ -231,21 +306,118 @@ pub fn new_full<RuntimeApi, Executor>(mut config: Configuration, enable_dev_sign
         },
     )?;
 
+    // Spawn Frontier pending transaction filters task: add the Ethereum
+    // transactions entering the pool to the `eth_newPendingTransactionFilter` filters.
+    if let Some(filter_pool) = filter_pool.clone() {
+        use futures::StreamExt;
+        let pool = transaction_pool.clone();
+        let pool_client = client.clone();
+        let max_filter_results = eth_config.max_filter_results;
+        task_manager.spawn_handle().spawn(
+            "frontier-pending-transaction-filters",
+            transaction_pool.import_notification_stream().for_each(move |hash| {
+                let has_pending_filters = filter_pool.lock()
+                    .map(|locked| locked.values().any(|item| {
+                        matches!(item.filter_type, FilterType::PendingTransaction(_))
+                    }))
+                    .unwrap_or(false);
+                // The runtime call finding the transaction hashes is made
+                // without holding the filter pool lock.
+                if has_pending_filters {
+                    let transaction_hashes = fc_rpc::pool_transaction_hashes(
+                        pool_client.as_ref(),
+                        pool.as_ref(),
+                        &hash,
+                    );
+                    if let Ok(locked) = &mut filter_pool.lock() {
+                        // Hashes past the result cap are dropped until the filter is polled.
+                        for item in locked.values_mut() {
+                            if let FilterType::PendingTransaction(hashes) = &mut item.filter_type {
+                                let room = max_filter_results.saturating_sub(hashes.len());
+                                hashes.extend(transaction_hashes.iter().take(room));
+                            }
+                        }
+                    }
+                }
+                futures::future::ready(())
+            })
+        );
+    }
+
     // Spawn Frontier EthFilterApi maintenance task.
     if filter_pool.is_some() {
         use futures::StreamExt;
-        // Each filter is allowed to stay in the pool for 100 blocks.
-        const FILTER_RETAIN_THRESHOLD: u64 = 100;
+        // Filters are uninstalled once they haven't been polled for `filter_lifetime`.
+        let filter_lifetime = eth_config.filter_lifetime;
         task_manager.spawn_essential_handle().spawn(
             "frontier-filter-pool",
-            client.import_notification_stream().for_each(move |notification| {
+            client.import_notification_stream().for_each(move |_| {
                 if let Ok(locked) = &mut filter_pool.clone().unwrap().lock() {
-                    let imported_number: u64 = notification.header.number as u64;
-                    for (k, v) in locked.clone().iter() {
-                        let lifespan_limit = v.at_block + FILTER_RETAIN_THRESHOLD;
-                        if lifespan_limit <= imported_number {
-                            locked.remove(&k);
-                        }
+                    locked.retain(|_, item| item.last_activity.elapsed() < filter_lifetime);
+                }
+                futures::future::ready(())
+            })
+        );
+    }
+
+    // Spawn Frontier finality task: prune the mappings of retracted forks,
+    // then index the logs of the finalized blocks. Only finalized blocks are
+    // indexed, so the index never needs to be rolled back on a reorg. Both are
+    // database bound, so the task runs on a blocking thread.
+    {
+        use futures::StreamExt;
+        // Number of blocks pruned or indexed per database write.
+        const FINALITY_BATCH: u64 = 1000;
+        let finality_client = client.clone();
+        task_manager.spawn_handle().spawn_blocking(
+            "frontier-finality",
+            client.finality_notification_stream().for_each(move |notification| {
+                let finalized_number: u64 = notification.header.number as u64;
+                loop {
+                    match fc_consensus::prune_finalized(
+                        finality_client.as_ref(),
+                        frontier_backend.as_ref(),
+                        notification.header.number,
+                        FINALITY_BATCH,
+                    ) {
+                        Ok(Some(pruned)) if pruned < finalized_number => (),
+                        Ok(_) => break,
+                        Err(e) => {
+                            log::warn!(target: "frontier", "Failed to prune Frontier mappings: {:?}", e);
+                            break
+                        },
+                    }
+                }
+                loop {
+                    let tip = frontier_backend.log_index_tip().unwrap_or_default();
+                    if tip.map_or(false, |tip| tip >= finalized_number) {
+                        break
+                    }
+                    match frontier_backend.index_logs(finalized_number, FINALITY_BATCH) {
+                        Ok(new_tip) if new_tip != tip => (),
+                        Ok(_) => {
+                            // The next block is missing from the mapping database,
+                            // e.g. imported before it existed: write it from the
+                            // block state, or skip it if the state is pruned.
+                            let next = tip.map_or(0, |tip| tip + 1);
+                            let res = match fc_consensus::backfill_block(
+                                finality_client.as_ref(),
+                                frontier_backend.as_ref(),
+                                next,
+                            ) {
+                                Ok(true) => Ok(()),
+                                Ok(false) => frontier_backend.skip_log_index(next).map(|_| ()),
+                                Err(e) => Err(format!("{:?}", e)),
+                            };
+                            if let Err(e) = res {
+                                log::warn!(target: "frontier", "Failed to index logs of block {}: {}", next, e);
+                                break
+                            }
+                        },
+                        Err(e) => {
+                            log::warn!(target: "frontier", "Failed to index logs: {}", e);
+                            break
+                        },
                     }
                 }
                 futures::future::ready(())
@@ -499,10 +671,61 @@ pub fn new_chain_ops<Runtime, Dispatch>(
     Ok((client, backend, import_queue, task_manager))
 }
 
+/// Rewrite the Frontier block and transaction mappings of the canonical
+/// blocks `from..=to` from their digests, and their Ethereum data in the
+/// mapping database from the block state. `to` defaults to the best block.
+#[cfg(feature = "full-node")]
+pub fn frontier_reindex<RuntimeApi, Executor>(
+    config: &mut Configuration,
+    from: BlockNumber,
+    to: Option<BlockNumber>,
+) -> Result<(), ServiceError>
+    where
+        Executor: 'static + NativeExecutionDispatch,
+        RuntimeApi: 'static + Send + Sync + ConstructRuntimeApi<Block, FullClient<RuntimeApi, Executor>>,
+        RuntimeApi::RuntimeApi: RuntimeEvmApiCollection<StateBackend = StateBackendFor<FullBackend, Block>>,
+{
+    use sp_blockchain::HeaderBackend;
+    // Progress is reported every this many blocks.
+    const PROGRESS_INTERVAL: BlockNumber = 1000;
+
+    config.keystore = KeystoreConfig::InMemory;
+
+    let PartialComponents {
+        client,
+        task_manager: _task_manager,
+        other: (_, _, _, frontier_backend),
+        ..
+    } = new_partial::<RuntimeApi, Executor>(config)?;
+
+    let best_number = client.info().best_number;
+    let to = to.unwrap_or(best_number).min(best_number);
+    log::info!("Re-indexing Frontier mappings of blocks #{}..=#{}", from, to);
+
+    let mut reindexed = 0u64;
+    if from == 0 && fc_consensus::reindex_genesis(client.as_ref(), frontier_backend.as_ref())? {
+        reindexed += 1;
+    }
+    for number in from.max(1)..=to {
+        let hash = client.hash(number)?
+            .ok_or_else(|| ServiceError::Other(format!("Block #{} not found", number)))?;
+        if fc_consensus::reindex_block(client.as_ref(), frontier_backend.as_ref(), hash)? {
+            reindexed += 1;
+        }
+        if number % PROGRESS_INTERVAL == 0 {
+            log::info!("Re-indexed Frontier mappings up to #{} of #{}", number, to);
+        }
+    }
+
+    log::info!("Re-indexed {} blocks with Frontier mappings", reindexed);
+    Ok(())
+}
+
 /// Create a new Uniarts service for a full node.
 #[cfg(feature = "full-node")]
 pub fn fuxi_new_full(
     config: Configuration,
+    eth_config: EthRpcConfig,
 ) -> Result<
     (
         TaskManager,
@@ -510,7 +733,7 @@ pub fn fuxi_new_full(
     ),
     ServiceError,
 > {
-    let (components, client) = new_full::<fuxi_runtime::RuntimeApi, FuxiExecutor>(config, false)?;
+    let (components, client) = new_full::<fuxi_runtime::RuntimeApi, FuxiExecutor>(config, false, eth_config)?;
 
     Ok((components, client))
 }
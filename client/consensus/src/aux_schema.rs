@@ -16,6 +16,7 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
+use std::collections::HashMap;
 use codec::{Encode, Decode};
 use sp_core::H256;
 use sp_runtime::traits::Block as BlockT;
@@ -106,3 +107,104 @@ pub fn write_transaction_metadata<F, R, Backend: AuxStore>(
 
 	Ok(write_aux(&[(&key, &data.encode()[..])]))
 }
+
+/// An Ethereum block imported at some height, kept until that height is finalized.
+#[derive(Encode, Decode, Clone, Debug, PartialEq)]
+pub struct PendingBlock<Hash> {
+	pub substrate_hash: Hash,
+	pub ethereum_hash: H256,
+	pub transaction_hashes: Vec<H256>,
+}
+
+/// Map a Substrate block number into the Ethereum blocks imported at that height.
+pub fn pending_blocks_key(number: u64) -> Vec<u8> {
+	let mut ret = b"ethereum_pending_blocks:".to_vec();
+	ret.append(&mut number.encode());
+	ret
+}
+
+/// Given a Substrate block number, get the Ethereum blocks imported at that height and not pruned yet.
+pub fn load_pending_blocks<Hash: Decode, B: AuxStore>(
+	backend: &B,
+	number: u64,
+) -> ClientResult<Option<Vec<PendingBlock<Hash>>>> {
+	load_decode(backend, &pending_blocks_key(number))
+}
+
+/// Update Aux pending blocks.
+pub fn write_pending_block<Hash: Encode + Decode, F, R, Backend: AuxStore>(
+	client: &Backend,
+	number: u64,
+	block: PendingBlock<Hash>,
+	write_aux: F,
+) -> ClientResult<R> where
+	F: FnOnce(&[(&[u8], &[u8])]) -> R,
+{
+	let key = pending_blocks_key(number);
+
+	let mut data: Vec<PendingBlock<Hash>> = match load_decode(client, &key) {
+		Ok(Some(blocks)) => blocks,
+		Ok(None) => Vec::new(),
+		Err(e) => return Err(e)
+	};
+	data.push(block);
+
+	Ok(write_aux(&[(&key, &data.encode()[..])]))
+}
+
+/// Last Substrate block number whose mappings have been pruned.
+pub const PRUNED_NUMBER_KEY: &[u8] = b"ethereum_pruned_number";
+
+/// Get the last Substrate block number whose mappings have been pruned.
+pub fn load_pruned_number<B: AuxStore>(backend: &B) -> ClientResult<Option<u64>> {
+	load_decode(backend, PRUNED_NUMBER_KEY)
+}
+
+/// Aux changes applied in a single write.
+pub(crate) struct AuxChanges<'a, C> {
+	client: &'a C,
+	changes: HashMap<Vec<u8>, Option<Vec<u8>>>,
+}
+
+impl<'a, C: AuxStore> AuxChanges<'a, C> {
+	pub fn new(client: &'a C) -> Self {
+		Self { client, changes: HashMap::new() }
+	}
+
+	pub fn get<T: Decode>(&self, key: &[u8]) -> ClientResult<Option<T>> {
+		match self.changes.get(key) {
+			Some(Some(value)) => T::decode(&mut &value[..]).map(Some).map_err(|e| {
+				ClientError::Backend(format!("Frontier DB is corrupted. Decode error: {}", e))
+			}),
+			Some(None) => Ok(None),
+			None => load_decode(self.client, key),
+		}
+	}
+
+	pub fn set<T: Encode>(&mut self, key: Vec<u8>, value: &T) {
+		self.changes.insert(key, Some(value.encode()));
+	}
+
+	pub fn delete(&mut self, key: Vec<u8>) {
+		self.changes.insert(key, None);
+	}
+
+	pub fn set_or_delete<T: Encode>(&mut self, key: Vec<u8>, values: Vec<T>) {
+		if values.is_empty() {
+			self.delete(key);
+		} else {
+			self.set(key, &values);
+		}
+	}
+
+	pub fn commit(self) -> ClientResult<()> {
+		let insert: Vec<(&[u8], &[u8])> = self.changes.iter()
+			.filter_map(|(key, value)| value.as_ref().map(|value| (&key[..], &value[..])))
+			.collect();
+		let delete: Vec<&[u8]> = self.changes.iter()
+			.filter(|(_, value)| value.is_none())
+			.map(|(key, _)| &key[..])
+			.collect();
+		self.client.insert_aux(insert.iter(), delete.iter())
+	}
+}
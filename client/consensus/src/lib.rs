@@ -17,8 +17,14 @@
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
 mod aux_schema;
+mod pruning;
+mod reindex;
+#[cfg(test)]
+mod test_utils;
 
 pub use crate::aux_schema::{load_block_hash, load_transaction_metadata};
+pub use crate::pruning::{prune_finalized, compact};
+pub use crate::reindex::{backfill_block, reindex_block, reindex_genesis};
 
 use std::sync::Arc;
 use std::collections::HashMap;
@@ -29,7 +35,8 @@ use sc_client_api::{BlockOf, backend::AuxStore};
 use sp_blockchain::{HeaderBackend, ProvideCache, well_known_cache_keys::Id as CacheKeyId};
 use sp_block_builder::BlockBuilder as BlockBuilderApi;
 use sp_runtime::generic::OpaqueDigestItemId;
-use sp_runtime::traits::{Block as BlockT, Header as HeaderT, One, Zero};
+use sp_runtime::traits::{Block as BlockT, Header as HeaderT, One, Zero, UniqueSaturatedInto};
+use sp_core::H256;
 use sp_api::{ProvideRuntimeApi, BlockId};
 use sp_consensus::{
 	BlockImportParams, Error as ConsensusError, BlockImport,
@@ -63,6 +70,7 @@ impl std::convert::From<Error> for ConsensusError {
 pub struct FrontierBlockImport<B: BlockT, I, C> {
 	inner: I,
 	client: Arc<C>,
+	backend: Arc<fc_db::Backend<B>>,
 	enabled: bool,
 	_marker: PhantomData<B>,
 }
@@ -72,6 +80,7 @@ impl<Block: BlockT, I: Clone + BlockImport<Block>, C> Clone for FrontierBlockImp
 		FrontierBlockImport {
 			inner: self.inner.clone(),
 			client: self.client.clone(),
+			backend: self.backend.clone(),
 			enabled: self.enabled,
 			_marker: PhantomData,
 		}
@@ -89,11 +98,13 @@ impl<B, I, C> FrontierBlockImport<B, I, C> where
 	pub fn new(
 		inner: I,
 		client: Arc<C>,
+		backend: Arc<fc_db::Backend<B>>,
 		enabled: bool,
 	) -> Self {
 		Self {
 			inner,
 			client,
+			backend,
 			enabled,
 			_marker: PhantomData,
 		}
@@ -132,26 +143,24 @@ impl<B, I, C> BlockImport<B> for FrontierBlockImport<B, I, C> where
 		}
 
 		let client = self.client.clone();
+		let mut ethereum_hash = None;
+		let hash = block.post_hash();
 
 		if self.enabled {
 			let log = find_frontier_log::<B>(&block.header)?;
-			let hash = block.post_hash();
 			match log {
 				ConsensusLog::EndBlock {
 					block_hash, transaction_hashes,
 				} => {
-					let res = aux_schema::write_block_hash(client.as_ref(), block_hash, hash, insert_closure!());
-					if res.is_err() { trace!(target: "frontier-consensus", "{:?}", res); }
-
-					for (index, transaction_hash) in transaction_hashes.into_iter().enumerate() {
-						let res = aux_schema::write_transaction_metadata(
-							client.as_ref(),
-							transaction_hash,
-							(block_hash, index as u32),
-							insert_closure!(),
-						);
-						if res.is_err() { trace!(target: "frontier-consensus", "{:?}", res); }
-					}
+					ethereum_hash = Some(block_hash);
+					write_ethereum_block_aux::<B, _, _>(
+						client.as_ref(),
+						(*block.header.number()).unique_saturated_into(),
+						hash,
+						block_hash,
+						transaction_hashes,
+						insert_closure!(),
+					);
 				},
 			}
 			// On importing block 1 we also map the genesis block in the auxiliary.
@@ -160,22 +169,134 @@ impl<B, I, C> BlockImport<B> for FrontierBlockImport<B, I, C> where
 				if let Ok(Some(header)) = client.header(id) {
 					let block = self.client.runtime_api().current_block(&id)
 						.map_err(|_| Error::RuntimeApiCallFailed)?;
-					let block_hash = block.unwrap().header.hash();
-					let res = aux_schema::write_block_hash(
-						client.as_ref(),
-						block_hash,
-						header.hash(),
-						insert_closure!()
-					);
-					if res.is_err() { trace!(target: "frontier-consensus", "{:?}", res); }
+					if let Some(block) = block {
+						let block_hash = block.header.hash();
+						let res = aux_schema::write_block_hash(
+							client.as_ref(),
+							block_hash,
+							header.hash(),
+							insert_closure!()
+						);
+						if res.is_err() { trace!(target: "frontier-consensus", "{:?}", res); }
+
+						let res = self.backend.write_block(header.hash(), block_hash, &block, &[], &[], true);
+						if res.is_err() { warn!(target: "frontier-consensus", "{:?}", res); }
+					}
 				}
 			}
 		}
 
-		self.inner.import_block(block, new_cache).map_err(Into::into)
+		let parent_hash = *block.header.parent_hash();
+		let result = self.inner.import_block(block, new_cache).map_err(Into::into)?;
+
+		// The Ethereum data is only readable from the block state, which exists
+		// once the inner import has completed.
+		if let (ImportResult::Imported(aux), Some(ethereum_hash)) = (&result, ethereum_hash) {
+			match client.runtime_api().current_all(&BlockId::Hash(hash)) {
+				Ok((Some(block), receipts, statuses)) => {
+					let res = self.backend.write_block(
+						hash,
+						ethereum_hash,
+						&block,
+						&receipts.unwrap_or_default(),
+						&statuses.unwrap_or_default(),
+						aux.is_new_best,
+					);
+					if res.is_err() { warn!(target: "frontier-consensus", "{:?}", res); }
+
+					if aux.is_new_best {
+						let res = set_best_branch(
+							client.as_ref(),
+							self.backend.as_ref(),
+							block.header.number.as_u64(),
+							parent_hash,
+						);
+						if res.is_err() { warn!(target: "frontier-consensus", "{:?}", res); }
+					}
+				},
+				Ok((None, _, _)) => (),
+				Err(e) => warn!(target: "frontier-consensus", "Failed to read Ethereum block {:?}: {:?}", hash, e),
+			}
+		}
+
+		Ok(result)
 	}
 }
 
+/// Point the number mappings at the branch of a new best block at height
+/// `number`: its ancestors from `parent_hash` down to the branch of the
+/// previous best block, and no block above `number`.
+fn set_best_branch<B, C>(
+	client: &C,
+	backend: &fc_db::Backend<B>,
+	number: u64,
+	parent_hash: B::Hash,
+) -> Result<(), String> where
+	B: BlockT,
+	C: HeaderBackend<B>,
+{
+	backend.remove_block_hashes_above(number)?;
+
+	let mut hash = parent_hash;
+	loop {
+		let header = match client.header(BlockId::Hash(hash)).map_err(|e| format!("{:?}", e))? {
+			Some(header) => header,
+			None => return Ok(()),
+		};
+		let ethereum_hash = match backend.ethereum_hash(&hash)? {
+			Some(ethereum_hash) => ethereum_hash,
+			// Blocks imported before the mapping database existed.
+			None => return Ok(()),
+		};
+		let number = (*header.number()).unique_saturated_into();
+		if backend.block_hash(number)? == Some(ethereum_hash) {
+			return Ok(())
+		}
+		backend.set_block_hash(number, ethereum_hash)?;
+
+		if header.number().is_zero() {
+			return Ok(())
+		}
+		hash = *header.parent_hash();
+	}
+}
+
+/// Write the aux mappings of an imported Ethereum block, and record it as
+/// pending until its height is finalized.
+fn write_ethereum_block_aux<B, C, F>(
+	client: &C,
+	number: u64,
+	hash: B::Hash,
+	block_hash: H256,
+	transaction_hashes: Vec<H256>,
+	mut write_aux: F,
+) where
+	B: BlockT,
+	C: AuxStore,
+	F: FnMut(&[(&[u8], &[u8])]),
+{
+	let res = aux_schema::write_block_hash(client, block_hash, hash, &mut write_aux);
+	if res.is_err() { trace!(target: "frontier-consensus", "{:?}", res); }
+
+	for (index, transaction_hash) in transaction_hashes.iter().enumerate() {
+		let res = aux_schema::write_transaction_metadata(
+			client,
+			*transaction_hash,
+			(block_hash, index as u32),
+			&mut write_aux,
+		);
+		if res.is_err() { trace!(target: "frontier-consensus", "{:?}", res); }
+	}
+
+	let res = aux_schema::write_pending_block(
+		client,
+		number,
+		aux_schema::PendingBlock { substrate_hash: hash, ethereum_hash: block_hash, transaction_hashes },
+		&mut write_aux,
+	);
+	if res.is_err() { trace!(target: "frontier-consensus", "{:?}", res); }
+}
+
 fn find_frontier_log<B: BlockT>(
 	header: &B::Header,
 ) -> Result<ConsensusLog, Error> {
@@ -193,3 +314,42 @@ fn find_frontier_log<B: BlockT>(
 
 	Ok(frontier_log.ok_or(Error::NoPostRuntimeLog)?)
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::test_utils::{Block, TestChain};
+
+	#[test]
+	fn reorg_points_number_mappings_at_new_branch() {
+		let backend = fc_db::Backend::<Block>::new_in_memory();
+		let mut chain = TestChain::new();
+		let genesis = chain.canonical[&0];
+		let (eth_a1, eth_a2, eth_a3) = (H256::repeat_byte(0xa1), H256::repeat_byte(0xa2), H256::repeat_byte(0xa3));
+		let (eth_b1, eth_b2) = (H256::repeat_byte(0xb1), H256::repeat_byte(0xb2));
+
+		let a1 = chain.import(1, genesis, eth_a1, Vec::new(), &backend);
+		let a2 = chain.import(2, a1, eth_a2, Vec::new(), &backend);
+		chain.import(3, a2, eth_a3, Vec::new(), &backend);
+
+		// B1 and B2 are imported as forks, then B2 becomes the best block.
+		let b1 = chain.import(1, genesis, eth_b1, Vec::new(), &backend);
+		backend.set_block_hash(1, eth_a1).unwrap();
+		chain.import(2, b1, eth_b2, Vec::new(), &backend);
+		set_best_branch(&chain, &backend, 2, b1).unwrap();
+
+		assert_eq!(backend.block_hash(1).unwrap(), Some(eth_b1));
+		assert_eq!(backend.block_hash(2).unwrap(), Some(eth_b2));
+		assert_eq!(backend.block_hash(3).unwrap(), None);
+	}
+
+	#[test]
+	fn best_branch_stops_at_unmapped_blocks() {
+		let backend = fc_db::Backend::<Block>::new_in_memory();
+		let chain = TestChain::new();
+		let genesis = chain.canonical[&0];
+
+		set_best_branch(&chain, &backend, 1, genesis).unwrap();
+		assert_eq!(backend.block_hash(0).unwrap(), None);
+	}
+}
@@ -0,0 +1,273 @@
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+// This file is part of Frontier.
+//
+// Copyright (c) 2020 Parity Technologies (UK) Ltd.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Pruning of the Frontier mappings on finality.
+//!
+//! Every Ethereum block imported is recorded as pending under its Substrate
+//! height. Once a height is finalized, the mappings of its canonical block
+//! only keep the canonical entries, and the blocks of the retracted forks are
+//! removed from the aux store and from the mapping database. Heights are
+//! pruned in order from the last pruned one, so a database written before
+//! pruning existed is compacted from genesis on the first run.
+
+use sp_core::H256;
+use sp_api::BlockId;
+use sp_runtime::traits::{Block as BlockT, NumberFor, UniqueSaturatedInto};
+use sp_blockchain::{HeaderBackend, Result as ClientResult, Error as ClientError};
+use sc_client_api::backend::AuxStore;
+use fp_consensus::ConsensusLog;
+use log::warn;
+use crate::aux_schema::{
+	self, AuxChanges, PendingBlock, PRUNED_NUMBER_KEY, block_hash_key, pending_blocks_key,
+	transaction_metadata_key,
+};
+
+/// Prune the finalized heights following the last pruned one, up to
+/// `finalized` and at most `max_blocks` of them. Returns the last pruned height.
+pub fn prune_finalized<B, C>(
+	client: &C,
+	backend: &fc_db::Backend<B>,
+	finalized: NumberFor<B>,
+	max_blocks: u64,
+) -> ClientResult<Option<u64>> where
+	B: BlockT,
+	C: HeaderBackend<B> + AuxStore,
+{
+	let finalized: u64 = finalized.unique_saturated_into();
+	let pruned = aux_schema::load_pruned_number(client)?;
+	let start = pruned.map(|number| number + 1).unwrap_or(0);
+	if start > finalized {
+		return Ok(pruned)
+	}
+
+	let end = finalized.min(start.saturating_add(max_blocks).saturating_sub(1));
+	for number in start..=end {
+		prune_height(client, backend, number)?;
+	}
+	Ok(Some(end))
+}
+
+/// Prune every finalized height again, from genesis.
+pub fn compact<B, C>(client: &C, backend: &fc_db::Backend<B>) -> ClientResult<()> where
+	B: BlockT,
+	C: HeaderBackend<B> + AuxStore,
+{
+	client.insert_aux(&[], &[PRUNED_NUMBER_KEY])?;
+	prune_finalized(client, backend, client.info().finalized_number, u64::max_value())?;
+	Ok(())
+}
+
+fn prune_height<B, C>(
+	client: &C,
+	backend: &fc_db::Backend<B>,
+	number: u64,
+) -> ClientResult<()> where
+	B: BlockT,
+	C: HeaderBackend<B> + AuxStore,
+{
+	let mut changes = AuxChanges::new(client);
+
+	// A finalized height missing from the client, e.g. after a warp sync,
+	// cannot be resolved later either: drop its pending blocks and move on.
+	let canonical_hash = match client.hash(number.unique_saturated_into())? {
+		Some(hash) => hash,
+		None => {
+			warn!(target: "frontier-consensus", "Finalized block #{} not found, skipping its pruning", number);
+			changes.delete(pending_blocks_key(number));
+			changes.set(PRUNED_NUMBER_KEY.to_vec(), &number);
+			return changes.commit()
+		},
+	};
+	// Genesis has no Frontier log, its mapping is written on importing block 1.
+	let canonical = match client.header(BlockId::Hash(canonical_hash))? {
+		Some(header) => match crate::find_frontier_log::<B>(&header) {
+			Ok(ConsensusLog::EndBlock { block_hash, transaction_hashes }) =>
+				Some((block_hash, transaction_hashes)),
+			Err(_) => None,
+		},
+		None => None,
+	};
+	let canonical_ethereum_hash = canonical.as_ref().map(|(hash, _)| *hash);
+
+	let pending: Vec<PendingBlock<B::Hash>> =
+		aux_schema::load_pending_blocks(client, number)?.unwrap_or_default();
+	for block in pending {
+		if block.substrate_hash == canonical_hash || Some(block.ethereum_hash) == canonical_ethereum_hash {
+			continue
+		}
+
+		let key = block_hash_key(block.ethereum_hash);
+		let mut hashes: Vec<B::Hash> = changes.get(&key)?.unwrap_or_default();
+		hashes.retain(|hash| *hash != block.substrate_hash);
+		let retracted = hashes.is_empty();
+		changes.set_or_delete(key, hashes);
+
+		for transaction_hash in block.transaction_hashes {
+			let key = transaction_metadata_key(transaction_hash);
+			let mut metadata: Vec<(H256, u32)> = changes.get(&key)?.unwrap_or_default();
+			metadata.retain(|(hash, _)| *hash != block.ethereum_hash);
+			changes.set_or_delete(key, metadata);
+		}
+
+		if retracted {
+			backend.remove_block(block.substrate_hash, block.ethereum_hash)
+				.map_err(ClientError::Backend)?;
+		}
+	}
+
+	if let Some((ethereum_hash, transaction_hashes)) = canonical {
+		changes.set(block_hash_key(ethereum_hash), &vec![canonical_hash]);
+		for transaction_hash in transaction_hashes {
+			let key = transaction_metadata_key(transaction_hash);
+			let mut metadata: Vec<(H256, u32)> = changes.get(&key)?.unwrap_or_default();
+			metadata.retain(|(hash, _)| *hash == ethereum_hash);
+			metadata.dedup();
+			changes.set_or_delete(key, metadata);
+		}
+		backend.set_block_hash(number, ethereum_hash).map_err(ClientError::Backend)?;
+	}
+
+	changes.delete(pending_blocks_key(number));
+	changes.set(PRUNED_NUMBER_KEY.to_vec(), &number);
+	changes.commit()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::{collections::HashMap, sync::Arc};
+	use codec::Encode;
+	use sp_consensus::{BlockImport, ImportResult};
+	use crate::FrontierBlockImport;
+	use crate::aux_schema::{load_block_hash, load_transaction_metadata, load_pending_blocks};
+	use crate::test_utils::{Block, TestChain, TestImport, fork_ethereum_block, import_params};
+
+	#[test]
+	fn finality_prunes_retracted_fork() {
+		let backend = Arc::new(fc_db::Backend::<Block>::new_in_memory());
+		let mut chain = TestChain::new();
+		let genesis = chain.canonical[&0];
+		let (block_a1, block_b1, block_a2) =
+			(fork_ethereum_block(1, 0xa), fork_ethereum_block(1, 0xb), fork_ethereum_block(2, 0xa));
+		let (eth_a1, eth_b1, eth_a2) =
+			(block_a1.header.hash(), block_b1.header.hash(), block_a2.header.hash());
+		let (tx1, tx2) = (H256::repeat_byte(1), H256::repeat_byte(2));
+
+		// Fork at height 1: both blocks include `tx1`, B1 is imported last and
+		// becomes the best block until A2 reorgs back onto the A branch.
+		let a1 = chain.add_header(1, genesis, eth_a1, vec![tx1]);
+		let b1 = chain.add_header(1, genesis, eth_b1, vec![tx1]);
+		let a2 = chain.add_header(2, a1, eth_a2, vec![tx2]);
+		let mut chain = Arc::new(chain);
+		{
+			let mut import = FrontierBlockImport::new(
+				TestImport { chain: chain.clone() },
+				chain.clone(),
+				backend.clone(),
+				true,
+			);
+			for (hash, block, expected) in vec![
+				(a1, block_a1, vec![(1, Some(eth_a1))]),
+				(b1, block_b1, vec![(1, Some(eth_b1))]),
+				(a2, block_a2, vec![(1, Some(eth_a1)), (2, Some(eth_a2))]),
+			] {
+				*chain.current_block.lock().unwrap() = Some(block);
+				let result = import.import_block(import_params(&chain, hash, true), HashMap::new()).unwrap();
+				assert!(matches!(result, ImportResult::Imported(_)));
+				for (number, ethereum_hash) in expected {
+					assert_eq!(backend.block_hash(number).unwrap(), ethereum_hash);
+				}
+			}
+		}
+		assert_eq!(load_transaction_metadata(chain.as_ref(), tx1).unwrap(), Some(vec![(eth_a1, 0), (eth_b1, 0)]));
+
+		// GRANDPA finalizes A2, retracting B1.
+		let chain = Arc::get_mut(&mut chain).unwrap();
+		chain.canonical.insert(1, a1);
+		chain.canonical.insert(2, a2);
+		chain.finalized = 2;
+		assert_eq!(prune_finalized(&*chain, &backend, 2, 100).unwrap(), Some(2));
+
+		assert_eq!(load_block_hash::<Block, _>(&*chain, eth_a1).unwrap(), Some(vec![a1]));
+		assert_eq!(load_block_hash::<Block, _>(&*chain, eth_b1).unwrap(), None);
+		assert_eq!(load_transaction_metadata(&*chain, tx1).unwrap(), Some(vec![(eth_a1, 0)]));
+		assert_eq!(load_transaction_metadata(&*chain, tx2).unwrap(), Some(vec![(eth_a2, 0)]));
+		assert_eq!(load_pending_blocks::<H256, _>(&*chain, 1).unwrap(), None);
+
+		assert_eq!(backend.block(&eth_b1).unwrap(), None);
+		assert_eq!(backend.ethereum_hash(&b1).unwrap(), None);
+		assert_eq!(backend.block_hash(1).unwrap(), Some(eth_a1));
+		assert_eq!(backend.block_hash(2).unwrap(), Some(eth_a2));
+	}
+
+	#[test]
+	fn pruning_skips_heights_without_block() {
+		let backend = fc_db::Backend::<Block>::new_in_memory();
+		let mut chain = TestChain::new();
+		let genesis = chain.canonical[&0];
+		let a1 = chain.import(1, genesis, H256::repeat_byte(0xa1), Vec::new(), &backend);
+		let a2 = chain.import(2, a1, H256::repeat_byte(0xa2), Vec::new(), &backend);
+		// Height 1 was finalized but its block is not known to the client.
+		chain.canonical.insert(2, a2);
+		chain.finalized = 2;
+
+		assert_eq!(prune_finalized(&chain, &backend, 2, 100).unwrap(), Some(2));
+		assert_eq!(load_pending_blocks::<H256, _>(&chain, 1).unwrap(), None);
+		assert_eq!(load_pending_blocks::<H256, _>(&chain, 2).unwrap(), None);
+		assert_eq!(aux_schema::load_pruned_number(&chain).unwrap(), Some(2));
+	}
+
+	#[test]
+	fn pruning_resumes_from_last_pruned_height() {
+		let backend = fc_db::Backend::<Block>::new_in_memory();
+		let mut chain = TestChain::new();
+		let mut parent = chain.canonical[&0];
+		for number in 1..=3u64 {
+			parent = chain.import(number, parent, H256::from_low_u64_be(number), Vec::new(), &backend);
+			chain.canonical.insert(number, parent);
+		}
+
+		assert_eq!(prune_finalized(&chain, &backend, 3, 2).unwrap(), Some(1));
+		assert!(load_pending_blocks::<H256, _>(&chain, 2).unwrap().is_some());
+		assert_eq!(prune_finalized(&chain, &backend, 3, 2).unwrap(), Some(3));
+		assert_eq!(prune_finalized(&chain, &backend, 3, 2).unwrap(), Some(3));
+		assert_eq!(load_pending_blocks::<H256, _>(&chain, 3).unwrap(), None);
+	}
+
+	#[test]
+	fn compaction_cleans_existing_mappings() {
+		let backend = fc_db::Backend::<Block>::new_in_memory();
+		let mut chain = TestChain::new();
+		let genesis = chain.canonical[&0];
+		let eth_a1 = H256::repeat_byte(0xa1);
+		let a1 = chain.import(1, genesis, eth_a1, Vec::new(), &backend);
+		chain.canonical.insert(1, a1);
+		chain.finalized = 1;
+
+		// A database written before pruning: duplicated entries, no pending blocks.
+		let stale = H256::repeat_byte(0xff);
+		let entries = vec![a1, stale].encode();
+		let key = block_hash_key(eth_a1);
+		chain.insert_aux(&[(&key[..], &entries[..])], &[&pending_blocks_key(1)[..]]).unwrap();
+
+		compact(&chain, &backend).unwrap();
+
+		assert_eq!(load_block_hash::<Block, _>(&chain, eth_a1).unwrap(), Some(vec![a1]));
+		assert_eq!(aux_schema::load_pruned_number(&chain).unwrap(), Some(1));
+	}
+}
@@ -0,0 +1,169 @@
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+// This file is part of Frontier.
+//
+// Copyright (c) 2020 Parity Technologies (UK) Ltd.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Rebuilding of the Frontier mappings from the block digests.
+
+use sp_core::H256;
+use sp_api::{BlockId, ProvideRuntimeApi};
+use sp_runtime::traits::{Block as BlockT, UniqueSaturatedInto, Zero};
+use sp_blockchain::{HeaderBackend, Result as ClientResult, Error as ClientError};
+use sc_client_api::backend::AuxStore;
+use fp_consensus::ConsensusLog;
+use fp_rpc::EthereumRuntimeRPCApi;
+use log::warn;
+use crate::aux_schema::{AuxChanges, block_hash_key, transaction_metadata_key};
+
+/// Write the Ethereum data of the canonical block `number` to the mapping
+/// database from the block state, unless it is there already. Used for the
+/// blocks imported before the database existed. Returns `false` if the block
+/// or its state is not available, or it has no Ethereum block.
+pub fn backfill_block<B, C>(client: &C, backend: &fc_db::Backend<B>, number: u64) -> ClientResult<bool> where
+	B: BlockT,
+	C: HeaderBackend<B> + ProvideRuntimeApi<B>,
+	C::Api: EthereumRuntimeRPCApi<B>,
+{
+	if backend.block_hash(number).map_err(ClientError::Backend)?.is_some() {
+		return Ok(true)
+	}
+	let hash = match client.hash(number.unique_saturated_into())? {
+		Some(hash) => hash,
+		None => return Ok(false),
+	};
+	write_mapping_block(client, backend, hash)
+}
+
+/// Write the Ethereum data of the canonical block `hash` to the mapping
+/// database, read from the block state. Returns `false` if the state is not
+/// available or the block has no Ethereum block.
+fn write_mapping_block<B, C>(client: &C, backend: &fc_db::Backend<B>, hash: B::Hash) -> ClientResult<bool> where
+	B: BlockT,
+	C: ProvideRuntimeApi<B>,
+	C::Api: EthereumRuntimeRPCApi<B>,
+{
+	match client.runtime_api().current_all(&BlockId::Hash(hash)) {
+		Ok((Some(block), receipts, statuses)) => {
+			backend.write_block(
+				hash,
+				block.header.hash(),
+				&block,
+				&receipts.unwrap_or_default(),
+				&statuses.unwrap_or_default(),
+				true,
+			).map_err(ClientError::Backend)?;
+			Ok(true)
+		},
+		Ok((None, _, _)) | Err(_) => Ok(false),
+	}
+}
+
+/// Rewrite the block and transaction mappings of the canonical block `hash`
+/// from its Frontier digest, replacing whatever is stored (including entries
+/// that fail to decode), and its Ethereum data in the mapping database from
+/// the block state. Returns `false` if the block has no Frontier digest.
+pub fn reindex_block<B, C>(client: &C, backend: &fc_db::Backend<B>, hash: B::Hash) -> ClientResult<bool> where
+	B: BlockT,
+	C: HeaderBackend<B> + AuxStore + ProvideRuntimeApi<B>,
+	C::Api: EthereumRuntimeRPCApi<B>,
+{
+	let header = client.header(BlockId::Hash(hash))?
+		.ok_or_else(|| ClientError::Backend(format!("Block {:?} not found", hash)))?;
+	let (block_hash, transaction_hashes) = match crate::find_frontier_log::<B>(&header) {
+		Ok(ConsensusLog::EndBlock { block_hash, transaction_hashes }) => (block_hash, transaction_hashes),
+		Err(_) => return Ok(false),
+	};
+
+	let mut changes = AuxChanges::new(client);
+	changes.set(block_hash_key(block_hash), &vec![hash]);
+	for (index, transaction_hash) in transaction_hashes.into_iter().enumerate() {
+		let metadata: Vec<(H256, u32)> = vec![(block_hash, index as u32)];
+		changes.set(transaction_metadata_key(transaction_hash), &metadata);
+	}
+	changes.commit()?;
+
+	if !write_mapping_block(client, backend, hash)? {
+		warn!(target: "frontier-consensus", "State of block {:?} not available, Ethereum data not re-indexed", hash);
+	}
+
+	Ok(true)
+}
+
+/// Rewrite the mappings of the genesis block. It has no Frontier digest, so its
+/// Ethereum block is read from the runtime. Returns `false` if there is none.
+pub fn reindex_genesis<B, C>(client: &C, backend: &fc_db::Backend<B>) -> ClientResult<bool> where
+	B: BlockT,
+	C: HeaderBackend<B> + AuxStore + ProvideRuntimeApi<B>,
+	C::Api: EthereumRuntimeRPCApi<B>,
+{
+	let hash = client.hash(Zero::zero())?
+		.ok_or_else(|| ClientError::Backend("Genesis block not found".to_string()))?;
+	let block = client.runtime_api().current_block(&BlockId::Hash(hash))
+		.map_err(|e| ClientError::Backend(format!("{:?}", e)))?;
+
+	match block {
+		Some(block) => {
+			let mut changes = AuxChanges::new(client);
+			changes.set(block_hash_key(block.header.hash()), &vec![hash]);
+			changes.commit()?;
+			backend.write_block(hash, block.header.hash(), &block, &[], &[], true)
+				.map_err(ClientError::Backend)?;
+			Ok(true)
+		},
+		None => Ok(false),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::aux_schema::{load_block_hash, load_transaction_metadata};
+	use crate::test_utils::{Block, TestChain, ethereum_block};
+
+	#[test]
+	fn reindex_overwrites_corrupted_mappings() {
+		let backend = fc_db::Backend::<Block>::new_in_memory();
+		let mut chain = TestChain::new();
+		let genesis = chain.canonical[&0];
+		let block = ethereum_block(1);
+		let (ethereum_hash, transaction_hash) = (block.header.hash(), H256::repeat_byte(1));
+		let hash = chain.import(1, genesis, ethereum_hash, vec![transaction_hash], &backend);
+		chain.canonical.insert(1, hash);
+		*chain.current_block.lock().unwrap() = Some(block.clone());
+
+		let key = transaction_metadata_key(transaction_hash);
+		chain.insert_aux(&[(&key[..], &b"corrupted"[..])], &[]).unwrap();
+		assert!(load_transaction_metadata(&chain, transaction_hash).is_err());
+
+		// A mapping database recreated from scratch is rewritten as well.
+		let backend = fc_db::Backend::<Block>::new_in_memory();
+		assert!(reindex_block(&chain, &backend, hash).unwrap());
+		assert!(!reindex_block(&chain, &backend, genesis).unwrap());
+		assert!(!reindex_genesis(&chain, &backend).unwrap());
+
+		assert_eq!(backend.block(&ethereum_hash).unwrap(), Some(block));
+		assert_eq!(backend.receipts(&ethereum_hash).unwrap(), Some(Vec::new()));
+		assert_eq!(backend.statuses(&ethereum_hash).unwrap(), Some(Vec::new()));
+		assert_eq!(backend.ethereum_hash(&hash).unwrap(), Some(ethereum_hash));
+		assert_eq!(backend.block_hash(1).unwrap(), Some(ethereum_hash));
+
+		assert_eq!(load_block_hash::<Block, _>(&chain, ethereum_hash).unwrap(), Some(vec![hash]));
+		assert_eq!(
+			load_transaction_metadata(&chain, transaction_hash).unwrap(),
+			Some(vec![(ethereum_hash, 0)]),
+		);
+	}
+}
@@ -0,0 +1,331 @@
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+// This file is part of Frontier.
+//
+// Copyright (c) 2020 Parity Technologies (UK) Ltd.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! In-memory chain for tests.
+
+use std::{collections::HashMap, sync::{Arc, Mutex}};
+use codec::Encode;
+use sp_core::{H160, H256, U256};
+use sp_api::{ApiRef, BlockId, ProvideRuntimeApi, TransactionFor};
+use sp_blockchain::{BlockStatus, Cache, Info, HeaderBackend, ProvideCache, Result as ClientResult};
+use sp_consensus::{
+	BlockCheckParams, BlockImport, BlockImportParams, BlockOrigin, Error as ConsensusError,
+	ForkChoiceStrategy, ImportResult, ImportedAux,
+};
+use sp_inherents::{CheckInherentsResult, InherentData};
+use sp_runtime::{
+	generic::DigestItem,
+	testing::{Block as RawBlock, Digest, ExtrinsicWrapper, Header},
+	traits::{Block as BlockT, Header as HeaderT},
+	ApplyExtrinsicResult, DispatchError,
+};
+use sc_client_api::{backend::AuxStore, BlockOf};
+use fp_consensus::{ConsensusLog, FRONTIER_ENGINE_ID};
+use fp_rpc::{EthereumRuntimeRPCApi, TransactionReceipt, TransactionStatus};
+use sp_block_builder::BlockBuilder;
+
+pub type Block = RawBlock<ExtrinsicWrapper<u64>>;
+
+#[derive(Default)]
+pub struct TestChain {
+	pub headers: HashMap<H256, Header>,
+	pub canonical: HashMap<u64, H256>,
+	pub finalized: u64,
+	pub aux: Mutex<HashMap<Vec<u8>, Vec<u8>>>,
+	/// Ethereum block returned by the runtime for the block being imported.
+	pub current_block: Mutex<Option<ethereum::Block>>,
+}
+
+impl AuxStore for TestChain {
+	fn insert_aux<
+		'a,
+		'b: 'a,
+		'c: 'a,
+		I: IntoIterator<Item=&'a(&'c [u8], &'c [u8])>,
+		D: IntoIterator<Item=&'a &'b [u8]>,
+	>(&self, insert: I, delete: D) -> ClientResult<()> {
+		let mut aux = self.aux.lock().unwrap();
+		for (key, value) in insert {
+			aux.insert(key.to_vec(), value.to_vec());
+		}
+		for key in delete {
+			aux.remove(*key);
+		}
+		Ok(())
+	}
+
+	fn get_aux(&self, key: &[u8]) -> ClientResult<Option<Vec<u8>>> {
+		Ok(self.aux.lock().unwrap().get(key).cloned())
+	}
+}
+
+impl HeaderBackend<Block> for TestChain {
+	fn header(&self, id: BlockId<Block>) -> ClientResult<Option<Header>> {
+		let hash = match id {
+			BlockId::Hash(hash) => Some(hash),
+			BlockId::Number(number) => self.canonical.get(&number).cloned(),
+		};
+		Ok(hash.and_then(|hash| self.headers.get(&hash).cloned()))
+	}
+
+	fn info(&self) -> Info<Block> {
+		let finalized_hash = self.canonical[&self.finalized];
+		Info {
+			best_hash: finalized_hash,
+			best_number: self.finalized,
+			genesis_hash: self.canonical[&0],
+			finalized_hash,
+			finalized_number: self.finalized,
+			number_leaves: 1,
+		}
+	}
+
+	fn status(&self, id: BlockId<Block>) -> ClientResult<BlockStatus> {
+		Ok(match self.header(id)? {
+			Some(_) => BlockStatus::InChain,
+			None => BlockStatus::Unknown,
+		})
+	}
+
+	fn number(&self, hash: H256) -> ClientResult<Option<u64>> {
+		Ok(self.headers.get(&hash).map(|header| header.number))
+	}
+
+	fn hash(&self, number: u64) -> ClientResult<Option<H256>> {
+		Ok(self.canonical.get(&number).cloned())
+	}
+}
+
+impl TestChain {
+	pub fn new() -> Self {
+		let mut chain = Self::default();
+		let genesis = Header::new(0, H256::zero(), H256::zero(), H256::zero(), Digest::default());
+		chain.canonical.insert(0, genesis.hash());
+		chain.headers.insert(genesis.hash(), genesis);
+		chain
+	}
+
+	/// Add the header of a block with a Frontier digest and return its hash.
+	pub fn add_header(
+		&mut self,
+		number: u64,
+		parent: H256,
+		ethereum_hash: H256,
+		transaction_hashes: Vec<H256>,
+	) -> H256 {
+		let log = ConsensusLog::EndBlock { block_hash: ethereum_hash, transaction_hashes };
+		let mut digest = Digest::default();
+		digest.push(DigestItem::Consensus(FRONTIER_ENGINE_ID, log.encode()));
+		let header = Header::new(number, H256::zero(), H256::zero(), parent, digest);
+		let hash = header.hash();
+		self.headers.insert(hash, header);
+		hash
+	}
+
+	/// Import a block the way `FrontierBlockImport` does and return its hash.
+	pub fn import(
+		&mut self,
+		number: u64,
+		parent: H256,
+		ethereum_hash: H256,
+		transaction_hashes: Vec<H256>,
+		backend: &fc_db::Backend<Block>,
+	) -> H256 {
+		let hash = self.add_header(number, parent, ethereum_hash, transaction_hashes.clone());
+
+		let none: &[&[u8]] = &[];
+		crate::write_ethereum_block_aux::<Block, _, _>(
+			&*self, number, hash, ethereum_hash, transaction_hashes,
+			|insert| self.insert_aux(insert.iter(), none.iter()).unwrap(),
+		);
+		backend.write_block(hash, ethereum_hash, &ethereum_block(number), &[], &[], true).unwrap();
+		hash
+	}
+}
+
+pub fn ethereum_block(number: u64) -> ethereum::Block {
+	let partial_header = ethereum::PartialHeader {
+		parent_hash: H256::default(),
+		beneficiary: Default::default(),
+		state_root: H256::default(),
+		receipts_root: H256::default(),
+		logs_bloom: Default::default(),
+		difficulty: Default::default(),
+		number: number.into(),
+		gas_limit: Default::default(),
+		gas_used: Default::default(),
+		timestamp: 0,
+		extra_data: Vec::new(),
+		mix_hash: H256::default(),
+		nonce: Default::default(),
+	};
+	ethereum::Block::new(partial_header, Vec::new(), Vec::new())
+}
+
+/// Ethereum block at `number`, distinct for each `fork`.
+pub fn fork_ethereum_block(number: u64, fork: u8) -> ethereum::Block {
+	let mut block = ethereum_block(number);
+	block.header.extra_data = vec![fork];
+	block
+}
+
+impl ProvideCache<Block> for TestChain {
+	fn cache(&self) -> Option<Arc<dyn Cache<Block>>> {
+		None
+	}
+}
+
+impl BlockOf for TestChain {
+	type Type = Block;
+}
+
+impl ProvideRuntimeApi<Block> for TestChain {
+	type Api = TestApi;
+
+	fn runtime_api<'a>(&'a self) -> ApiRef<'a, Self::Api> {
+		TestApi { current_block: self.current_block.lock().unwrap().clone() }.into()
+	}
+}
+
+/// Runtime API of the test chain. The genesis block has no Ethereum block.
+pub struct TestApi {
+	current_block: Option<ethereum::Block>,
+}
+
+sp_api::mock_impl_runtime_apis! {
+	impl EthereumRuntimeRPCApi<Block> for TestApi {
+		fn chain_id() -> u64 { 42 }
+		fn account_basic(_address: H160) -> fp_evm::Account { Default::default() }
+		fn gas_price() -> U256 { U256::zero() }
+		fn account_code_at(_address: H160) -> Vec<u8> { Vec::new() }
+		fn author() -> H160 { H160::zero() }
+		fn storage_at(_address: H160, _index: U256) -> H256 { H256::zero() }
+		fn call(
+			_from: H160,
+			_to: H160,
+			_data: Vec<u8>,
+			_value: U256,
+			_gas_limit: U256,
+			_gas_price: Option<U256>,
+			_nonce: Option<U256>,
+			_estimate: bool,
+		) -> Result<fp_evm::CallInfo, DispatchError> {
+			Err(DispatchError::Other("not supported"))
+		}
+		fn create(
+			_from: H160,
+			_data: Vec<u8>,
+			_value: U256,
+			_gas_limit: U256,
+			_gas_price: Option<U256>,
+			_nonce: Option<U256>,
+			_estimate: bool,
+		) -> Result<fp_evm::CreateInfo, DispatchError> {
+			Err(DispatchError::Other("not supported"))
+		}
+		fn current_block() -> Option<ethereum::Block> { None }
+		fn current_receipts(&self) -> Option<Vec<TransactionReceipt>> {
+			self.current_block.as_ref().map(|_| Vec::new())
+		}
+		fn current_transaction_statuses(&self) -> Option<Vec<TransactionStatus>> {
+			self.current_block.as_ref().map(|_| Vec::new())
+		}
+		fn current_all(&self) -> (
+			Option<ethereum::Block>,
+			Option<Vec<TransactionReceipt>>,
+			Option<Vec<TransactionStatus>>
+		) {
+			let empty = self.current_block.as_ref().map(|_| Vec::new());
+			(self.current_block.clone(), empty.clone(), empty)
+		}
+		fn current_block_gas_limit() -> U256 { U256::zero() }
+		fn extrinsic_filter(_xts: Vec<<Block as BlockT>::Extrinsic>) -> Vec<ethereum::Transaction> {
+			Vec::new()
+		}
+		fn trace_block(
+			_header: <Block as BlockT>::Header,
+			_xts: Vec<<Block as BlockT>::Extrinsic>,
+		) -> Vec<(H256, Vec<fp_evm::CallTrace>)> {
+			Vec::new()
+		}
+		fn account_basic_key(_address: H160) -> Vec<u8> { Vec::new() }
+	}
+
+	impl BlockBuilder<Block> for TestApi {
+		fn apply_extrinsic(_extrinsic: <Block as BlockT>::Extrinsic) -> ApplyExtrinsicResult {
+			unimplemented!()
+		}
+		fn finalize_block() -> <Block as BlockT>::Header {
+			unimplemented!()
+		}
+		fn inherent_extrinsics(_data: InherentData) -> Vec<<Block as BlockT>::Extrinsic> {
+			unimplemented!()
+		}
+		fn check_inherents(_block: Block, _data: InherentData) -> CheckInherentsResult {
+			unimplemented!()
+		}
+		fn random_seed() -> <Block as BlockT>::Hash {
+			unimplemented!()
+		}
+	}
+}
+
+/// Inner block import of the test chain: commits the auxiliary data and
+/// reports the block as the new best one when its fork choice says so.
+pub struct TestImport {
+	pub chain: Arc<TestChain>,
+}
+
+impl BlockImport<Block> for TestImport {
+	type Error = ConsensusError;
+	type Transaction = TransactionFor<TestChain, Block>;
+
+	fn check_block(&mut self, _block: BlockCheckParams<Block>) -> Result<ImportResult, Self::Error> {
+		Ok(ImportResult::imported(false))
+	}
+
+	fn import_block(
+		&mut self,
+		block: BlockImportParams<Block, Self::Transaction>,
+		_new_cache: HashMap<sp_blockchain::well_known_cache_keys::Id, Vec<u8>>,
+	) -> Result<ImportResult, Self::Error> {
+		let insert: Vec<(&[u8], &[u8])> = block.auxiliary.iter()
+			.filter_map(|(key, value)| value.as_ref().map(|value| (&key[..], &value[..])))
+			.collect();
+		let delete: Vec<&[u8]> = block.auxiliary.iter()
+			.filter(|(_, value)| value.is_none())
+			.map(|(key, _)| &key[..])
+			.collect();
+		self.chain.insert_aux(insert.iter(), delete.iter())
+			.map_err(|e| ConsensusError::ClientImport(e.to_string()))?;
+
+		let is_new_best = matches!(block.fork_choice, Some(ForkChoiceStrategy::Custom(true)));
+		Ok(ImportResult::Imported(ImportedAux { is_new_best, ..Default::default() }))
+	}
+}
+
+/// Import parameters of the block `hash` of `chain`.
+pub fn import_params(
+	chain: &TestChain,
+	hash: H256,
+	is_new_best: bool,
+) -> BlockImportParams<Block, TransactionFor<TestChain, Block>> {
+	let mut params = BlockImportParams::new(BlockOrigin::Own, chain.headers[&hash].clone());
+	params.fork_choice = Some(ForkChoiceStrategy::Custom(is_new_best));
+	params
+}
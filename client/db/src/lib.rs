@@ -0,0 +1,553 @@
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+// This file is part of Frontier.
+//
+// Copyright (c) 2020 Parity Technologies (UK) Ltd.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Node-side Frontier mapping database.
+//!
+//! `pallet_ethereum` only keeps the Ethereum data of the latest block in state,
+//! so the RPC can not serve history once state is pruned. This database keeps
+//! a copy of every imported Ethereum block, its receipts and transaction
+//! statuses, keyed by Ethereum block hash, together with the Ethereum block
+//! number and Substrate block hash mappings.
+//!
+//! It also holds the log index used by `eth_getLogs`: the blocks in which an
+//! address or topic appears, and the union of the block blooms of every
+//! `LOG_BLOOM_RANGE` blocks, so whole ranges without matches are skipped.
+
+use std::{collections::{BTreeMap, BTreeSet}, marker::PhantomData, path::PathBuf, sync::Arc};
+use codec::{Encode, Decode, DecodeAll};
+use ethereum_types::Bloom;
+use sp_core::{H160, H256};
+use sp_runtime::traits::Block as BlockT;
+use fp_rpc::TransactionStatus;
+use fp_evm::CallTrace;
+use kvdb::{KeyValueDB, DBTransaction};
+
+const DB_VERSION: u32 = 1;
+const VERSION_KEY: &[u8] = b"version";
+const LOG_INDEX_TIP_KEY: &[u8] = b"log_index_tip";
+
+/// Number of blocks covered by one range bloom.
+pub const LOG_BLOOM_RANGE: u64 = 1024;
+/// Log index entries are iterated by chunks of `2^LOG_INDEX_CHUNK_BITS` blocks.
+const LOG_INDEX_CHUNK_BITS: u32 = 16;
+/// Leading bytes of the block number shared by the blocks of a chunk.
+const LOG_INDEX_CHUNK_PREFIX: usize = 8 - LOG_INDEX_CHUNK_BITS as usize / 8;
+
+pub mod columns {
+	pub const NUM_COLUMNS: u32 = 10;
+
+	pub const META: u32 = 0;
+	/// Ethereum block hash => `ethereum::Block`.
+	pub const BLOCK: u32 = 1;
+	/// Ethereum block hash => `Vec<fp_rpc::TransactionReceipt>`.
+	pub const RECEIPTS: u32 = 2;
+	/// Ethereum block hash => `Vec<TransactionStatus>`.
+	pub const STATUSES: u32 = 3;
+	/// Ethereum block number => canonical Ethereum block hash.
+	pub const BLOCK_NUMBER: u32 = 4;
+	/// Ethereum block hash => Substrate block hash.
+	pub const ETHEREUM_TO_SUBSTRATE: u32 = 5;
+	/// Substrate block hash => Ethereum block hash.
+	pub const SUBSTRATE_TO_ETHEREUM: u32 = 6;
+	/// `LogIndexKey` ++ Ethereum block number => ().
+	pub const LOG_INDEX: u32 = 7;
+	/// Range number => union of the blooms of the blocks in the range.
+	pub const LOG_BLOOM: u32 = 8;
+	/// Ethereum block hash => `Vec<(H256, Vec<fp_evm::CallTrace>)>`, the call frames
+	/// of each transaction hash, filled on demand.
+	pub const TRACES: u32 = 9;
+}
+
+/// Entry of the log index.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LogIndexKey {
+	/// Address of the contract emitting the log.
+	Address(H160),
+	/// Topic at any position of the log.
+	Topic(H256),
+}
+
+impl LogIndexKey {
+	fn prefix(&self) -> Vec<u8> {
+		match self {
+			LogIndexKey::Address(address) => [&b"a"[..], address.as_bytes()].concat(),
+			LogIndexKey::Topic(topic) => [&b"t"[..], topic.as_bytes()].concat(),
+		}
+	}
+
+	fn key(&self, number: u64) -> Vec<u8> {
+		let mut key = self.prefix();
+		key.extend_from_slice(&number_key(number));
+		key
+	}
+}
+
+pub struct DatabaseSettings {
+	/// Directory of the RocksDB database.
+	pub path: PathBuf,
+	/// Cache size in MiB.
+	pub cache_size: usize,
+}
+
+pub struct Backend<Block: BlockT> {
+	db: Arc<dyn KeyValueDB>,
+	_marker: PhantomData<Block>,
+}
+
+impl<Block: BlockT> Backend<Block> {
+	/// Open (or create) the database at the configured path.
+	pub fn open(settings: &DatabaseSettings) -> Result<Self, String> {
+		let mut config = kvdb_rocksdb::DatabaseConfig::with_columns(columns::NUM_COLUMNS);
+		config.memory_budget = (0..columns::NUM_COLUMNS)
+			.map(|column| (column, settings.cache_size / columns::NUM_COLUMNS as usize))
+			.collect();
+
+		let path = settings.path.to_str()
+			.ok_or_else(|| format!("invalid Frontier database path: {:?}", settings.path))?;
+		let db = kvdb_rocksdb::Database::open(&config, path)
+			.map_err(|e| format!("failed to open Frontier database: {:?}", e))?;
+
+		Self::from_database(Arc::new(db))
+	}
+
+	/// Create a database that lives in memory only.
+	pub fn new_in_memory() -> Self {
+		Self::from_database(Arc::new(kvdb_memorydb::create(columns::NUM_COLUMNS)))
+			.expect("in-memory database is empty; qed")
+	}
+
+	fn from_database(db: Arc<dyn KeyValueDB>) -> Result<Self, String> {
+		match db.get(columns::META, VERSION_KEY).map_err(|e| format!("{:?}", e))? {
+			Some(version) => {
+				let version = u32::decode(&mut &version[..]).map_err(|e| format!("{:?}", e))?;
+				if version != DB_VERSION {
+					return Err(format!(
+						"Frontier database version {} is not supported, expected {}", version, DB_VERSION,
+					))
+				}
+			},
+			None => {
+				let mut transaction = DBTransaction::new();
+				transaction.put(columns::META, VERSION_KEY, &DB_VERSION.encode());
+				db.write(transaction).map_err(|e| format!("{:?}", e))?;
+			},
+		}
+
+		Ok(Self { db, _marker: PhantomData })
+	}
+
+	fn read<T: Decode>(&self, column: u32, key: &[u8]) -> Result<Option<T>, String> {
+		match self.db.get(column, key).map_err(|e| format!("{:?}", e))? {
+			Some(data) => T::decode(&mut &data[..])
+				.map(Some)
+				.map_err(|e| format!("Frontier DB is corrupted. Decode error: {}", e)),
+			None => Ok(None),
+		}
+	}
+
+	/// Persist the Ethereum data of an imported block. The number mapping is
+	/// only updated when the block is the new best block.
+	pub fn write_block(
+		&self,
+		substrate_hash: Block::Hash,
+		ethereum_hash: H256,
+		block: &ethereum::Block,
+		receipts: &[fp_rpc::TransactionReceipt],
+		statuses: &[TransactionStatus],
+		is_new_best: bool,
+	) -> Result<(), String> {
+		let mut transaction = DBTransaction::new();
+		transaction.put(columns::BLOCK, ethereum_hash.as_bytes(), &block.encode());
+		transaction.put(columns::RECEIPTS, ethereum_hash.as_bytes(), &receipts.encode());
+		transaction.put(columns::STATUSES, ethereum_hash.as_bytes(), &statuses.encode());
+		transaction.put(columns::ETHEREUM_TO_SUBSTRATE, ethereum_hash.as_bytes(), &substrate_hash.encode());
+		transaction.put(columns::SUBSTRATE_TO_ETHEREUM, substrate_hash.as_ref(), &ethereum_hash.encode());
+		if is_new_best {
+			transaction.put(columns::BLOCK_NUMBER, &number_key(block.header.number.as_u64()), &ethereum_hash.encode());
+		}
+
+		self.db.write(transaction).map_err(|e| format!("{:?}", e))
+	}
+
+	/// Point the number mapping of `number` at the canonical Ethereum block
+	/// `ethereum_hash`, e.g. once it is finalized.
+	pub fn set_block_hash(&self, number: u64, ethereum_hash: H256) -> Result<(), String> {
+		let mut transaction = DBTransaction::new();
+		transaction.put(columns::BLOCK_NUMBER, &number_key(number), &ethereum_hash.encode());
+		self.db.write(transaction).map_err(|e| format!("{:?}", e))
+	}
+
+	/// Remove the number mappings above `number`, once a block at `number`
+	/// on a shorter branch became the best block.
+	pub fn remove_block_hashes_above(&self, number: u64) -> Result<(), String> {
+		let mut transaction = DBTransaction::new();
+		let mut current = number.saturating_add(1);
+		while self.block_hash(current)?.is_some() {
+			transaction.delete(columns::BLOCK_NUMBER, &number_key(current));
+			current = current.saturating_add(1);
+		}
+		self.db.write(transaction).map_err(|e| format!("{:?}", e))
+	}
+
+	/// Remove the data of a block retracted by finality. The number mapping is
+	/// left as is, it is overwritten by `set_block_hash` for the canonical block.
+	pub fn remove_block(&self, substrate_hash: Block::Hash, ethereum_hash: H256) -> Result<(), String> {
+		let mut transaction = DBTransaction::new();
+		transaction.delete(columns::BLOCK, ethereum_hash.as_bytes());
+		transaction.delete(columns::RECEIPTS, ethereum_hash.as_bytes());
+		transaction.delete(columns::STATUSES, ethereum_hash.as_bytes());
+		transaction.delete(columns::TRACES, ethereum_hash.as_bytes());
+		transaction.delete(columns::ETHEREUM_TO_SUBSTRATE, ethereum_hash.as_bytes());
+		transaction.delete(columns::SUBSTRATE_TO_ETHEREUM, substrate_hash.as_ref());
+		self.db.write(transaction).map_err(|e| format!("{:?}", e))
+	}
+
+	/// Ethereum block with the given hash.
+	pub fn block(&self, ethereum_hash: &H256) -> Result<Option<ethereum::Block>, String> {
+		self.read(columns::BLOCK, ethereum_hash.as_bytes())
+	}
+
+	/// Receipts of the Ethereum block with the given hash.
+	pub fn receipts(&self, ethereum_hash: &H256) -> Result<Option<Vec<fp_rpc::TransactionReceipt>>, String> {
+		self.read(columns::RECEIPTS, ethereum_hash.as_bytes())
+	}
+
+	/// Transaction statuses of the Ethereum block with the given hash.
+	pub fn statuses(&self, ethereum_hash: &H256) -> Result<Option<Vec<TransactionStatus>>, String> {
+		self.read(columns::STATUSES, ethereum_hash.as_bytes())
+	}
+
+	/// Hash of the canonical Ethereum block at `number`.
+	pub fn block_hash(&self, number: u64) -> Result<Option<H256>, String> {
+		self.read(columns::BLOCK_NUMBER, &number_key(number))
+	}
+
+	/// Ethereum block hash of a Substrate block.
+	pub fn ethereum_hash(&self, substrate_hash: &Block::Hash) -> Result<Option<H256>, String> {
+		self.read(columns::SUBSTRATE_TO_ETHEREUM, substrate_hash.as_ref())
+	}
+
+	/// Substrate block hash of an Ethereum block.
+	pub fn substrate_hash(&self, ethereum_hash: &H256) -> Result<Option<Block::Hash>, String> {
+		self.read(columns::ETHEREUM_TO_SUBSTRATE, ethereum_hash.as_bytes())
+	}
+
+	/// Call traces of the transactions of the Ethereum block with the given
+	/// hash, by transaction hash, if the block has been traced already. Traces
+	/// stored in an older format are traced again.
+	pub fn traces(&self, ethereum_hash: &H256) -> Result<Option<Vec<(H256, Vec<CallTrace>)>>, String> {
+		Ok(self.db.get(columns::TRACES, ethereum_hash.as_bytes())
+			.map_err(|e| format!("{:?}", e))?
+			.and_then(|data| Vec::<(H256, Vec<CallTrace>)>::decode_all(&data[..]).ok()))
+	}
+
+	/// Store the call traces of the transactions of an Ethereum block, by transaction hash.
+	pub fn write_traces(&self, ethereum_hash: H256, traces: &[(H256, Vec<CallTrace>)]) -> Result<(), String> {
+		let mut transaction = DBTransaction::new();
+		transaction.put(columns::TRACES, ethereum_hash.as_bytes(), &traces.encode());
+		self.db.write(transaction).map_err(|e| format!("{:?}", e))
+	}
+}
+
+impl<Block: BlockT> Backend<Block> {
+	/// Last Ethereum block number whose logs are indexed.
+	pub fn log_index_tip(&self) -> Result<Option<u64>, String> {
+		self.read(columns::META, LOG_INDEX_TIP_KEY)
+	}
+
+	/// Index the logs of the canonical blocks following the current tip, up to
+	/// `number` and at most `max_blocks` of them. Only blocks that can no longer
+	/// be reorganised (i.e. finalized) should be indexed. Returns the new tip.
+	pub fn index_logs(&self, number: u64, max_blocks: u64) -> Result<Option<u64>, String> {
+		let tip = self.log_index_tip()?;
+		let start = tip.map(|tip| tip + 1).unwrap_or(0);
+		let end = number.min(start.saturating_add(max_blocks).saturating_sub(1));
+
+		let mut transaction = DBTransaction::new();
+		let mut blooms: BTreeMap<u64, Bloom> = BTreeMap::new();
+		let mut new_tip = tip;
+
+		for current in start..=end {
+			let hash = match self.block_hash(current)? {
+				Some(hash) => hash,
+				// Blocks are indexed in order, so stop at the first gap.
+				None => break,
+			};
+			let block = self.block(&hash)?
+				.ok_or_else(|| format!("missing Ethereum block {:?}", hash))?;
+
+			for status in self.statuses(&hash)?.unwrap_or_default() {
+				for log in status.logs {
+					transaction.put(columns::LOG_INDEX, &LogIndexKey::Address(log.address).key(current), &[]);
+					for topic in log.topics {
+						transaction.put(columns::LOG_INDEX, &LogIndexKey::Topic(topic).key(current), &[]);
+					}
+				}
+			}
+
+			let range = current / LOG_BLOOM_RANGE;
+			if !blooms.contains_key(&range) {
+				let bloom = self.range_bloom(range)?.unwrap_or_default();
+				blooms.insert(range, bloom);
+			}
+			if let Some(bloom) = blooms.get_mut(&range) {
+				bloom.accrue_bloom(&block.header.logs_bloom);
+			}
+			new_tip = Some(current);
+		}
+
+		for (range, bloom) in blooms {
+			transaction.put(columns::LOG_BLOOM, &number_key(range), bloom.as_bytes());
+		}
+		if let Some(new_tip) = new_tip {
+			transaction.put(columns::META, LOG_INDEX_TIP_KEY, &new_tip.encode());
+		}
+		self.db.write(transaction).map_err(|e| format!("{:?}", e))?;
+
+		Ok(new_tip)
+	}
+
+	/// Union of the blooms of the blocks in range `range`, i.e. blocks
+	/// `range * LOG_BLOOM_RANGE .. (range + 1) * LOG_BLOOM_RANGE`.
+	pub fn range_bloom(&self, range: u64) -> Result<Option<Bloom>, String> {
+		match self.db.get(columns::LOG_BLOOM, &number_key(range)).map_err(|e| format!("{:?}", e))? {
+			Some(data) if data.len() == 256 => Ok(Some(Bloom::from_slice(&data))),
+			Some(_) => Err("Frontier DB is corrupted. Invalid range bloom".to_string()),
+			None => Ok(None),
+		}
+	}
+
+	/// Mark the block following the tip, which is missing from the database
+	/// and can not be recovered, as indexed without logs. Returns the new tip.
+	pub fn skip_log_index(&self, number: u64) -> Result<u64, String> {
+		let expected = self.log_index_tip()?.map(|tip| tip + 1).unwrap_or(0);
+		if number != expected {
+			return Err(format!("can not skip block {} of the log index, next is {}", number, expected))
+		}
+
+		let mut transaction = DBTransaction::new();
+		transaction.put(columns::META, LOG_INDEX_TIP_KEY, &number.encode());
+		self.db.write(transaction).map_err(|e| format!("{:?}", e))?;
+		Ok(number)
+	}
+
+	/// Indexed blocks in `from..=to` containing logs matching `key`.
+	pub fn indexed_blocks(&self, key: LogIndexKey, from: u64, to: u64) -> BTreeSet<u64> {
+		let prefix = key.prefix();
+		let mut blocks = BTreeSet::new();
+		if from > to {
+			return blocks
+		}
+
+		// Keys end with the big endian block number, and the database can only
+		// iterate over a prefix. The range is walked by chunks of blocks
+		// sharing the leading bytes of their number, starting at the chunk of
+		// `from` and stopping past `to`.
+		for chunk in (from >> LOG_INDEX_CHUNK_BITS)..=(to >> LOG_INDEX_CHUNK_BITS) {
+			let mut chunk_prefix = prefix.clone();
+			chunk_prefix.extend_from_slice(&number_key(chunk << LOG_INDEX_CHUNK_BITS)[..LOG_INDEX_CHUNK_PREFIX]);
+
+			for (key, _) in self.db.iter_with_prefix(columns::LOG_INDEX, &chunk_prefix) {
+				let number = match key.get(prefix.len()..).and_then(|number| number.get(..8)) {
+					Some(number) => {
+						let mut bytes = [0u8; 8];
+						bytes.copy_from_slice(number);
+						u64::from_be_bytes(bytes)
+					},
+					None => continue,
+				};
+				if number > to {
+					break
+				}
+				if number >= from {
+					blocks.insert(number);
+				}
+			}
+		}
+		blocks
+	}
+}
+
+fn number_key(number: u64) -> [u8; 8] {
+	number.to_be_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sp_core::{H64, U256};
+	use sp_runtime::testing::{Block as RawBlock, ExtrinsicWrapper};
+
+	type Block = RawBlock<ExtrinsicWrapper<u64>>;
+
+	fn ethereum_block(number: u64) -> ethereum::Block {
+		let partial_header = ethereum::PartialHeader {
+			parent_hash: H256::default(),
+			beneficiary: Default::default(),
+			state_root: H256::default(),
+			receipts_root: H256::default(),
+			logs_bloom: Default::default(),
+			difficulty: U256::zero(),
+			number: U256::from(number),
+			gas_limit: U256::zero(),
+			gas_used: U256::zero(),
+			timestamp: 0,
+			extra_data: Vec::new(),
+			mix_hash: H256::default(),
+			nonce: H64::default(),
+		};
+		ethereum::Block::new(partial_header, Vec::new(), Vec::new())
+	}
+
+	#[test]
+	fn block_round_trip() {
+		let backend = Backend::<Block>::new_in_memory();
+		let block = ethereum_block(1);
+		let ethereum_hash = H256::repeat_byte(1);
+		let substrate_hash = H256::repeat_byte(2);
+
+		backend.write_block(substrate_hash, ethereum_hash, &block, &[], &[], true).unwrap();
+
+		assert_eq!(backend.block(&ethereum_hash).unwrap(), Some(block));
+		assert_eq!(backend.receipts(&ethereum_hash).unwrap(), Some(Vec::new()));
+		assert_eq!(backend.block_hash(1).unwrap(), Some(ethereum_hash));
+		assert_eq!(backend.ethereum_hash(&substrate_hash).unwrap(), Some(ethereum_hash));
+		assert_eq!(backend.substrate_hash(&ethereum_hash).unwrap(), Some(substrate_hash));
+		assert_eq!(backend.traces(&ethereum_hash).unwrap(), None);
+
+		let traces = vec![(H256::repeat_byte(3), Vec::new())];
+		backend.write_traces(ethereum_hash, &traces).unwrap();
+		assert_eq!(backend.traces(&ethereum_hash).unwrap(), Some(traces));
+
+		// Traces of the previous format, without transaction hashes, are dropped.
+		let mut transaction = DBTransaction::new();
+		transaction.put(columns::TRACES, ethereum_hash.as_bytes(), &vec![Vec::<CallTrace>::new(); 2].encode());
+		backend.db.write(transaction).unwrap();
+		assert_eq!(backend.traces(&ethereum_hash).unwrap(), None);
+	}
+
+	#[test]
+	fn logs_are_indexed_by_address_and_topic() {
+		let backend = Backend::<Block>::new_in_memory();
+		let address = H160::repeat_byte(0xaa);
+		let topic = H256::repeat_byte(0xbb);
+
+		for number in 0..3u64 {
+			let mut block = ethereum_block(number);
+			let statuses = if number == 1 {
+				block.header.logs_bloom.accrue(ethereum_types::BloomInput::Raw(address.as_bytes()));
+				vec![TransactionStatus {
+					logs: vec![ethereum::Log { address, topics: vec![topic], data: Vec::new() }],
+					..Default::default()
+				}]
+			} else {
+				Vec::new()
+			};
+			let hash = H256::from_low_u64_be(number + 1);
+			backend.write_block(hash, hash, &block, &[], &statuses, true).unwrap();
+		}
+
+		assert_eq!(backend.index_logs(2, 2).unwrap(), Some(1));
+		assert_eq!(backend.index_logs(2, 100).unwrap(), Some(2));
+
+		let expected: BTreeSet<u64> = vec![1].into_iter().collect();
+		assert_eq!(backend.indexed_blocks(LogIndexKey::Address(address), 0, 2), expected);
+		assert_eq!(backend.indexed_blocks(LogIndexKey::Topic(topic), 0, 2), expected);
+		assert!(backend.indexed_blocks(LogIndexKey::Address(address), 2, 2).is_empty());
+
+		let bloom = backend.range_bloom(0).unwrap().unwrap();
+		assert!(bloom.contains_input(ethereum_types::BloomInput::Raw(address.as_bytes())));
+	}
+
+	#[test]
+	fn indexed_blocks_are_limited_to_range() {
+		let backend = Backend::<Block>::new_in_memory();
+		let address = H160::repeat_byte(0xaa);
+		let numbers = [1u64, 70_000, 70_001, 200_000];
+
+		let mut transaction = DBTransaction::new();
+		for number in numbers.iter() {
+			transaction.put(columns::LOG_INDEX, &LogIndexKey::Address(address).key(*number), &[]);
+		}
+		transaction.put(columns::LOG_INDEX, &LogIndexKey::Address(H160::repeat_byte(0xbb)).key(70_000), &[]);
+		backend.db.write(transaction).unwrap();
+
+		let blocks = |from, to| backend.indexed_blocks(LogIndexKey::Address(address), from, to)
+			.into_iter().collect::<Vec<_>>();
+		assert_eq!(blocks(0, 300_000), numbers.to_vec());
+		assert_eq!(blocks(2, 70_000), vec![70_000]);
+		assert_eq!(blocks(70_001, 199_999), vec![70_001]);
+		assert_eq!(blocks(200_001, 300_000), Vec::<u64>::new());
+		assert_eq!(blocks(5, 1), Vec::<u64>::new());
+	}
+
+	#[test]
+	fn missing_blocks_can_be_skipped_by_the_log_index() {
+		let backend = Backend::<Block>::new_in_memory();
+		let hash = H256::repeat_byte(2);
+		backend.write_block(hash, hash, &ethereum_block(1), &[], &[], true).unwrap();
+
+		// Block 0 was imported before the database existed.
+		assert_eq!(backend.index_logs(1, 100).unwrap(), None);
+		assert!(backend.skip_log_index(1).is_err());
+		assert_eq!(backend.skip_log_index(0).unwrap(), 0);
+		assert_eq!(backend.index_logs(1, 100).unwrap(), Some(1));
+	}
+
+	#[test]
+	fn retracted_block_is_removed() {
+		let backend = Backend::<Block>::new_in_memory();
+		let block = ethereum_block(1);
+
+		backend.write_block(H256::repeat_byte(2), H256::repeat_byte(1), &block, &[], &[], true).unwrap();
+		backend.write_block(H256::repeat_byte(4), H256::repeat_byte(3), &block, &[], &[], false).unwrap();
+		backend.write_traces(H256::repeat_byte(1), &[(H256::repeat_byte(5), Vec::new())]).unwrap();
+		backend.remove_block(H256::repeat_byte(2), H256::repeat_byte(1)).unwrap();
+		backend.set_block_hash(1, H256::repeat_byte(3)).unwrap();
+
+		assert_eq!(backend.block(&H256::repeat_byte(1)).unwrap(), None);
+		assert_eq!(backend.traces(&H256::repeat_byte(1)).unwrap(), None);
+		assert_eq!(backend.ethereum_hash(&H256::repeat_byte(2)).unwrap(), None);
+		assert_eq!(backend.block_hash(1).unwrap(), Some(H256::repeat_byte(3)));
+	}
+
+	#[test]
+	fn number_mapping_follows_best_block() {
+		let backend = Backend::<Block>::new_in_memory();
+		let block = ethereum_block(1);
+
+		backend.write_block(H256::repeat_byte(2), H256::repeat_byte(1), &block, &[], &[], true).unwrap();
+		backend.write_block(H256::repeat_byte(4), H256::repeat_byte(3), &block, &[], &[], false).unwrap();
+
+		assert_eq!(backend.block_hash(1).unwrap(), Some(H256::repeat_byte(1)));
+	}
+
+	#[test]
+	fn number_mappings_above_best_block_are_removed() {
+		let backend = Backend::<Block>::new_in_memory();
+		for number in 1..4u64 {
+			backend.set_block_hash(number, H256::from_low_u64_be(number)).unwrap();
+		}
+
+		backend.remove_block_hashes_above(1).unwrap();
+
+		assert_eq!(backend.block_hash(1).unwrap(), Some(H256::from_low_u64_be(1)));
+		assert_eq!(backend.block_hash(2).unwrap(), None);
+		assert_eq!(backend.block_hash(3).unwrap(), None);
+	}
+}
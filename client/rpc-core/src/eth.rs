@@ -24,10 +24,11 @@ use jsonrpc_derive::rpc;
 
 use crate::types::{
 	BlockNumber, Bytes, CallRequest, Filter, FilterChanges, Index, Log, Receipt,
-	RichBlock, SyncStatus, Transaction, Work, TransactionRequest,
+	RichBlock, SyncStatus, Transaction, Work, TransactionRequest, TypedData,
 };
 pub use rpc_impl_EthApi::gen_server::EthApi as EthApiServer;
 pub use rpc_impl_EthFilterApi::gen_server::EthFilterApi as EthFilterApiServer;
+pub use rpc_impl_EthLightApi::gen_server::EthLightApi as EthLightApiServer;
 
 /// Eth rpc interface.
 #[rpc(server)]
@@ -119,6 +120,18 @@ pub trait EthApi {
 	#[rpc(name = "eth_sendRawTransaction")]
 	fn send_raw_transaction(&self, _: Bytes) -> BoxFuture<H256>;
 
+	/// Signs data with an account, prefixed as an Ethereum signed message.
+	#[rpc(name = "eth_sign")]
+	fn sign(&self, _: H160, _: Bytes) -> Result<Bytes>;
+
+	/// Signs a transaction without sending it, returning its RLP encoding.
+	#[rpc(name = "eth_signTransaction")]
+	fn sign_transaction(&self, _: TransactionRequest) -> Result<Bytes>;
+
+	/// Signs EIP-712 typed data with an account.
+	#[rpc(name = "eth_signTypedData_v4")]
+	fn sign_typed_data(&self, _: H160, _: TypedData) -> Result<Bytes>;
+
 	/// Call contract, returning the output data.
 	#[rpc(name = "eth_call")]
 	fn call(&self, _: CallRequest, _: Option<BlockNumber>) -> Result<Bytes>;
@@ -151,6 +164,10 @@ pub trait EthApi {
 	#[rpc(name = "eth_getTransactionReceipt")]
 	fn transaction_receipt(&self, _: H256) -> Result<Option<Receipt>>;
 
+	/// Returns the receipts of the transactions of a block.
+	#[rpc(name = "eth_getBlockReceipts")]
+	fn block_receipts(&self, _: BlockNumber) -> Result<Option<Vec<Receipt>>>;
+
 	/// Returns an uncles at given block and index.
 	#[rpc(name = "eth_getUncleByBlockHashAndIndex")]
 	fn uncle_by_block_hash_and_index(&self, _: H256, _: Index) -> Result<Option<RichBlock>>;
@@ -183,17 +200,20 @@ pub trait EthApi {
 /// Eth filters rpc api (polling).
 #[rpc(server)]
 pub trait EthFilterApi {
+	/// RPC Metadata, identifying the connection installing a filter.
+	type Metadata;
+
 	/// Returns id of new filter.
-	#[rpc(name = "eth_newFilter")]
-	fn new_filter(&self, _: Filter) -> Result<U256>;
+	#[rpc(meta, name = "eth_newFilter")]
+	fn new_filter(&self, _: Self::Metadata, _: Filter) -> Result<U256>;
 
 	/// Returns id of new block filter.
-	#[rpc(name = "eth_newBlockFilter")]
-	fn new_block_filter(&self) -> Result<U256>;
+	#[rpc(meta, name = "eth_newBlockFilter")]
+	fn new_block_filter(&self, _: Self::Metadata) -> Result<U256>;
 
 	/// Returns id of new block filter.
-	#[rpc(name = "eth_newPendingTransactionFilter")]
-	fn new_pending_transaction_filter(&self) -> Result<U256>;
+	#[rpc(meta, name = "eth_newPendingTransactionFilter")]
+	fn new_pending_transaction_filter(&self, _: Self::Metadata) -> Result<U256>;
 
 	/// Returns filter changes since last poll.
 	#[rpc(name = "eth_getFilterChanges")]
@@ -207,3 +227,45 @@ pub trait EthFilterApi {
 	#[rpc(name = "eth_uninstallFilter")]
 	fn uninstall_filter(&self, _: Index) -> Result<bool>;
 }
+
+/// Eth rpc interface of light clients, the read-only subset of `EthApi`
+/// answered from remote storage proofs, and transaction submission. Blocks
+/// can't be given by hash.
+#[rpc(server)]
+pub trait EthLightApi {
+	/// Returns the chain ID used for transaction signing at the best block.
+	#[rpc(name = "eth_chainId")]
+	fn chain_id(&self) -> BoxFuture<Option<U64>>;
+
+	/// Returns current gas_price.
+	#[rpc(name = "eth_gasPrice")]
+	fn gas_price(&self) -> BoxFuture<U256>;
+
+	/// Returns highest block number.
+	#[rpc(name = "eth_blockNumber")]
+	fn block_number(&self) -> Result<U256>;
+
+	/// Returns balance of the given account.
+	#[rpc(name = "eth_getBalance")]
+	fn balance(&self, _: H160, _: Option<BlockNumber>) -> BoxFuture<U256>;
+
+	/// Returns content of the storage at given address.
+	#[rpc(name = "eth_getStorageAt")]
+	fn storage_at(&self, _: H160, _: U256, _: Option<BlockNumber>) -> BoxFuture<H256>;
+
+	/// Returns the number of transactions sent from given address at given time (block number).
+	#[rpc(name = "eth_getTransactionCount")]
+	fn transaction_count(&self, _: H160, _: Option<BlockNumber>) -> BoxFuture<U256>;
+
+	/// Returns the code at given address at given time (block number).
+	#[rpc(name = "eth_getCode")]
+	fn code_at(&self, _: H160, _: Option<BlockNumber>) -> BoxFuture<Bytes>;
+
+	/// Returns block with given number.
+	#[rpc(name = "eth_getBlockByNumber")]
+	fn block_by_number(&self, _: BlockNumber, _: bool) -> BoxFuture<Option<RichBlock>>;
+
+	/// Sends signed transaction, returning its hash.
+	#[rpc(name = "eth_sendRawTransaction")]
+	fn send_raw_transaction(&self, _: Bytes) -> BoxFuture<H256>;
+}
@@ -0,0 +1,43 @@
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+// This file is part of Frontier.
+//
+// Copyright (c) 2015-2020 Parity Technologies (UK) Ltd.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! uniarts_evm rpc interface.
+use ethereum_types::{H160, H256};
+use jsonrpc_core::Result;
+use jsonrpc_derive::rpc;
+
+use crate::types::EvmBalances;
+
+pub use rpc_impl_EvmAccountApi::gen_server::EvmAccountApi as EvmAccountApiServer;
+
+/// uniarts_evm rpc interface, the EVM view of the Substrate accounts.
+#[rpc(server)]
+pub trait EvmAccountApi<AccountId> {
+	/// Returns the Substrate account holding the balance of an EVM address.
+	#[rpc(name = "uniarts_evm_accountId")]
+	fn account_id(&self, address: H160, at: Option<H256>) -> Result<AccountId>;
+
+	/// Returns the EVM address a Substrate account can call and withdraw from.
+	#[rpc(name = "uniarts_evm_address")]
+	fn evm_address(&self, account: AccountId, at: Option<H256>) -> Result<Option<H160>>;
+
+	/// Returns the Substrate balance of an account and the EVM balance of its
+	/// address.
+	#[rpc(name = "uniarts_evm_balances")]
+	fn balances(&self, account: AccountId, at: Option<H256>) -> Result<EvmBalances>;
+}
@@ -0,0 +1,37 @@
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+// This file is part of Frontier.
+//
+// Copyright (c) 2015-2020 Parity Technologies (UK) Ltd.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! uniarts_evm proof rpc interface.
+use ethereum_types::{H160, H256, U256};
+use jsonrpc_core::Result;
+use jsonrpc_derive::rpc;
+
+use crate::types::EvmAccountProof;
+
+pub use rpc_impl_EvmProofApi::gen_server::EvmProofApi as EvmProofApiServer;
+
+/// uniarts_evm proof rpc interface.
+#[rpc(server)]
+pub trait EvmProofApi {
+	/// Returns an EVM account and the given storage slots with their proofs.
+	/// These are not EIP-1186 proofs: the EVM state is stored in the Substrate
+	/// state, so the proofs are nodes of the Substrate state trie of the block
+	/// and are checked against its Substrate state root.
+	#[rpc(name = "uniarts_evm_proof")]
+	fn proof(&self, address: H160, storage_keys: Vec<U256>, at: Option<H256>) -> Result<EvmAccountProof>;
+}
@@ -20,10 +20,20 @@ pub mod types;
 
 mod eth;
 mod eth_pubsub;
+mod evm_account;
+mod evm_proof;
 mod net;
+mod personal;
+mod trace;
+mod txpool;
 mod web3;
 
-pub use eth::{EthApi, EthApiServer, EthFilterApi, EthFilterApiServer};
+pub use eth::{EthApi, EthApiServer, EthFilterApi, EthFilterApiServer, EthLightApi, EthLightApiServer};
 pub use eth_pubsub::{EthPubSubApi, EthPubSubApiServer};
+pub use evm_account::{EvmAccountApi, EvmAccountApiServer};
+pub use evm_proof::{EvmProofApi, EvmProofApiServer};
 pub use net::{NetApi, NetApiServer};
+pub use personal::{PersonalApi, PersonalApiServer};
+pub use trace::{TraceApi, TraceApiServer};
+pub use txpool::{TxPoolApi, TxPoolApiServer};
 pub use web3::{Web3Api, Web3ApiServer};
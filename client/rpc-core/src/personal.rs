@@ -0,0 +1,40 @@
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+// This file is part of Frontier.
+//
+// Copyright (c) 2015-2020 Parity Technologies (UK) Ltd.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+//! Personal rpc interface.
+use ethereum_types::H160;
+use jsonrpc_core::Result;
+use jsonrpc_derive::rpc;
+
+pub use rpc_impl_PersonalApi::gen_server::PersonalApi as PersonalApiServer;
+
+/// Personal rpc interface, managing the accounts of the node's keystore.
+#[rpc(server)]
+pub trait PersonalApi {
+	/// Creates an account in the keystore, returning its address.
+	#[rpc(name = "personal_newAccount")]
+	fn new_account(&self, _: String) -> Result<H160>;
+
+	/// Unlocks an account for signing, for the given number of seconds
+	/// (300 by default, 0 until the node stops).
+	#[rpc(name = "personal_unlockAccount")]
+	fn unlock_account(&self, _: H160, _: String, _: Option<u64>) -> Result<bool>;
+
+	/// Returns the accounts of the keystore.
+	#[rpc(name = "personal_listAccounts")]
+	fn list_accounts(&self) -> Result<Vec<H160>>;
+}
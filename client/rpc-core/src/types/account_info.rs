@@ -51,6 +51,22 @@ pub struct EthAccount {
 	pub storage_proof: Vec<StorageProof>,
 }
 
+/// EVM account with the proofs of its Substrate storage (`uniarts_evm_proof`).
+/// The proofs are nodes of the Substrate state trie of `block_hash`, checked
+/// against its `state_root`.
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EvmAccountProof {
+	pub address: H160,
+	pub balance: U256,
+	pub nonce: U256,
+	pub code_hash: H256,
+	pub block_hash: H256,
+	pub state_root: H256,
+	pub account_proof: Vec<Bytes>,
+	pub storage_proof: Vec<StorageProof>,
+}
+
 /// Extended account information (used by `parity_allAccountInfo`).
 #[derive(Debug, Default, Clone, PartialEq, Serialize)]
 pub struct ExtAccountInfo {
@@ -0,0 +1,36 @@
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+// This file is part of Frontier.
+//
+// Copyright (c) 2015-2020 Parity Technologies (UK) Ltd.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! `uniarts_evm` types
+
+use serde::Serialize;
+use ethereum_types::{H160, U256};
+
+/// Balances of a Substrate account and of its EVM address.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EvmBalances {
+	/// EVM address of the account, if it has one.
+	pub address: Option<H160>,
+	/// Free balance of the Substrate account.
+	pub substrate: U256,
+	/// Balance of the EVM address, held by the account it maps to.
+	pub evm: U256,
+	/// Sum of both balances.
+	pub total: U256,
+}
@@ -15,7 +15,7 @@
 //
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
-use std::{sync::{Arc, Mutex}, collections::BTreeMap};
+use std::{sync::{Arc, Mutex}, collections::BTreeMap, time::Instant};
 use ethereum_types::{H160, H256, U256};
 use serde::de::{Error, DeserializeOwned};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
@@ -329,7 +329,8 @@ impl Serialize for FilterChanges {
 #[derive(Debug, Clone)]
 pub enum FilterType {
 	Block,
-	PendingTransaction,
+	/// Hashes of the Ethereum transactions entered in the pool since last poll.
+	PendingTransaction(Vec<H256>),
 	Log(Filter)
 }
 
@@ -337,7 +338,12 @@ pub enum FilterType {
 pub struct FilterPoolItem {
 	pub last_poll: BlockNumber,
 	pub filter_type: FilterType,
-	pub at_block: u64
+	pub at_block: u64,
+	/// Time of the creation or of the last poll of the filter.
+	pub last_activity: Instant,
+	/// Connection which installed the filter, `None` for connections without
+	/// a session such as HTTP ones.
+	pub owner: Option<u64>,
 }
 
 /// On-memory stored filters created through the `eth_newFilter` RPC.
@@ -23,22 +23,29 @@ mod block;
 mod block_number;
 mod bytes;
 mod call_request;
+mod evm_account;
 mod filter;
 mod index;
 mod log;
 mod receipt;
 mod sync;
+mod trace;
 mod transaction;
 mod transaction_request;
+mod txpool;
+mod typed_data;
 mod work;
 
 pub mod pubsub;
 
-pub use self::account_info::{AccountInfo, ExtAccountInfo, EthAccount, StorageProof, RecoveredAccount};
+pub use self::account_info::{
+	AccountInfo, ExtAccountInfo, EthAccount, EvmAccountProof, StorageProof, RecoveredAccount,
+};
 pub use self::bytes::Bytes;
 pub use self::block::{RichBlock, Block, BlockTransactions, Header, RichHeader, Rich};
 pub use self::block_number::BlockNumber;
 pub use self::call_request::CallRequest;
+pub use self::evm_account::EvmBalances;
 pub use self::filter::{
 	Filter, FilterChanges, FilterPool, FilterPoolItem, FilterType, VariadicValue, FilterAddress,
 	Topic, FilteredParams,
@@ -50,8 +57,14 @@ pub use self::sync::{
 	SyncStatus, SyncInfo, Peers, PeerInfo, PeerNetworkInfo, PeerProtocolsInfo,
 	TransactionStats, ChainStatus, EthProtocolInfo, PipProtocolInfo,
 };
+pub use self::trace::{
+	Action, CallAction, CallResult, CallType, CreateAction, CreateResult, LocalizedTrace, TraceFilter,
+	TraceResult, TraceType,
+};
 pub use self::transaction::{
 	Transaction, RichRawTransaction, LocalTransactionStatus, PendingTransactions, PendingTransaction,
 };
 pub use self::transaction_request::TransactionRequest;
+pub use self::txpool::{TransactionMap, TxPoolResult, Summary};
+pub use self::typed_data::{TypedData, TypedDataField};
 pub use self::work::Work;
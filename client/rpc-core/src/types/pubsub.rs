@@ -22,7 +22,7 @@ use ethereum_types::H256;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use serde::de::Error;
 use serde_json::{Value, from_value};
-use crate::types::{RichHeader, Filter, Log};
+use crate::types::{RichHeader, Filter, Log, SyncInfo};
 
 /// Subscription result.
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -43,6 +43,9 @@ pub enum Result {
 pub struct PubSubSyncStatus {
 	/// is_major_syncing?
 	pub syncing: bool,
+	/// Sync progress, when syncing.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub status: Option<SyncInfo>,
 }
 
 impl Serialize for Result {
@@ -21,7 +21,7 @@ use ethereum_types::{H160, H256, U64, U256, Bloom as H2048};
 use crate::types::Log;
 
 /// Receipt
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct Receipt {
 	/// Transaction Hash
@@ -40,6 +40,8 @@ pub struct Receipt {
 	pub cumulative_gas_used: U256,
 	/// Gas used
 	pub gas_used: Option<U256>,
+	/// Gas price paid per unit of gas
+	pub effective_gas_price: U256,
 	/// Contract address
 	pub contract_address: Option<H160>,
 	/// Logs
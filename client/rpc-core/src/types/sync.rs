@@ -22,7 +22,7 @@ use ethereum_types::{U256, H512};
 use serde::{Serialize, Serializer};
 
 /// Sync info
-#[derive(Default, Debug, Serialize, PartialEq)]
+#[derive(Default, Debug, Clone, Serialize, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
 pub struct SyncInfo {
 	/// Starting block
@@ -35,6 +35,10 @@ pub struct SyncInfo {
 	pub warp_chunks_amount: Option<U256>,
 	/// Warp sync snpashot chunks processed.
 	pub warp_chunks_processed: Option<U256>,
+	/// Number of blocks the best block is ahead of the last finalized one.
+	/// Substrate specific, omitted when unknown.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub finalization_lag: Option<U256>,
 }
 
 /// Peers info
@@ -107,7 +111,7 @@ pub struct PipProtocolInfo {
 }
 
 /// Sync status
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum SyncStatus {
 	/// Info when syncing
 	Info(SyncInfo),
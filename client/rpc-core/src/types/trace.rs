@@ -0,0 +1,169 @@
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+// This file is part of Frontier.
+//
+// Copyright (c) 2015-2020 Parity Technologies (UK) Ltd.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! `trace` types
+
+use serde::{Serialize, Deserialize};
+use ethereum_types::{H160, H256, U256};
+
+use crate::types::BlockNumber;
+
+/// Kind of a traced call frame.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CallType {
+	/// Message call.
+	Call,
+	/// Message call which can't modify state.
+	StaticCall,
+	/// Call running the code of the callee in the context of the caller.
+	DelegateCall,
+	/// Call running the code of the callee on the storage of the caller.
+	CallCode,
+}
+
+/// Action of a message call frame. The call input is not recorded.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CallAction {
+	/// Kind of the call
+	pub call_type: CallType,
+	/// Caller
+	pub from: H160,
+	/// Callee
+	pub to: H160,
+	/// Value transferred
+	pub value: U256,
+	/// Gas limit
+	pub gas: U256,
+}
+
+/// Action of a contract creation frame. The init code is not recorded.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateAction {
+	/// Creator
+	pub from: H160,
+	/// Endowment of the contract
+	pub value: U256,
+	/// Gas limit
+	pub gas: U256,
+}
+
+/// Action of a traced frame.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum Action {
+	/// Message call
+	Call(CallAction),
+	/// Contract creation
+	Create(CreateAction),
+}
+
+/// Result of a message call frame. The call output is not recorded.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CallResult {
+	/// Gas used, including subcalls
+	pub gas_used: U256,
+}
+
+/// Result of a contract creation frame. The deployed code is not recorded.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateResult {
+	/// Address of the created contract
+	pub address: H160,
+	/// Gas used, including subcalls
+	pub gas_used: U256,
+}
+
+/// Result of a successful traced frame.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum TraceResult {
+	/// Message call
+	Call(CallResult),
+	/// Contract creation
+	Create(CreateResult),
+}
+
+/// Kind of a traced frame.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TraceType {
+	/// Message call
+	Call,
+	/// Contract creation
+	Create,
+}
+
+/// Call frame of a transaction, located in the chain.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LocalizedTrace {
+	/// Action of the frame
+	pub action: Action,
+	/// Result of the frame, `None` if it failed
+	pub result: Option<TraceResult>,
+	/// Failure of the frame
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub error: Option<String>,
+	/// Number of direct subcalls
+	pub subtraces: u32,
+	/// Indices of the subcalls leading from the top-level call to this frame
+	pub trace_address: Vec<u32>,
+	/// Kind of the frame
+	#[serde(rename = "type")]
+	pub trace_type: TraceType,
+	/// Hash of the block
+	pub block_hash: H256,
+	/// Number of the block
+	pub block_number: U256,
+	/// Hash of the transaction
+	pub transaction_hash: H256,
+	/// Index of the transaction in the block
+	pub transaction_position: u32,
+}
+
+/// Filter of `trace_filter`.
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "camelCase")]
+pub struct TraceFilter {
+	/// From block, the latest block by default
+	pub from_block: Option<BlockNumber>,
+	/// To block, the latest block by default
+	pub to_block: Option<BlockNumber>,
+	/// Callers to match, any by default
+	pub from_address: Option<Vec<H160>>,
+	/// Callees to match, any by default
+	pub to_address: Option<Vec<H160>>,
+	/// Number of matching traces to skip
+	pub after: Option<usize>,
+	/// Maximum number of traces to return
+	pub count: Option<usize>,
+}
+
+impl TraceFilter {
+	/// Whether a frame from `from` to `to` matches the address filters.
+	pub fn matches(&self, from: &H160, to: &H160) -> bool {
+		self.from_address.as_ref().map_or(true, |addresses| addresses.is_empty() || addresses.contains(from)) &&
+			self.to_address.as_ref().map_or(true, |addresses| addresses.is_empty() || addresses.contains(to))
+	}
+}
@@ -0,0 +1,59 @@
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+// This file is part of Frontier.
+//
+// Copyright (c) 2015-2020 Parity Technologies (UK) Ltd.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+//! `txpool` types
+
+use std::collections::BTreeMap;
+use serde::{Serialize, Serializer};
+use ethereum_types::{H160, U256};
+
+/// Pool transactions by sender, then by decimal nonce.
+pub type TransactionMap<T> = BTreeMap<H160, BTreeMap<String, T>>;
+
+/// Result of the `txpool` RPCs, split between executable and future transactions.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize)]
+pub struct TxPoolResult<T> {
+	/// Transactions ready to be included in a block.
+	pub pending: T,
+	/// Transactions waiting for a previous nonce.
+	pub queued: T,
+}
+
+/// Summary of a pool transaction, serialized as in `txpool_inspect`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Summary {
+	/// Recipient, `None` for a contract creation
+	pub to: Option<H160>,
+	/// Value of transaction in wei
+	pub value: U256,
+	/// Gas limit
+	pub gas: U256,
+	/// Gas price
+	pub gas_price: U256,
+}
+
+impl Serialize for Summary {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
+		let to = match self.to {
+			Some(to) => format!("{:?}", to),
+			None => "contract creation".to_string(),
+		};
+		serializer.serialize_str(&format!(
+			"{}: {} wei + {} gas × {} wei", to, self.value, self.gas, self.gas_price
+		))
+	}
+}
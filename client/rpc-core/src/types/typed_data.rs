@@ -0,0 +1,46 @@
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+// This file is part of Frontier.
+//
+// Copyright (c) 2015-2020 Parity Technologies (UK) Ltd.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+//! `TypedData` type
+
+use std::collections::BTreeMap;
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+
+/// Member of a struct type of EIP-712 typed data.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct TypedDataField {
+	/// Member name
+	pub name: String,
+	/// Member type
+	#[serde(rename = "type")]
+	pub type_: String,
+}
+
+/// EIP-712 typed data, as signed by `eth_signTypedData_v4`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TypedData {
+	/// Struct types, including `EIP712Domain`
+	pub types: BTreeMap<String, Vec<TypedDataField>>,
+	/// Type of `message`
+	pub primary_type: String,
+	/// Domain separator values
+	pub domain: Value,
+	/// Message to sign
+	pub message: Value,
+}
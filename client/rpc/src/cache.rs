@@ -0,0 +1,282 @@
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+// This file is part of Frontier.
+//
+// Copyright (c) 2020 Parity Technologies (UK) Ltd.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Cache of the decoded Ethereum data of recent blocks, shared by the
+//! Ethereum RPC handlers.
+
+use std::sync::{Arc, Mutex};
+use codec::Decode;
+use ethereum_types::H256;
+use lru::LruCache;
+use prometheus_endpoint::{register, CounterVec, Opts, PrometheusError, Registry, U64};
+use sp_runtime::{traits::{Block as BlockT, BlakeTwo256}, generic::BlockId};
+use sp_storage::StorageKey;
+use sp_api::ProvideRuntimeApi;
+use sc_client_api::backend::{Backend, StateBackend, StorageProvider};
+use fp_rpc::{EthereumRuntimeRPCApi, TransactionStatus, TransactionReceipt};
+use crate::storage_prefix_build;
+
+/// Hit and miss counters of the cache, by kind of data.
+struct CacheMetrics {
+	hits: CounterVec<U64>,
+	misses: CounterVec<U64>,
+}
+
+impl CacheMetrics {
+	fn register(registry: &Registry) -> Result<Self, PrometheusError> {
+		Ok(Self {
+			hits: register(
+				CounterVec::new(
+					Opts::new("frontier_rpc_block_data_cache_hits", "Ethereum block data served from the RPC cache"),
+					&["kind"],
+				)?,
+				registry,
+			)?,
+			misses: register(
+				CounterVec::new(
+					Opts::new("frontier_rpc_block_data_cache_misses", "Ethereum block data decoded by the RPC"),
+					&["kind"],
+				)?,
+				registry,
+			)?,
+		})
+	}
+}
+
+/// Ethereum blocks, transaction statuses and receipts of recent blocks, keyed
+/// by Substrate block hash.
+pub struct EthBlockDataCache<B: BlockT> {
+	backend: Arc<fc_db::Backend<B>>,
+	/// Caches of each kind of data, none if the cache size is 0.
+	blocks: Option<Mutex<LruCache<H256, ethereum::Block>>>,
+	statuses: Option<Mutex<LruCache<H256, Vec<TransactionStatus>>>>,
+	receipts: Option<Mutex<LruCache<H256, Vec<TransactionReceipt>>>>,
+	metrics: Option<CacheMetrics>,
+}
+
+impl<B: BlockT<Hash=H256>> EthBlockDataCache<B> {
+	/// Create a cache of the data of `size` blocks, reporting its hit rate to
+	/// `registry` if any. Nothing is cached if `size` is 0.
+	pub fn new(
+		backend: Arc<fc_db::Backend<B>>,
+		size: usize,
+		registry: Option<&Registry>,
+	) -> Result<Self, PrometheusError> {
+		fn lru<T>(size: usize) -> Option<Mutex<LruCache<H256, T>>> {
+			if size == 0 { None } else { Some(Mutex::new(LruCache::new(size))) }
+		}
+
+		Ok(Self {
+			backend,
+			blocks: lru(size),
+			statuses: lru(size),
+			receipts: lru(size),
+			metrics: registry.map(CacheMetrics::register).transpose()?,
+		})
+	}
+
+	/// Ethereum block of a Substrate block.
+	pub fn current_block<C, BE>(&self, client: &C, hash: H256) -> Option<ethereum::Block> where
+		C: StorageProvider<B, BE>,
+		BE: Backend<B>,
+		BE::State: StateBackend<BlakeTwo256>,
+	{
+		self.get(&self.blocks, "block", hash, |ethereum_hash| {
+			self.backend.block(ethereum_hash)
+		}, || {
+			let data = state_storage(client, hash, b"CurrentBlock")?;
+			Decode::decode(&mut &data[..]).ok()
+		})
+	}
+
+	/// Ethereum transaction statuses of a Substrate block.
+	pub fn current_statuses<C, BE>(&self, client: &C, hash: H256) -> Option<Vec<TransactionStatus>> where
+		C: StorageProvider<B, BE>,
+		BE: Backend<B>,
+		BE::State: StateBackend<BlakeTwo256>,
+	{
+		self.get(&self.statuses, "statuses", hash, |ethereum_hash| {
+			self.backend.statuses(ethereum_hash)
+		}, || {
+			let data = state_storage(client, hash, b"CurrentTransactionStatuses")?;
+			Decode::decode(&mut &data[..]).ok()
+		})
+	}
+
+	/// Ethereum transaction receipts of a Substrate block. Runtimes before
+	/// version 2 of `EthereumRuntimeRPCApi` stored them as `ethereum::Receipt`.
+	pub fn current_receipts<C, BE>(&self, client: &C, hash: H256) -> Option<Vec<TransactionReceipt>> where
+		C: StorageProvider<B, BE> + ProvideRuntimeApi<B>,
+		C::Api: EthereumRuntimeRPCApi<B>,
+		BE: Backend<B>,
+		BE::State: StateBackend<BlakeTwo256>,
+	{
+		self.get(&self.receipts, "receipts", hash, |ethereum_hash| {
+			self.backend.receipts(ethereum_hash)
+		}, || {
+			let data = state_storage(client, hash, b"CurrentReceipts")?;
+			let supported = client.runtime_api()
+				.has_api_with::<dyn EthereumRuntimeRPCApi<B>, _>(&BlockId::Hash(hash), |version| version >= 2)
+				.ok()?;
+			if supported {
+				return Decode::decode(&mut &data[..]).ok()
+			}
+			let receipts: Vec<ethereum::Receipt> = Decode::decode(&mut &data[..]).ok()?;
+			let block = self.current_block(client, hash)?;
+			Some(TransactionReceipt::from_legacy(&block, receipts))
+		})
+	}
+
+	/// Data of block `hash`, read from the mapping database or else the
+	/// block state.
+	fn get<T, F, S>(
+		&self,
+		cache: &Option<Mutex<LruCache<H256, T>>>,
+		kind: &str,
+		hash: H256,
+		from_database: F,
+		from_state: S,
+	) -> Option<T> where
+		T: Clone,
+		F: FnOnce(&H256) -> Result<Option<T>, String>,
+		S: FnOnce() -> Option<T>,
+	{
+		let cached = cache.as_ref()
+			.and_then(|cache| cache.lock().ok())
+			.and_then(|mut cache| cache.get(&hash).cloned());
+		if let Some(data) = cached {
+			if let Some(metrics) = &self.metrics {
+				metrics.hits.with_label_values(&[kind]).inc();
+			}
+			return Some(data);
+		}
+		if let Some(metrics) = &self.metrics {
+			metrics.misses.with_label_values(&[kind]).inc();
+		}
+
+		let data = self.backend.ethereum_hash(&hash).ok().flatten()
+			.and_then(|ethereum_hash| from_database(&ethereum_hash).ok().flatten())
+			.or_else(from_state)?;
+
+		if let Some(Ok(mut cache)) = cache.as_ref().map(Mutex::lock) {
+			cache.put(hash, data.clone());
+		}
+		Some(data)
+	}
+}
+
+/// Raw value of the `pallet_ethereum` storage item `storage` at block `hash`.
+fn state_storage<B, C, BE>(client: &C, hash: H256, storage: &[u8]) -> Option<Vec<u8>> where
+	B: BlockT<Hash=H256>,
+	C: StorageProvider<B, BE>,
+	BE: Backend<B>,
+	BE::State: StateBackend<BlakeTwo256>,
+{
+	let key = StorageKey(storage_prefix_build(b"Ethereum", storage));
+	client.storage(&BlockId::Hash(hash), &key).ok().flatten().map(|data| data.0)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::cell::Cell;
+	use sp_runtime::testing::{Block as RawBlock, ExtrinsicWrapper};
+
+	type Block = RawBlock<ExtrinsicWrapper<u64>>;
+
+	fn cache(size: usize, registry: Option<&Registry>) -> EthBlockDataCache<Block> {
+		EthBlockDataCache::new(Arc::new(fc_db::Backend::new_in_memory()), size, registry).unwrap()
+	}
+
+	fn statuses(hash: u8) -> Vec<TransactionStatus> {
+		vec![TransactionStatus { transaction_hash: H256::repeat_byte(hash), ..Default::default() }]
+	}
+
+	/// Statuses of block `hash`, counting the reads of the block state.
+	fn get_statuses(cache: &EthBlockDataCache<Block>, hash: H256, state_reads: &Cell<u32>) -> Option<Vec<TransactionStatus>> {
+		cache.get(&cache.statuses, "statuses", hash, |_| Ok(None), || {
+			state_reads.set(state_reads.get() + 1);
+			Some(statuses(hash.as_bytes()[0]))
+		})
+	}
+
+	#[test]
+	fn data_is_read_once_then_cached() {
+		let registry = Registry::new();
+		let cache = cache(2, Some(&registry));
+		let state_reads = Cell::new(0);
+
+		assert_eq!(get_statuses(&cache, H256::repeat_byte(1), &state_reads), Some(statuses(1)));
+		assert_eq!(get_statuses(&cache, H256::repeat_byte(1), &state_reads), Some(statuses(1)));
+		assert_eq!(state_reads.get(), 1);
+
+		let metrics = cache.metrics.as_ref().unwrap();
+		assert_eq!(metrics.hits.with_label_values(&["statuses"]).get(), 1);
+		assert_eq!(metrics.misses.with_label_values(&["statuses"]).get(), 1);
+	}
+
+	#[test]
+	fn least_recently_used_data_is_evicted() {
+		let cache = cache(1, None);
+		let state_reads = Cell::new(0);
+
+		get_statuses(&cache, H256::repeat_byte(1), &state_reads);
+		get_statuses(&cache, H256::repeat_byte(2), &state_reads);
+		get_statuses(&cache, H256::repeat_byte(1), &state_reads);
+		assert_eq!(state_reads.get(), 3);
+	}
+
+	#[test]
+	fn size_zero_disables_the_cache() {
+		let cache = cache(0, None);
+		let state_reads = Cell::new(0);
+
+		assert_eq!(get_statuses(&cache, H256::repeat_byte(1), &state_reads), Some(statuses(1)));
+		assert_eq!(get_statuses(&cache, H256::repeat_byte(1), &state_reads), Some(statuses(1)));
+		assert_eq!(state_reads.get(), 2);
+	}
+
+	#[test]
+	fn mapping_database_is_read_before_the_state() {
+		let cache = cache(1, None);
+		let hash = H256::repeat_byte(1);
+		let ethereum_hash = H256::repeat_byte(2);
+		let partial_header = ethereum::PartialHeader {
+			parent_hash: H256::default(),
+			beneficiary: Default::default(),
+			state_root: H256::default(),
+			receipts_root: H256::default(),
+			logs_bloom: Default::default(),
+			difficulty: Default::default(),
+			number: Default::default(),
+			gas_limit: Default::default(),
+			gas_used: Default::default(),
+			timestamp: 0,
+			extra_data: Vec::new(),
+			mix_hash: H256::default(),
+			nonce: Default::default(),
+		};
+		let block = ethereum::Block::new(partial_header, Vec::new(), Vec::new());
+		cache.backend.write_block(hash, ethereum_hash, &block, &[], &statuses(3), true).unwrap();
+
+		let data = cache.get(&cache.statuses, "statuses", hash, |ethereum_hash| {
+			cache.backend.statuses(ethereum_hash)
+		}, || panic!("the state is not read when the mapping database has the data"));
+		assert_eq!(data, Some(statuses(3)));
+	}
+}
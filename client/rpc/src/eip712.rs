@@ -0,0 +1,224 @@
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+// This file is part of Frontier.
+//
+// Copyright (c) 2020 Parity Technologies (UK) Ltd.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+//! EIP-712 hashing of typed structured data.
+
+use std::collections::BTreeSet;
+use ethereum_types::{H160, H256, U256};
+use jsonrpc_core::serde_json::Value;
+use rustc_hex::FromHex;
+use sha3::{Digest, Keccak256};
+use fc_rpc_core::types::TypedData;
+
+const DOMAIN_TYPE: &str = "EIP712Domain";
+
+fn keccak_256(data: &[u8]) -> H256 {
+	H256::from_slice(Keccak256::digest(data).as_slice())
+}
+
+/// Hash signed for typed data:
+/// `keccak256("\x19\x01" ++ hashStruct(domain) ++ hashStruct(message))`.
+pub fn hash_typed_data(data: &TypedData) -> Result<H256, String> {
+	let mut encoded = vec![0x19, 0x01];
+	encoded.extend_from_slice(hash_struct(data, DOMAIN_TYPE, &data.domain)?.as_bytes());
+	if data.primary_type != DOMAIN_TYPE {
+		encoded.extend_from_slice(hash_struct(data, &data.primary_type, &data.message)?.as_bytes());
+	}
+	Ok(keccak_256(&encoded))
+}
+
+fn hash_struct(data: &TypedData, type_name: &str, value: &Value) -> Result<H256, String> {
+	let fields = data.types.get(type_name)
+		.ok_or_else(|| format!("unknown type {}", type_name))?;
+
+	let mut encoded = keccak_256(encode_type(data, type_name)?.as_bytes()).as_bytes().to_vec();
+	for field in fields {
+		let member = value.get(&field.name).unwrap_or(&Value::Null);
+		encoded.extend_from_slice(encode_value(data, &field.type_, member)?.as_bytes());
+	}
+	Ok(keccak_256(&encoded))
+}
+
+/// The struct type followed by the struct types it references, sorted by name.
+fn encode_type(data: &TypedData, type_name: &str) -> Result<String, String> {
+	let mut dependencies = BTreeSet::new();
+	collect_dependencies(data, type_name, &mut dependencies);
+	dependencies.remove(type_name);
+
+	let mut encoded = String::new();
+	for name in std::iter::once(type_name).chain(dependencies.iter().map(String::as_str)) {
+		let fields = data.types.get(name)
+			.ok_or_else(|| format!("unknown type {}", name))?;
+		let members: Vec<String> = fields.iter()
+			.map(|field| format!("{} {}", field.type_, field.name))
+			.collect();
+		encoded.push_str(&format!("{}({})", name, members.join(",")));
+	}
+	Ok(encoded)
+}
+
+fn collect_dependencies(data: &TypedData, type_name: &str, dependencies: &mut BTreeSet<String>) {
+	let name = type_name.split('[').next().unwrap_or(type_name);
+	if dependencies.contains(name) {
+		return
+	}
+	if let Some(fields) = data.types.get(name) {
+		dependencies.insert(name.to_string());
+		for field in fields {
+			collect_dependencies(data, &field.type_, dependencies);
+		}
+	}
+}
+
+fn encode_value(data: &TypedData, type_name: &str, value: &Value) -> Result<H256, String> {
+	if type_name.ends_with(']') {
+		let element_type = &type_name[..type_name.rfind('[').unwrap_or(0)];
+		let elements = value.as_array()
+			.ok_or_else(|| format!("expected an array of {}", element_type))?;
+		let mut encoded = Vec::new();
+		for element in elements {
+			encoded.extend_from_slice(encode_value(data, element_type, element)?.as_bytes());
+		}
+		return Ok(keccak_256(&encoded))
+	}
+	if data.types.contains_key(type_name) {
+		return hash_struct(data, type_name, value)
+	}
+
+	match type_name {
+		"string" => Ok(keccak_256(value.as_str().unwrap_or_default().as_bytes())),
+		"bytes" => Ok(keccak_256(&parse_bytes(value)?)),
+		"bool" => Ok(u256_word(U256::from(value.as_bool().unwrap_or_default() as u8))),
+		"address" => {
+			let bytes = parse_bytes(value)?;
+			if bytes.len() != 20 {
+				return Err(format!("invalid address {}", value))
+			}
+			Ok(H160::from_slice(&bytes).into())
+		},
+		_ if type_name.starts_with("bytes") => {
+			let bytes = parse_bytes(value)?;
+			if bytes.len() > 32 {
+				return Err(format!("invalid {} {}", type_name, value))
+			}
+			let mut word = H256::zero();
+			word.as_bytes_mut()[..bytes.len()].copy_from_slice(&bytes);
+			Ok(word)
+		},
+		_ if type_name.starts_with("uint") || type_name.starts_with("int") =>
+			Ok(u256_word(parse_integer(value)?)),
+		_ => Err(format!("unknown type {}", type_name)),
+	}
+}
+
+fn u256_word(value: U256) -> H256 {
+	let mut word = H256::zero();
+	value.to_big_endian(word.as_bytes_mut());
+	word
+}
+
+fn parse_bytes(value: &Value) -> Result<Vec<u8>, String> {
+	let hex = value.as_str().ok_or_else(|| format!("expected hex data, got {}", value))?;
+	hex.trim_start_matches("0x").from_hex()
+		.map_err(|_| format!("invalid hex data {}", hex))
+}
+
+/// A decimal or `0x`-prefixed hex integer, negative ones in two's complement.
+fn parse_integer(value: &Value) -> Result<U256, String> {
+	let invalid = || format!("invalid integer {}", value);
+	let (negative, magnitude) = match value {
+		Value::Number(number) => match (number.as_u64(), number.as_i64()) {
+			(Some(n), _) => (false, U256::from(n)),
+			(None, Some(n)) => (true, U256::from(-(n as i128) as u128)),
+			_ => return Err(invalid()),
+		},
+		Value::String(string) => {
+			let (negative, digits) = match string.strip_prefix('-') {
+				Some(digits) => (true, digits),
+				None => (false, string.as_str()),
+			};
+			let magnitude = match digits.strip_prefix("0x") {
+				Some(hex) => hex.parse::<U256>().map_err(|_| invalid())?,
+				None => U256::from_dec_str(digits).map_err(|_| invalid())?,
+			};
+			(negative, magnitude)
+		},
+		_ => return Err(invalid()),
+	};
+	Ok(if negative { (!magnitude).overflowing_add(U256::one()).0 } else { magnitude })
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use jsonrpc_core::serde_json;
+
+	#[test]
+	fn hashes_eip712_example() {
+		let data: TypedData = serde_json::from_str(r#"{
+			"types": {
+				"EIP712Domain": [
+					{ "name": "name", "type": "string" },
+					{ "name": "version", "type": "string" },
+					{ "name": "chainId", "type": "uint256" },
+					{ "name": "verifyingContract", "type": "address" }
+				],
+				"Person": [
+					{ "name": "name", "type": "string" },
+					{ "name": "wallet", "type": "address" }
+				],
+				"Mail": [
+					{ "name": "from", "type": "Person" },
+					{ "name": "to", "type": "Person" },
+					{ "name": "contents", "type": "string" }
+				]
+			},
+			"primaryType": "Mail",
+			"domain": {
+				"name": "Ether Mail",
+				"version": "1",
+				"chainId": 1,
+				"verifyingContract": "0xCcCCccccCCCCcCCCCCCcCcCccCcCCCcCcccccccC"
+			},
+			"message": {
+				"from": { "name": "Cow", "wallet": "0xCD2a3d9F938E13CD947Ec05AbC7FE734Df8DD826" },
+				"to": { "name": "Bob", "wallet": "0xbBbBBBBbbBBBbbbBbbBbbbbBBbBbbbbBbBbbBBbB" },
+				"contents": "Hello, Bob!"
+			}
+		}"#).unwrap();
+
+		assert_eq!(
+			encode_type(&data, "Mail").unwrap(),
+			"Mail(Person from,Person to,string contents)Person(string name,address wallet)",
+		);
+		assert_eq!(
+			hash_struct(&data, DOMAIN_TYPE, &data.domain).unwrap(),
+			"f2cee375fa42b42143804025fc449deafd50cc031ca257e0b194a650a912090f".parse().unwrap(),
+		);
+		assert_eq!(
+			hash_typed_data(&data).unwrap(),
+			"be609aee343fb3c4b28e1df9e632fca64fcfaede20f02e86244efddf30957bd2".parse().unwrap(),
+		);
+	}
+
+	#[test]
+	fn encodes_negative_integers_in_twos_complement() {
+		assert_eq!(parse_integer(&Value::from(-1)).unwrap(), U256::max_value());
+		assert_eq!(parse_integer(&Value::from("-0x2")).unwrap(), U256::max_value() - 1);
+		assert_eq!(parse_integer(&Value::from("0x10")).unwrap(), U256::from(16));
+	}
+}
@@ -16,11 +16,12 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 use std::{marker::PhantomData, sync::Arc};
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
+use std::time::Instant;
 use ethereum::{
 	Block as EthereumBlock, Transaction as EthereumTransaction
 };
-use ethereum_types::{H160, H256, H64, U256, U64, H512};
+use ethereum_types::{H160, H256, H64, U256, U64, H512, Bloom, BloomInput};
 use jsonrpc_core::{BoxFuture, Result, futures::future::{self, Future}};
 use futures::future::TryFutureExt;
 use sp_runtime::{
@@ -28,13 +29,13 @@ use sp_runtime::{
 	transaction_validity::TransactionSource
 };
 use sp_api::{ProvideRuntimeApi, BlockId, Core, HeaderT};
-use sp_transaction_pool::{TransactionPool, InPoolTransaction};
+use sp_transaction_pool::{TransactionPool, InPoolTransaction, error::Error as PoolError};
 use sc_client_api::backend::{StorageProvider, Backend, StateBackend, AuxStore};
 use sha3::{Keccak256, Digest};
 use sp_blockchain::{Error as BlockChainError, HeaderMetadata, HeaderBackend};
 use sp_storage::StorageKey;
 use codec::Decode;
-use sp_io::hashing::{twox_128, blake2_128};
+use sp_io::hashing::blake2_128;
 use sc_network::{NetworkService, ExHashT};
 use fc_rpc_core::{
 	EthApi as EthApiT, NetApi as NetApiT, Web3Api as Web3ApiT, EthFilterApi as EthFilterApiT
@@ -42,11 +43,19 @@ use fc_rpc_core::{
 use fc_rpc_core::types::{
 	BlockNumber, Bytes, CallRequest, Filter, FilteredParams, FilterChanges, FilterPool, FilterPoolItem,
 	FilterType, Index, Log, Receipt, RichBlock, SyncStatus, SyncInfo, Transaction, Work, Rich, Block,
-	BlockTransactions, TransactionRequest, PendingTransactions, PendingTransaction,
+	BlockTransactions, TransactionRequest, PendingTransactions, PendingTransaction, VariadicValue,
+	TypedData,
+};
+use fc_db::{LogIndexKey, LOG_BLOOM_RANGE};
+use fp_rpc::{EthereumRuntimeRPCApi, ConvertTransaction, TransactionStatus, TransactionReceipt};
+use sc_transaction_graph::{ChainApi, ExtrinsicHash, Pool};
+use sc_rpc::{DenyUnsafe, Metadata};
+use jsonrpc_pubsub::PubSubMetadata;
+use crate::eip712::hash_typed_data;
+use crate::{
+	internal_err, limit_exceeded_err, pool_err, error_on_execution_failure, storage_prefix_build, EthSigner,
+	EthBlockDataCache, EthNetworkStatus, public_key,
 };
-use fp_rpc::{EthereumRuntimeRPCApi, ConvertTransaction, TransactionStatus};
-use sc_transaction_graph::{ChainApi, Pool};
-use crate::{internal_err, error_on_execution_failure, EthSigner, public_key};
 
 pub use fc_rpc_core::{EthApiServer, NetApiServer, Web3ApiServer, EthFilterApiServer};
 use codec::{self, Encode};
@@ -57,9 +66,16 @@ pub struct EthApi<B: BlockT, C, P, CT, BE, H: ExHashT, A: ChainApi> {
 	client: Arc<C>,
 	convert_transaction: CT,
 	network: Arc<NetworkService<B, H>>,
+	network_status: Arc<EthNetworkStatus>,
 	is_authority: bool,
 	signers: Vec<Box<dyn EthSigner>>,
 	pending_transactions: PendingTransactions,
+	backend: Arc<fc_db::Backend<B>>,
+	block_data_cache: Arc<EthBlockDataCache<B>>,
+	max_past_logs_range: u32,
+	max_logs_count: usize,
+	replacement_bump: u32,
+	deny_unsafe: DenyUnsafe,
 	_marker: PhantomData<(B, BE)>,
 }
 
@@ -70,8 +86,15 @@ impl<B: BlockT, C, P, CT, BE, H: ExHashT, A: ChainApi> EthApi<B, C, P, CT, BE, H
 		graph: Arc<Pool<A>>,
 		convert_transaction: CT,
 		network: Arc<NetworkService<B, H>>,
+		network_status: Arc<EthNetworkStatus>,
 		pending_transactions: PendingTransactions,
 		signers: Vec<Box<dyn EthSigner>>,
+		backend: Arc<fc_db::Backend<B>>,
+		block_data_cache: Arc<EthBlockDataCache<B>>,
+		max_past_logs_range: u32,
+		max_logs_count: usize,
+		replacement_bump: u32,
+		deny_unsafe: DenyUnsafe,
 		is_authority: bool,
 	) -> Self {
 		Self {
@@ -80,15 +103,64 @@ impl<B: BlockT, C, P, CT, BE, H: ExHashT, A: ChainApi> EthApi<B, C, P, CT, BE, H
 			graph,
 			convert_transaction,
 			network,
+			network_status,
 			is_authority,
 			signers,
 			pending_transactions,
+			backend,
+			block_data_cache,
+			max_past_logs_range,
+			max_logs_count,
+			replacement_bump,
+			deny_unsafe,
 			_marker: PhantomData,
 		}
 	}
 }
 
-fn rich_block_build(
+/// Message of a transaction request, with the given nonce, chain id and gas
+/// price and limit.
+fn transaction_message(
+	request: TransactionRequest,
+	nonce: U256,
+	gas_price: U256,
+	gas_limit: U256,
+	chain_id: Option<u64>,
+) -> ethereum::TransactionMessage {
+	ethereum::TransactionMessage {
+		nonce,
+		gas_price,
+		gas_limit,
+		value: request.value.unwrap_or(U256::zero()),
+		input: request.data.map(|s| s.into_vec()).unwrap_or_default(),
+		action: match request.to {
+			Some(to) => ethereum::TransactionAction::Call(to),
+			None => ethereum::TransactionAction::Create,
+		},
+		chain_id,
+	}
+}
+
+/// Sender of a signed transaction, if its signature is valid.
+fn transaction_sender(transaction: &EthereumTransaction) -> Option<H160> {
+	public_key(transaction).ok()
+		.map(|pk| H160::from(H256::from_slice(Keccak256::digest(&pk).as_slice())))
+}
+
+/// Checks that a replacement raises the gas price of the replaced transaction
+/// by at least `bump` percent.
+fn check_replacement_price(old: U256, new: U256, bump: u32) -> Result<()> {
+	let min_gas_price = old.saturating_mul(U256::from(100 + bump as u64)) / 100;
+	if new < min_gas_price {
+		return Err(pool_err(PoolError::TooLowPriority {
+			old: old.unique_saturated_into(),
+			new: new.unique_saturated_into(),
+		}));
+	}
+	Ok(())
+}
+
+pub(crate) fn rich_block_build(
 	block: ethereum::Block,
 	statuses: Vec<Option<TransactionStatus>>,
 	hash: Option<H256>,
@@ -148,7 +220,7 @@ fn rich_block_build(
 	}
 }
 
-fn transaction_build(
+pub(crate) fn transaction_build(
 	transaction: EthereumTransaction,
 	block: Option<EthereumBlock>,
 	status: Option<TransactionStatus>
@@ -270,8 +342,131 @@ fn logs_build(
 	ret
 }
 
-fn storage_prefix_build(module: &[u8], storage: &[u8]) -> Vec<u8> {
-	[twox_128(module), twox_128(storage)].concat().to_vec()
+/// Receipt of a transaction of `block`, whose first log is the block's
+/// `first_log_index`-th one.
+fn receipt_build(
+	block: &EthereumBlock,
+	block_hash: H256,
+	status: &TransactionStatus,
+	receipt: &TransactionReceipt,
+	first_log_index: u32,
+) -> Receipt {
+	Receipt {
+		transaction_hash: Some(status.transaction_hash),
+		transaction_index: Some(status.transaction_index.into()),
+		block_hash: Some(block_hash),
+		from: Some(status.from),
+		to: status.to,
+		block_number: Some(block.header.number),
+		cumulative_gas_used: receipt.cumulative_gas_used,
+		gas_used: Some(receipt.used_gas),
+		effective_gas_price: receipt.effective_gas_price,
+		contract_address: status.contract_address,
+		logs: receipt.logs.iter().enumerate().map(|(i, log)| {
+			Log {
+				address: log.address,
+				topics: log.topics.clone(),
+				data: Bytes(log.data.clone()),
+				block_hash: Some(block_hash),
+				block_number: Some(block.header.number),
+				transaction_hash: Some(status.transaction_hash),
+				transaction_index: Some(status.transaction_index.into()),
+				log_index: Some(U256::from(first_log_index + i as u32)),
+				transaction_log_index: Some(U256::from(i)),
+				removed: false,
+			}
+		}).collect(),
+		status_code: Some(U64::from(receipt.status_code)),
+		logs_bloom: receipt.logs_bloom,
+		state_root: None,
+	}
+}
+
+/// Receipt of the `index`-th transaction of `block`.
+fn transaction_receipt_build(
+	block: &EthereumBlock,
+	block_hash: H256,
+	statuses: &[TransactionStatus],
+	receipts: &[TransactionReceipt],
+	index: usize,
+) -> Receipt {
+	let first_log_index = receipts[..index].iter()
+		.map(|r| r.logs.len() as u32)
+		.sum::<u32>();
+	receipt_build(block, block_hash, &statuses[index], &receipts[index], first_log_index)
+}
+
+/// Receipts of all the transactions of `block`.
+fn block_receipts_build(
+	block: &EthereumBlock,
+	block_hash: H256,
+	statuses: &[TransactionStatus],
+	receipts: &[TransactionReceipt],
+) -> Vec<Receipt> {
+	let mut first_log_index = 0u32;
+	statuses.iter().zip(receipts.iter()).map(|(status, receipt)| {
+		let receipt_rpc = receipt_build(block, block_hash, status, receipt, first_log_index);
+		first_log_index += receipt.logs.len() as u32;
+		receipt_rpc
+	}).collect()
+}
+
+/// Logs in block order truncated to `max` results, with the number of the
+/// first block whose logs were left out if any. Only the logs of complete
+/// blocks are returned, unless a single block has more than `max` logs.
+fn truncate_logs(mut logs: Vec<Log>, max: usize) -> (Vec<Log>, Option<u64>) {
+	if logs.len() <= max {
+		return (logs, None);
+	}
+	let block_number = |log: &Log| log.block_number.unwrap_or_default().low_u64();
+	let cut = block_number(&logs[max]);
+	let complete = logs[..max].iter().take_while(|log| block_number(log) < cut).count();
+	if complete > 0 {
+		logs.truncate(complete);
+		(logs, Some(cut))
+	} else {
+		logs.truncate(max);
+		(logs, Some(cut + 1))
+	}
+}
+
+/// Log index keys a filter requires: the addresses, then the topics of each
+/// constrained position. A log can only match if it matches one key of every
+/// set; `logs_build` does the exact matching.
+fn log_index_constraints(filter: &Filter) -> Vec<Vec<LogIndexKey>> {
+	let mut constraints = Vec::new();
+	match &filter.address {
+		Some(VariadicValue::Single(address)) =>
+			constraints.push(vec![LogIndexKey::Address(*address)]),
+		Some(VariadicValue::Multiple(addresses)) if !addresses.is_empty() =>
+			constraints.push(addresses.iter().map(|address| LogIndexKey::Address(*address)).collect()),
+		_ => (),
+	}
+
+	let positions = match &filter.topics {
+		Some(VariadicValue::Single(topic)) => vec![topic.clone()],
+		Some(VariadicValue::Multiple(topics)) => topics.clone(),
+		_ => Vec::new(),
+	};
+	for position in positions {
+		let topics: Option<Vec<H256>> = match position {
+			Some(VariadicValue::Single(Some(topic))) => Some(vec![topic]),
+			Some(VariadicValue::Multiple(topics)) if !topics.is_empty() =>
+				topics.into_iter().collect(),
+			_ => None,
+		};
+		if let Some(topics) = topics {
+			constraints.push(topics.into_iter().map(LogIndexKey::Topic).collect());
+		}
+	}
+	constraints
+}
+
+fn bloom_matches(bloom: &Bloom, constraints: &[Vec<LogIndexKey>]) -> bool {
+	constraints.iter().all(|keys| keys.iter().any(|key| match key {
+		LogIndexKey::Address(address) => bloom.contains_input(BloomInput::Raw(address.as_bytes())),
+		LogIndexKey::Topic(topic) => bloom.contains_input(BloomInput::Raw(topic.as_bytes())),
+	}))
 }
 
 fn blake2_128_extend(bytes: &[u8]) -> Vec<u8> {
@@ -280,6 +475,24 @@ fn blake2_128_extend(bytes: &[u8]) -> Vec<u8> {
 	ext
 }
 
+pub(crate) fn account_codes_key(address: H160) -> Vec<u8> {
+	let mut key: Vec<u8> = storage_prefix_build(b"EVM", b"AccountCodes");
+	key.extend(blake2_128_extend(address.as_bytes()));
+	key
+}
+
+/// Key of the `AccountStorages` entry of a storage slot, which the runtime
+/// keys by the big-endian slot index.
+pub(crate) fn account_storages_key(address: H160, index: U256) -> Vec<u8> {
+	let tmp: &mut [u8; 32] = &mut [0; 32];
+	index.to_big_endian(tmp);
+
+	let mut key: Vec<u8> = storage_prefix_build(b"EVM", b"AccountStorages");
+	key.extend(blake2_128_extend(address.as_bytes()));
+	key.extend(blake2_128_extend(tmp));
+	key
+}
+
 impl<B, C, P, CT, BE, H: ExHashT, A> EthApi<B, C, P, CT, BE, H, A> where
 	C: ProvideRuntimeApi<B> + StorageProvider<B, BE> + AuxStore,
 	C: HeaderBackend<B> + HeaderMetadata<B, Error=BlockChainError> + 'static,
@@ -292,6 +505,150 @@ impl<B, C, P, CT, BE, H: ExHashT, A> EthApi<B, C, P, CT, BE, H, A> where
 	A: ChainApi<Block = B> + 'static,
 	CT: ConvertTransaction<<B as BlockT>::Extrinsic> + Send + Sync + 'static,
 {
+	/// The signer of the given account.
+	fn signer(&self, address: &H160) -> Result<&dyn EthSigner> {
+		self.signers.iter()
+			.find(|signer| signer.accounts().contains(address))
+			.map(|signer| signer.as_ref())
+			.ok_or(internal_err("no signer available"))
+	}
+
+	/// Fill in the missing fields of a transaction request and sign it.
+	fn sign_request(&self, request: TransactionRequest) -> Result<EthereumTransaction> {
+		let from = match request.from {
+			Some(from) => from,
+			None => *self.accounts()?.get(0).ok_or(internal_err("no signer available"))?,
+		};
+
+		let nonce = match request.nonce {
+			Some(nonce) => nonce,
+			None => self.transaction_count(from, None)?,
+		};
+
+		let chain_id = self.chain_id()?;
+
+		let gas_price = match request.gas_price {
+			Some(gas_price) => gas_price,
+			None => self.gas_price()?,
+		};
+		// The transaction is rejected from the pool if its gas limit is above
+		// the block gas limit, so only the gas it needs is requested.
+		let gas_limit = match request.gas {
+			Some(gas) => gas,
+			None => self.estimate_gas(CallRequest {
+				from: Some(from),
+				to: request.to,
+				gas_price: Some(gas_price),
+				gas: None,
+				value: request.value,
+				data: request.data.clone(),
+				nonce: Some(nonce),
+			}, None)?,
+		};
+
+		let message = transaction_message(request, nonce, gas_price, gas_limit, chain_id.map(|s| s.as_u64()));
+		self.signer(&from)?.sign(message, &from)
+	}
+
+	/// Submit a signed transaction to the pool and record it as pending.
+	fn submit_transaction(&self, transaction: EthereumTransaction) -> BoxFuture<H256> {
+		let transaction_hash = H256::from_slice(
+			Keccak256::digest(&rlp::encode(&transaction)).as_slice()
+		);
+		let hash = self.client.info().best_hash;
+		let number = self.client.info().best_number;
+
+		let uxt = self
+			.convert_transaction
+			.convert_transaction(transaction.clone());
+		let (uxt_hash, _bytes) = self.graph.validated_pool().api().hash_and_length(&uxt);
+		if self.graph.validated_pool().check_is_known(&uxt_hash, false).is_err() {
+			return Box::new(futures::future::ok(transaction_hash).compat());
+		}
+
+		let replaced = match self.check_replacement(&transaction) {
+			Ok(replaced) => replaced,
+			Err(err) => return Box::new(future::result(Err(err))),
+		};
+
+		let pending = self.pending_transactions.clone();
+		let graph = self.graph.clone();
+		Box::new(
+			self.pool
+				.submit_one(&BlockId::hash(hash), TransactionSource::Local, uxt)
+				.compat()
+				.map(move |_| {
+					if let Some((_, Some(future_hash))) = replaced {
+						graph.validated_pool().remove_invalid(&[future_hash]);
+					}
+					if let Some(pending) = pending {
+						if let Ok(locked) = &mut pending.lock() {
+							if let Some((from, _)) = replaced {
+								locked.retain(|_, pending| {
+									pending.transaction.from != from ||
+										pending.transaction.nonce != transaction.nonce
+								});
+							}
+							locked.insert(
+								transaction_hash,
+								PendingTransaction::new(
+									transaction_build(transaction, None, None),
+									UniqueSaturatedInto::<u64>::unique_saturated_into(
+										number
+									)
+								)
+							);
+						}
+					}
+					transaction_hash
+				})
+				.map_err(pool_err)
+		)
+	}
+
+	/// The sender of a pooled transaction replaced by `transaction`, with the
+	/// pool hash of the replaced transaction when it is in the future pool.
+	///
+	/// The pool replaces a ready transaction providing the same `(sender, nonce)`
+	/// tag with one of higher priority, but keeps future transactions side by
+	/// side until they are promoted. A replacement must raise the gas price of
+	/// the replaced transaction by at least `replacement_bump` percent.
+	fn check_replacement(
+		&self,
+		transaction: &EthereumTransaction,
+	) -> Result<Option<(H160, Option<ExtrinsicHash<A>>)>> {
+		let from = match transaction_sender(transaction) {
+			Some(from) => from,
+			// Rejected by the pool.
+			None => return Ok(None),
+		};
+		let pool = self.graph.validated_pool();
+		let tag = (from, transaction.nonce).encode();
+		let ready = pool.ready()
+			.filter(|ready| ready.provides.contains(&tag))
+			.map(|ready| (ready.data.clone(), None));
+		let future = pool.futures().into_iter()
+			.map(|(hash, xt)| (xt, Some(hash)));
+
+		let best = BlockId::Hash(self.client.info().best_hash);
+		for (xt, future_hash) in ready.chain(future) {
+			let replaced = self.client.runtime_api().extrinsic_filter(&best, vec![xt])
+				.unwrap_or_default()
+				.into_iter()
+				.find(|pooled| pooled.nonce == transaction.nonce &&
+					transaction_sender(pooled) == Some(from));
+			if let Some(replaced) = replaced {
+				check_replacement_price(
+					replaced.gas_price,
+					transaction.gas_price,
+					self.replacement_bump,
+				)?;
+				return Ok(Some((from, future_hash)));
+			}
+		}
+		Ok(None)
+	}
+
 	fn native_block_id(&self, number: Option<BlockNumber>) -> Result<Option<BlockId<B>>> {
 		Ok(match number.unwrap_or(BlockNumber::Latest) {
 			BlockNumber::Hash { hash, .. } => {
@@ -392,53 +749,66 @@ impl<B, C, P, CT, BE, H: ExHashT, A> EthApi<B, C, P, CT, BE, H, A> where
 		}
 	}
 
+	/// Indexed blocks in `from..=to` that may contain logs matching
+	/// `constraints`. Ranges whose bloom rules out a match are skipped.
+	fn indexed_log_blocks(
+		&self,
+		constraints: &[Vec<LogIndexKey>],
+		from: u64,
+		to: u64,
+	) -> Result<Vec<u64>> {
+		let mut ranges = BTreeSet::new();
+		for range in (from / LOG_BLOOM_RANGE)..=(to / LOG_BLOOM_RANGE) {
+			let bloom = self.backend.range_bloom(range).map_err(internal_err)?;
+			if bloom.map_or(true, |bloom| bloom_matches(&bloom, constraints)) {
+				ranges.insert(range);
+			}
+		}
+		if ranges.is_empty() {
+			return Ok(Vec::new());
+		}
+
+		let mut matching: Option<BTreeSet<u64>> = None;
+		for keys in constraints {
+			let hits: BTreeSet<u64> = keys.iter()
+				.flat_map(|key| self.backend.indexed_blocks(*key, from, to))
+				.filter(|number| ranges.contains(&(number / LOG_BLOOM_RANGE)))
+				.collect();
+			matching = Some(match matching {
+				Some(matching) => matching.intersection(&hits).cloned().collect(),
+				None => hits,
+			});
+		}
+
+		Ok(matching.unwrap_or_default().into_iter().collect())
+	}
+
 	fn current_block(&self, id: &BlockId<B>) -> Option<ethereum::Block> {
-		self.query_storage::<ethereum::Block>(
-			id,
-			&StorageKey(
-				storage_prefix_build(b"Ethereum", b"CurrentBlock")
-			)
-		)
+		let hash = self.client.expect_block_hash_from_id(id).ok()?;
+		self.block_data_cache.current_block(self.client.as_ref(), hash)
 	}
 
 	fn current_statuses(&self, id: &BlockId<B>) -> Option<Vec<TransactionStatus>> {
-		self.query_storage::<Vec<TransactionStatus>>(
-			id,
-			&StorageKey(
-				storage_prefix_build(b"Ethereum", b"CurrentTransactionStatuses")
-			)
-		)
+		let hash = self.client.expect_block_hash_from_id(id).ok()?;
+		self.block_data_cache.current_statuses(self.client.as_ref(), hash)
 	}
 
-	fn current_receipts(&self, id: &BlockId<B>) -> Option<Vec<ethereum::Receipt>> {
-		self.query_storage::<Vec<ethereum::Receipt>>(
-			id,
-			&StorageKey(
-				storage_prefix_build(b"Ethereum", b"CurrentReceipts")
-			)
-		)
+	fn current_receipts(&self, id: &BlockId<B>) -> Option<Vec<TransactionReceipt>> {
+		let hash = self.client.expect_block_hash_from_id(id).ok()?;
+		self.block_data_cache.current_receipts(self.client.as_ref(), hash)
 	}
 
 	fn account_codes(&self, id: &BlockId<B>, address: H160) -> Option<Vec<u8>> {
-		let mut key: Vec<u8> = storage_prefix_build(b"EVM", b"AccountCodes");
-		key.extend(blake2_128_extend(address.as_bytes()));
 		self.query_storage::<Vec<u8>>(
 			id,
-			&StorageKey(key)
+			&StorageKey(account_codes_key(address))
 		)
 	}
 
 	fn account_storages(&self, id: &BlockId<B>, address: H160, index: U256) -> Option<H256> {
-		let tmp: &mut [u8; 32] = &mut [0; 32];
-		index.to_little_endian(tmp);
-
-		let mut key: Vec<u8> = storage_prefix_build(b"EVM", b"AccountStorages");
-		key.extend(blake2_128_extend(address.as_bytes()));
-		key.extend(blake2_128_extend(tmp));
-
 		self.query_storage::<H256>(
 			id,
-			&StorageKey(key)
+			&StorageKey(account_storages_key(address, index))
 		)
 	}
 
@@ -472,6 +842,10 @@ impl<B, C, P, CT, BE, H: ExHashT, A> EthApiT for EthApi<B, C, P, CT, BE, H, A> w
 	}
 
 	fn syncing(&self) -> Result<SyncStatus> {
+		if let Some(status) = self.network_status.sync_status(self.client.as_ref()) {
+			return Ok(status);
+		}
+		// No status was reported by the network worker yet.
 		if self.network.is_major_syncing() {
 			let block_number = U256::from(
 				UniqueSaturatedInto::<u128>::unique_saturated_into(self.client.info().best_number.clone())
@@ -479,12 +853,10 @@ impl<B, C, P, CT, BE, H: ExHashT, A> EthApiT for EthApi<B, C, P, CT, BE, H, A> w
 			Ok(SyncStatus::Info(SyncInfo {
 				starting_block: U256::zero(),
 				current_block: block_number,
-				// TODO `highest_block` is not correct, should load `best_seen_block` from NetworkWorker,
-				// but afaik that is not currently possible in Substrate:
-				// https://github.com/paritytech/substrate/issues/7311
 				highest_block: block_number,
 				warp_chunks_amount: None,
 				warp_chunks_processed: None,
+				finalization_lag: None,
 			}))
 		} else {
 			Ok(SyncStatus::None)
@@ -528,6 +900,10 @@ impl<B, C, P, CT, BE, H: ExHashT, A> EthApiT for EthApi<B, C, P, CT, BE, H, A> w
 	}
 
 	fn accounts(&self) -> Result<Vec<H160>> {
+		// The accounts can only sign on endpoints serving unsafe methods.
+		if self.deny_unsafe.check_if_safe().is_err() {
+			return Ok(Vec::new());
+		}
 		let mut accounts = Vec::new();
 		for signer in &self.signers {
 			accounts.append(&mut signer.accounts());
@@ -712,109 +1088,15 @@ impl<B, C, P, CT, BE, H: ExHashT, A> EthApiT for EthApi<B, C, P, CT, BE, H, A> w
 	}
 
 	fn send_transaction(&self, request: TransactionRequest) -> BoxFuture<H256> {
-		let from = match request.from {
-			Some(from) => from,
-			None => {
-				let accounts = match self.accounts() {
-					Ok(accounts) => accounts,
-					Err(e) => return Box::new(future::result(Err(e))),
-				};
-
-				match accounts.get(0) {
-					Some(account) => account.clone(),
-					None => return Box::new(future::result(Err(internal_err("no signer available")))),
-				}
-			},
-		};
-
-		let nonce = match request.nonce {
-			Some(nonce) => nonce,
-			None => {
-				match self.transaction_count(from, None) {
-					Ok(nonce) => nonce,
-					Err(e) => return Box::new(future::result(Err(e))),
-				}
-			},
-		};
-
-		let chain_id = match self.chain_id() {
-			Ok(chain_id) => chain_id,
-			Err(e) => return Box::new(future::result(Err(e))),
-		};
-
-		let message = ethereum::TransactionMessage {
-			nonce,
-			gas_price: request.gas_price.unwrap_or(U256::from(1)),
-			gas_limit: request.gas.unwrap_or(U256::max_value()),
-			value: request.value.unwrap_or(U256::zero()),
-			input: request.data.map(|s| s.into_vec()).unwrap_or_default(),
-			action: match request.to {
-				Some(to) => ethereum::TransactionAction::Call(to),
-				None => ethereum::TransactionAction::Create,
-			},
-			chain_id: chain_id.map(|s| s.as_u64()),
-		};
-
-		let mut transaction = None;
-
-		for signer in &self.signers {
-			if signer.accounts().contains(&from) {
-				match signer.sign(message, &from) {
-					Ok(t) => transaction = Some(t),
-					Err(e) => return Box::new(future::result(Err(e))),
-				}
-				break
-			}
+		if let Err(e) = self.deny_unsafe.check_if_safe() {
+			return Box::new(future::result(Err(e.into())));
 		}
-
-		let transaction = match transaction {
-			Some(transaction) => transaction,
-			None => return Box::new(future::result(Err(internal_err("no signer available")))),
+		let transaction = match self.sign_request(request) {
+			Ok(transaction) => transaction,
+			Err(e) => return Box::new(future::result(Err(e))),
 		};
-		let transaction_hash = H256::from_slice(
-			Keccak256::digest(&rlp::encode(&transaction)).as_slice()
-		);
-		let hash = self.client.info().best_hash;
-		let number = self.client.info().best_number;
 
-		let uxt = self
-			.convert_transaction
-			.convert_transaction(transaction.clone());
-		let (uxt_hash, _bytes) = self.graph.validated_pool().api().hash_and_length(&uxt);
-		let check_is_known = self.graph.validated_pool().check_is_known(&uxt_hash, false);
-
-		match check_is_known {
-			Ok(_) => {
-				let pending = self.pending_transactions.clone();
-				Box::new(
-					self.pool
-						.submit_one(
-							&BlockId::hash(hash),
-							TransactionSource::Local,
-							self.convert_transaction.convert_transaction(transaction.clone()),
-						)
-						.compat()
-						.map(move |_| {
-							if let Some(pending) = pending {
-								if let Ok(locked) = &mut pending.lock() {
-									locked.insert(
-										transaction_hash,
-										PendingTransaction::new(
-											transaction_build(transaction, None, None),
-											UniqueSaturatedInto::<u64>::unique_saturated_into(
-												number
-											)
-										)
-									);
-								}
-							}
-							transaction_hash
-						})
-						.map_err(|err| internal_err(format!("submit transaction to pool failed: {:?}", err)))
-				)
-			},
-			_ => Box::new(futures::future::ok(transaction_hash).compat()),
-		}
+		self.submit_transaction(transaction)
 	}
 
 	fn send_raw_transaction(&self, bytes: Bytes) -> BoxFuture<H256> {
@@ -824,50 +1106,27 @@ impl<B, C, P, CT, BE, H: ExHashT, A> EthApiT for EthApi<B, C, P, CT, BE, H, A> w
 				future::result(Err(internal_err("decode transaction failed")))
 			),
 		};
-		let transaction_hash = H256::from_slice(
-			Keccak256::digest(&rlp::encode(&transaction)).as_slice()
-		);
-		let hash = self.client.info().best_hash;
-		let number = self.client.info().best_number;
 
-		let uxt = self
-			.convert_transaction
-			.convert_transaction(transaction.clone());
-		let (uxt_hash, _bytes) = self.graph.validated_pool().api().hash_and_length(&uxt);
-		let check_is_known = self.graph.validated_pool().check_is_known(&uxt_hash, false);
-
-		match check_is_known {
-			Ok(_) => {
-				let pending = self.pending_transactions.clone();
-				Box::new(
-					self.pool
-						.submit_one(
-							&BlockId::hash(hash),
-							TransactionSource::Local,
-							self.convert_transaction.convert_transaction(transaction.clone()),
-						)
-						.compat()
-						.map(move |_| {
-							if let Some(pending) = pending {
-								if let Ok(locked) = &mut pending.lock() {
-									locked.insert(
-										transaction_hash,
-										PendingTransaction::new(
-											transaction_build(transaction, None, None),
-											UniqueSaturatedInto::<u64>::unique_saturated_into(
-												number
-											)
-										)
-									);
-								}
-							}
-							transaction_hash
-						})
-						.map_err(|err| internal_err(format!("submit transaction to pool failed: {:?}", err)))
-				)
-			},
-			_ => Box::new(futures::future::ok(transaction_hash).compat()),
-		}
+		self.submit_transaction(transaction)
+	}
+
+	fn sign(&self, address: H160, data: Bytes) -> Result<Bytes> {
+		self.deny_unsafe.check_if_safe()?;
+		let mut message = format!("\x19Ethereum Signed Message:\n{}", data.0.len()).into_bytes();
+		message.extend_from_slice(&data.0);
+		let hash = H256::from_slice(Keccak256::digest(&message).as_slice());
+		Ok(Bytes(self.signer(&address)?.sign_hash(hash, &address)?.to_vec()))
+	}
+
+	fn sign_transaction(&self, request: TransactionRequest) -> Result<Bytes> {
+		self.deny_unsafe.check_if_safe()?;
+		Ok(Bytes(rlp::encode(&self.sign_request(request)?).to_vec()))
+	}
+
+	fn sign_typed_data(&self, address: H160, data: TypedData) -> Result<Bytes> {
+		self.deny_unsafe.check_if_safe()?;
+		let hash = hash_typed_data(&data).map_err(internal_err)?;
+		Ok(Bytes(self.signer(&address)?.sign_hash(hash, &address)?.to_vec()))
 	}
 
 	fn call(&self, request: CallRequest, _: Option<BlockNumber>) -> Result<Bytes> {
@@ -1161,62 +1420,40 @@ impl<B, C, P, CT, BE, H: ExHashT, A> EthApiT for EthApi<B, C, P, CT, BE, H, A> w
 
 		let block: Option<ethereum::Block> = self.current_block(&id);
 		let statuses: Option<Vec<TransactionStatus>> = self.current_statuses(&id);
-		let receipts: Option<Vec<ethereum::Receipt>> = self.current_receipts(&id);
+		let receipts: Option<Vec<TransactionReceipt>> = self.current_receipts(&id);
 
 		match (block, statuses, receipts) {
 			(Some(block), Some(statuses), Some(receipts)) => {
 				let block_hash = H256::from_slice(
 					Keccak256::digest(&rlp::encode(&block.header)).as_slice()
 				);
-				let receipt = receipts[index].clone();
-				let status = statuses[index].clone();
-				let mut cumulative_receipts = receipts.clone();
-				cumulative_receipts.truncate((status.transaction_index + 1) as usize);
-
-				return Ok(Some(Receipt {
-					transaction_hash: Some(status.transaction_hash),
-					transaction_index: Some(status.transaction_index.into()),
-					block_hash: Some(block_hash),
-					from: Some(status.from),
-					to: status.to,
-					block_number: Some(block.header.number),
-					cumulative_gas_used: {
-						let cumulative_gas: u32 = cumulative_receipts.iter().map(|r| {
-							r.used_gas.as_u32()
-						}).sum();
-						U256::from(cumulative_gas)
-					},
-					gas_used: Some(receipt.used_gas),
-					contract_address: status.contract_address,
-					logs: {
-						let mut pre_receipts_log_index = None;
-						if cumulative_receipts.len() > 0 {
-							cumulative_receipts.truncate(cumulative_receipts.len() - 1);
-							pre_receipts_log_index = Some(cumulative_receipts.iter().map(|r| {
-								r.logs.len() as u32
-							}).sum::<u32>());
-						}
-						receipt.logs.iter().enumerate().map(|(i, log)| {
-							Log {
-								address: log.address,
-								topics: log.topics.clone(),
-								data: Bytes(log.data.clone()),
-								block_hash: Some(block_hash),
-								block_number: Some(block.header.number),
-								transaction_hash: Some(hash),
-								transaction_index: Some(status.transaction_index.into()),
-								log_index: Some(U256::from(
-									(pre_receipts_log_index.unwrap_or(0)) + i as u32
-								)),
-								transaction_log_index: Some(U256::from(i)),
-								removed: false,
-							}
-						}).collect()
-					},
-					status_code: Some(U64::from(receipt.state_root.to_low_u64_be())),
-					logs_bloom: receipt.logs_bloom,
-					state_root: None,
-				}))
+				Ok(Some(transaction_receipt_build(&block, block_hash, &statuses, &receipts, index)))
+			}
+			_ => Ok(None),
+		}
+	}
+
+	fn block_receipts(&self, number: BlockNumber) -> Result<Option<Vec<Receipt>>> {
+		let id = match self.native_block_id(Some(number))? {
+			Some(id) => id,
+			None => return Ok(None),
+		};
+		if let Ok((best_number, header_number)) = self.headers(&id) {
+			if header_number > best_number {
+				return Ok(None);
+			}
+		}
+
+		let block: Option<ethereum::Block> = self.current_block(&id);
+		let statuses: Option<Vec<TransactionStatus>> = self.current_statuses(&id);
+		let receipts: Option<Vec<TransactionReceipt>> = self.current_receipts(&id);
+
+		match (block, statuses, receipts) {
+			(Some(block), Some(statuses), Some(receipts)) => {
+				let block_hash = H256::from_slice(
+					Keccak256::digest(&rlp::encode(&block.header)).as_slice()
+				);
+				Ok(Some(block_receipts_build(&block, block_hash, &statuses, &receipts)))
 			}
 			_ => Ok(None),
 		}
@@ -1235,7 +1472,6 @@ impl<B, C, P, CT, BE, H: ExHashT, A> EthApiT for EthApi<B, C, P, CT, BE, H, A> w
 	}
 
 	fn logs(&self, filter: Filter) -> Result<Vec<Log>> {
-		let mut blocks_and_statuses = Vec::new();
 		if let Some(hash) = filter.block_hash.clone() {
 			let id = match self.load_hash(hash)
 				.map_err(|err| internal_err(format!("{:?}", err)))?
@@ -1252,46 +1488,59 @@ impl<B, C, P, CT, BE, H: ExHashT, A> EthApiT for EthApi<B, C, P, CT, BE, H, A> w
 			let block: Option<ethereum::Block> = self.current_block(&id);
 			let statuses: Option<Vec<TransactionStatus>> = self.current_statuses(&id);
 
+			let mut blocks_and_statuses = Vec::new();
 			if let (Some(block), Some(statuses)) = (block, statuses) {
 				blocks_and_statuses.push((block, statuses));
 			}
-		} else {
-			let best_number = self.client.info().best_number;
-			let mut current_number = filter
-				.to_block.clone()
-				.and_then(|v| v.to_min_block_num())
-				.map(|s| s.unique_saturated_into())
-				.unwrap_or(best_number);
-
-			if current_number > best_number {
-				current_number = best_number;
-			}
-
-			let from_number = filter.from_block.clone()
-				.and_then(|v| v.to_min_block_num())
-				.map(|s| s.unique_saturated_into())
-				.unwrap_or(
-					self.client.info().best_number
-				);
-			while current_number >= from_number {
-				let id = BlockId::Number(current_number);
+			return Ok(logs_build(filter, blocks_and_statuses));
+		}
 
-				let block: Option<ethereum::Block> = self.current_block(&id);
-				let statuses: Option<Vec<TransactionStatus>> = self.current_statuses(&id);
+		let best_number: u64 = self.client.info().best_number.unique_saturated_into();
+		let to_number = filter.to_block.clone()
+			.and_then(|v| v.to_min_block_num())
+			.unwrap_or(best_number)
+			.min(best_number);
+		let from_number = filter.from_block.clone()
+			.and_then(|v| v.to_min_block_num())
+			.unwrap_or(best_number);
+		if from_number > to_number {
+			return Ok(Vec::new());
+		}
+		if to_number - from_number >= self.max_past_logs_range as u64 {
+			return Err(limit_exceeded_err(format!(
+				"query exceeds max block range {}", self.max_past_logs_range
+			)));
+		}
 
-				if let (Some(block), Some(statuses)) = (block, statuses) {
-					blocks_and_statuses.push((block, statuses));
+		// Blocks covered by the log index are looked up in it, the more recent
+		// ones are walked one by one.
+		let constraints = log_index_constraints(&filter);
+		let mut numbers = Vec::new();
+		let mut unindexed_from = from_number;
+		if !constraints.is_empty() {
+			if let Some(tip) = self.backend.log_index_tip().map_err(internal_err)? {
+				if tip >= from_number {
+					numbers = self.indexed_log_blocks(&constraints, from_number, tip.min(to_number))?;
+					unindexed_from = tip + 1;
 				}
+			}
+		}
+		numbers.extend(unindexed_from..=to_number);
 
-				if current_number == Zero::zero() {
-					break
-				} else {
-					current_number = current_number.saturating_sub(One::one());
-				}
+		let mut logs = Vec::new();
+		for number in numbers {
+			let id = BlockId::Number(number.unique_saturated_into());
+			if let (Some(block), Some(statuses)) = (self.current_block(&id), self.current_statuses(&id)) {
+				logs.extend(logs_build(filter.clone(), vec![(block, statuses)]));
+			}
+			if logs.len() > self.max_logs_count {
+				return Err(limit_exceeded_err(format!(
+					"query returned more than {} results", self.max_logs_count
+				)));
 			}
 		}
 
-		Ok(logs_build(filter,blocks_and_statuses))
+		Ok(logs)
 	}
 
 	fn work(&self) -> Result<Work> {
@@ -1315,6 +1564,7 @@ impl<B, C, P, CT, BE, H: ExHashT, A> EthApiT for EthApi<B, C, P, CT, BE, H, A> w
 pub struct NetApi<B: BlockT, BE, C, H: ExHashT> {
 	client: Arc<C>,
 	network: Arc<NetworkService<B, H>>,
+	network_status: Arc<EthNetworkStatus>,
 	_marker: PhantomData<BE>,
 }
 
@@ -1322,10 +1572,12 @@ impl<B: BlockT, BE, C, H: ExHashT> NetApi<B, BE, C, H> {
 	pub fn new(
 		client: Arc<C>,
 		network: Arc<NetworkService<B, H>>,
+		network_status: Arc<EthNetworkStatus>,
 	) -> Self {
 		Self {
 			client,
 			network,
+			network_status,
 			_marker: PhantomData,
 		}
 	}
@@ -1341,11 +1593,11 @@ impl<B: BlockT, BE, C, H: ExHashT> NetApiT for NetApi<B, BE, C, H> where
 	B: BlockT<Hash=H256> + Send + Sync + 'static,
 {
 	fn is_listening(&self) -> Result<bool> {
-		Ok(true)
+		Ok(self.network_status.listening().unwrap_or(true))
 	}
 
 	fn peer_count(&self) -> Result<u32> {
-		Ok(self.network.num_connected() as u32)
+		Ok(self.network_status.peer_count().unwrap_or_else(|| self.network.num_connected()) as u32)
 	}
 
 	fn version(&self) -> Result<String> {
@@ -1399,23 +1651,32 @@ impl<B, C> Web3ApiT for Web3Api<B, C> where
 	}
 }
 
-pub struct EthFilterApi<B, C, BE> {
+pub struct EthFilterApi<B: BlockT, C, BE> {
 	client: Arc<C>,
 	filter_pool: FilterPool,
+	block_data_cache: Arc<EthBlockDataCache<B>>,
 	max_stored_filters: usize,
+	max_filters_per_connection: usize,
+	max_filter_results: usize,
 	_marker: PhantomData<(B, BE)>,
 }
 
-impl<B, C, BE> EthFilterApi<B, C, BE> {
+impl<B: BlockT, C, BE> EthFilterApi<B, C, BE> {
 	pub fn new(
 		client: Arc<C>,
 		filter_pool: FilterPool,
+		block_data_cache: Arc<EthBlockDataCache<B>>,
 		max_stored_filters: usize,
+		max_filters_per_connection: usize,
+		max_filter_results: usize,
 	) -> Self {
 		Self {
 			client,
 			filter_pool,
+			block_data_cache,
 			max_stored_filters,
+			max_filters_per_connection,
+			max_filter_results,
 			_marker: PhantomData,
 		}
 	}
@@ -1429,17 +1690,28 @@ impl<B, C, BE> EthFilterApi<B, C, BE> where
 	C: Send + Sync + 'static,
 	B: BlockT<Hash=H256> + Send + Sync + 'static,
 {
-	fn create_filter(&self, filter_type: FilterType) -> Result<U256> {
+	fn create_filter(&self, meta: Metadata, filter_type: FilterType) -> Result<U256> {
 		let block_number = UniqueSaturatedInto::<u64>::unique_saturated_into(
 			self.client.info().best_number
 		);
+		// Connections are told apart by their session. HTTP requests have none
+		// and share the `None` owner, which only the pool limit applies to.
+		let session = meta.session();
+		let owner = session.as_ref().map(|session| Arc::as_ptr(session) as usize as u64);
 		let pool = self.filter_pool.clone();
 		let response = if let Ok(locked) = &mut pool.lock() {
 			if locked.len() >= self.max_stored_filters {
-				return Err(internal_err(
+				return Err(limit_exceeded_err(
 					format!("Filter pool is full (limit {:?}).", self.max_stored_filters)
 				));
 			}
+			let owned = locked.values().filter(|item| item.owner == owner).count();
+			if owner.is_some() && owned >= self.max_filters_per_connection {
+				return Err(limit_exceeded_err(format!(
+					"Too many filters installed by this connection (limit {:?}).",
+					self.max_filters_per_connection,
+				)));
+			}
 			let last_key = match locked.iter().next_back() {
 				Some((k,_)) => *k,
 				None => U256::zero()
@@ -1451,9 +1723,20 @@ impl<B, C, BE> EthFilterApi<B, C, BE> where
 				FilterPoolItem {
 					last_poll: BlockNumber::Num(block_number),
 					filter_type: filter_type,
-					at_block: block_number
+					at_block: block_number,
+					last_activity: Instant::now(),
+					owner,
 				}
 			);
+			// The filters of a connection go away with it.
+			if let (Some(session), 0) = (session, owned) {
+				let filter_pool = self.filter_pool.clone();
+				session.on_drop(move || {
+					if let Ok(locked) = &mut filter_pool.lock() {
+						locked.retain(|_, item| item.owner != owner);
+					}
+				});
+			}
 			Ok(key)
 		} else {
 			Err(internal_err("Filter pool is not available."))
@@ -1461,34 +1744,25 @@ impl<B, C, BE> EthFilterApi<B, C, BE> where
 		response
 	}
 
-	fn current_block(&self, id: &BlockId<B>) -> Option<ethereum::Block> {
-		self.query_storage::<ethereum::Block>(
-			id,
-			&StorageKey(
-				storage_prefix_build(b"Ethereum", b"CurrentBlock")
-			)
-		)
+	/// Logs of a filter in block order, truncated to the results a filter can
+	/// return, with the block to poll from next if they were truncated.
+	fn filter_logs_checked(
+		&self,
+		filter: Filter,
+		mut blocks_and_statuses: Vec<(EthereumBlock, Vec<TransactionStatus>)>,
+	) -> (Vec<Log>, Option<u64>) {
+		blocks_and_statuses.sort_by_key(|(block, _)| block.header.number);
+		truncate_logs(logs_build(filter, blocks_and_statuses), self.max_filter_results)
 	}
 
-	fn current_statuses(&self, id: &BlockId<B>) -> Option<Vec<TransactionStatus>> {
-		self.query_storage::<Vec<TransactionStatus>>(
-			id,
-			&StorageKey(
-				storage_prefix_build(b"Ethereum", b"CurrentTransactionStatuses")
-			)
-		)
+	fn current_block(&self, id: &BlockId<B>) -> Option<ethereum::Block> {
+		let hash = self.client.expect_block_hash_from_id(id).ok()?;
+		self.block_data_cache.current_block(self.client.as_ref(), hash)
 	}
 
-	fn query_storage<T: Decode>(&self, id: &BlockId<B>, key: &StorageKey) -> Option<T> {
-		if let Ok(Some(data)) = self.client.storage(
-			id,
-			key
-		) {
-			if let Ok(result) = Decode::decode(&mut &data.0[..]) {
-				return Some(result);
-			}
-		}
-		None
+	fn current_statuses(&self, id: &BlockId<B>) -> Option<Vec<TransactionStatus>> {
+		let hash = self.client.expect_block_hash_from_id(id).ok()?;
+		self.block_data_cache.current_statuses(self.client.as_ref(), hash)
 	}
 }
 
@@ -1500,16 +1774,18 @@ impl<B, C, BE> EthFilterApiT for EthFilterApi<B, C, BE> where
 	C: Send + Sync + 'static,
 	B: BlockT<Hash=H256> + Send + Sync + 'static,
 {
-	fn new_filter(&self, filter: Filter) -> Result<U256> {
-		self.create_filter(FilterType::Log(filter))
+	type Metadata = Metadata;
+
+	fn new_filter(&self, meta: Metadata, filter: Filter) -> Result<U256> {
+		self.create_filter(meta, FilterType::Log(filter))
 	}
 
-	fn new_block_filter(&self) -> Result<U256> {
-		self.create_filter(FilterType::Block)
+	fn new_block_filter(&self, meta: Metadata) -> Result<U256> {
+		self.create_filter(meta, FilterType::Block)
 	}
 
-	fn new_pending_transaction_filter(&self) -> Result<U256> {
-		Err(internal_err("Method not available."))
+	fn new_pending_transaction_filter(&self, meta: Metadata) -> Result<U256> {
+		self.create_filter(meta, FilterType::PendingTransaction(Vec::new()))
 	}
 
 	fn filter_changes(&self, index: Index) -> Result<FilterChanges> {
@@ -1542,11 +1818,27 @@ impl<B, C, BE> EthFilterApiT for EthFilterApi<B, C, BE> where
 							FilterPoolItem {
 								last_poll: BlockNumber::Num(next),
 								filter_type: pool_item.clone().filter_type,
-								at_block: pool_item.at_block
+								at_block: pool_item.at_block,
+								last_activity: Instant::now(),
+								owner: pool_item.owner,
 							}
 						);
 						Ok(FilterChanges::Hashes(ethereum_hashes))
 					},
+					// Transactions are added to the filter as they enter the pool.
+					FilterType::PendingTransaction(transaction_hashes) => {
+						locked.insert(
+							key,
+							FilterPoolItem {
+								last_poll: BlockNumber::Num(block_number + 1),
+								filter_type: FilterType::PendingTransaction(Vec::new()),
+								at_block: pool_item.at_block,
+								last_activity: Instant::now(),
+								owner: pool_item.owner,
+							}
+						);
+						Ok(FilterChanges::Hashes(transaction_hashes.clone()))
+					},
 					// For each event since last poll, get a vector of ethereum logs.
 					FilterType::Log(filter) => {
 						// Either the filter-specific `to` block or best block.
@@ -1592,25 +1884,23 @@ impl<B, C, BE> EthFilterApiT for EthFilterApi<B, C, BE> where
 								current_number = current_number.saturating_sub(One::one());
 							}
 						}
-						// Update filter `last_poll`.
+						let (logs, next_poll) = self.filter_logs_checked(filter.clone(), blocks_and_statuses);
+						// Update filter `last_poll`, to the first block whose logs
+						// weren't returned if they were truncated.
 						locked.insert(
 							key,
 							FilterPoolItem {
 								last_poll: BlockNumber::Num(
-									block_number + 1
+									next_poll.unwrap_or(block_number + 1)
 								),
 								filter_type: pool_item.clone().filter_type,
-								at_block: pool_item.at_block
+								at_block: pool_item.at_block,
+								last_activity: Instant::now(),
+								owner: pool_item.owner,
 							}
 						);
-						Ok(FilterChanges::Logs(
-							logs_build(filter.clone(), blocks_and_statuses)
-						))
+						Ok(FilterChanges::Logs(logs))
 					},
-					// Should never reach here.
-					_ => {
-						Err(internal_err("Method not available."))
-					}
 				}
 			} else {
 				Err(internal_err(format!("Filter id {:?} does not exist.", key)))
@@ -1668,7 +1958,11 @@ impl<B, C, BE> EthFilterApiT for EthFilterApi<B, C, BE> where
 								current_number = current_number.saturating_sub(One::one());
 							}
 						}
-						Ok(logs_build(filter.clone(), blocks_and_statuses))
+						let (logs, _) = self.filter_logs_checked(filter.clone(), blocks_and_statuses);
+						if let Some(item) = locked.get_mut(&key) {
+							item.last_activity = Instant::now();
+						}
+						Ok(logs)
 					},
 					_ => Err(internal_err(
 						format!("Filter id {:?} is not a Log filter.", key)
@@ -1701,3 +1995,144 @@ impl<B, C, BE> EthFilterApiT for EthFilterApi<B, C, BE> where
 		response
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn transaction_message_uses_given_gas_fields() {
+		let request = TransactionRequest {
+			to: Some(H160::repeat_byte(1)),
+			value: Some(U256::from(5)),
+			data: Some(Bytes(vec![1, 2])),
+			..Default::default()
+		};
+		let message = transaction_message(request, U256::from(3), U256::from(10), U256::from(21_000), Some(42));
+
+		assert_eq!(message.nonce, U256::from(3));
+		assert_eq!(message.gas_price, U256::from(10));
+		assert_eq!(message.gas_limit, U256::from(21_000));
+		assert_eq!(message.value, U256::from(5));
+		assert_eq!(message.input, vec![1, 2]);
+		assert_eq!(message.action, ethereum::TransactionAction::Call(H160::repeat_byte(1)));
+		assert_eq!(message.chain_id, Some(42));
+	}
+
+	#[test]
+	fn transaction_message_without_recipient_creates() {
+		let message = transaction_message(Default::default(), U256::zero(), U256::one(), U256::one(), None);
+		assert_eq!(message.action, ethereum::TransactionAction::Create);
+		assert!(message.input.is_empty());
+	}
+
+	#[test]
+	fn replacement_must_raise_gas_price_by_bump() {
+		assert!(check_replacement_price(U256::from(100), U256::from(110), 10).is_ok());
+		assert!(check_replacement_price(U256::from(100), U256::from(200), 10).is_ok());
+		assert!(check_replacement_price(U256::from(100), U256::from(101), 0).is_ok());
+	}
+
+	#[test]
+	fn underpriced_replacement_is_rejected() {
+		let err = check_replacement_price(U256::from(100), U256::from(109), 10).unwrap_err();
+		assert_eq!(err.message, "replacement transaction underpriced");
+	}
+
+	#[test]
+	fn replacement_price_is_not_truncated_to_u64() {
+		let old = U256::from(u64::max_value()) * 2;
+		assert!(check_replacement_price(old, U256::from(u64::max_value()), 10).is_err());
+		assert!(check_replacement_price(old, old * 11 / 10, 10).is_ok());
+	}
+
+	fn log(data: u8) -> ethereum::Log {
+		ethereum::Log { address: H160::repeat_byte(data), topics: Vec::new(), data: vec![data] }
+	}
+
+	#[test]
+	fn block_receipts_match_transaction_receipts() {
+		let partial_header = ethereum::PartialHeader {
+			parent_hash: H256::default(),
+			beneficiary: Default::default(),
+			state_root: H256::default(),
+			receipts_root: H256::default(),
+			logs_bloom: Default::default(),
+			difficulty: U256::zero(),
+			number: U256::from(7),
+			gas_limit: U256::zero(),
+			gas_used: U256::zero(),
+			timestamp: 0,
+			extra_data: Vec::new(),
+			mix_hash: H256::default(),
+			nonce: H64::default(),
+		};
+		let block = ethereum::Block::new(partial_header, Vec::new(), Vec::new());
+		let block_hash = H256::repeat_byte(9);
+		let logs = vec![vec![log(1), log(2)], Vec::new(), vec![log(3)]];
+		let statuses: Vec<TransactionStatus> = logs.iter().enumerate().map(|(index, logs)| TransactionStatus {
+			transaction_hash: H256::repeat_byte(index as u8 + 1),
+			transaction_index: index as u32,
+			logs: logs.clone(),
+			..Default::default()
+		}).collect();
+		let receipts: Vec<TransactionReceipt> = logs.into_iter().enumerate().map(|(index, logs)| TransactionReceipt {
+			status_code: 1,
+			cumulative_gas_used: U256::from(21_000 * (index + 1)),
+			used_gas: U256::from(21_000),
+			effective_gas_price: U256::one(),
+			logs_bloom: Bloom::default(),
+			logs,
+		}).collect();
+
+		let block_receipts = block_receipts_build(&block, block_hash, &statuses, &receipts);
+
+		assert_eq!(block_receipts.len(), 3);
+		for (index, receipt) in block_receipts.iter().enumerate() {
+			assert_eq!(*receipt, transaction_receipt_build(&block, block_hash, &statuses, &receipts, index));
+		}
+		assert_eq!(block_receipts[2].logs[0].log_index, Some(U256::from(2)));
+	}
+
+	fn block_log(number: u64) -> Log {
+		Log {
+			address: H160::default(),
+			topics: Vec::new(),
+			data: Bytes(Vec::new()),
+			block_hash: None,
+			block_number: Some(U256::from(number)),
+			transaction_hash: None,
+			transaction_index: None,
+			log_index: None,
+			transaction_log_index: None,
+			removed: false,
+		}
+	}
+
+	fn block_numbers(logs: &[Log]) -> Vec<u64> {
+		logs.iter().map(|log| log.block_number.unwrap().low_u64()).collect()
+	}
+
+	#[test]
+	fn logs_within_the_limit_are_not_truncated() {
+		let (logs, next) = truncate_logs(vec![block_log(1), block_log(2)], 2);
+		assert_eq!(block_numbers(&logs), vec![1, 2]);
+		assert_eq!(next, None);
+	}
+
+	#[test]
+	fn logs_are_truncated_to_complete_blocks() {
+		let logs = vec![block_log(1), block_log(2), block_log(2), block_log(3)];
+		let (logs, next) = truncate_logs(logs, 2);
+		assert_eq!(block_numbers(&logs), vec![1]);
+		assert_eq!(next, Some(2));
+	}
+
+	#[test]
+	fn logs_of_a_block_over_the_limit_are_truncated() {
+		let logs = vec![block_log(4), block_log(4), block_log(4), block_log(5)];
+		let (logs, next) = truncate_logs(logs, 2);
+		assert_eq!(block_numbers(&logs), vec![4, 4]);
+		assert_eq!(next, Some(5));
+	}
+}
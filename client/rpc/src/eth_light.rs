@@ -0,0 +1,324 @@
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+// This file is part of Frontier.
+//
+// Copyright (c) 2020 Parity Technologies (UK) Ltd.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Ethereum RPC of light clients. Requests are answered from storage read
+//! proofs and runtime calls fetched from full peers, transactions are
+//! forwarded to them through the light transaction pool. Light clients keep
+//! no mapping of Ethereum block hashes, so blocks can't be given by hash
+//! (`BlockNumber::Hash`).
+
+use std::{marker::PhantomData, sync::Arc};
+use ethereum_types::{H160, H256, U256, U64};
+use jsonrpc_core::{BoxFuture, Result, futures::future};
+use futures::future::{FutureExt, TryFutureExt};
+use sp_runtime::{
+	traits::{Block as BlockT, Header as HeaderT, UniqueSaturatedInto, Zero},
+	transaction_validity::TransactionSource,
+};
+use sp_api::BlockId;
+use sp_blockchain::{Error as ClientError, HeaderBackend};
+use sp_transaction_pool::TransactionPool;
+use sc_client_api::light::{
+	future_header, Fetcher, RemoteBlockchain, RemoteCallRequest, RemoteReadRequest,
+};
+use sha3::{Keccak256, Digest};
+use codec::{Decode, Encode};
+use fc_rpc_core::EthLightApi as EthLightApiT;
+use fc_rpc_core::types::{BlockNumber, Bytes, RichBlock};
+use fp_rpc::{ConvertTransaction, TransactionStatus};
+use crate::{internal_err, pool_err, storage_prefix_build};
+use crate::eth::{account_codes_key, account_storages_key, rich_block_build};
+
+pub use fc_rpc_core::EthLightApiServer;
+
+pub struct LightEthApi<B: BlockT, C, F, P, CT> {
+	client: Arc<C>,
+	remote_blockchain: Arc<dyn RemoteBlockchain<B>>,
+	fetcher: Arc<F>,
+	pool: Arc<P>,
+	convert_transaction: CT,
+	_marker: PhantomData<B>,
+}
+
+impl<B: BlockT, C, F, P, CT> LightEthApi<B, C, F, P, CT> {
+	pub fn new(
+		client: Arc<C>,
+		remote_blockchain: Arc<dyn RemoteBlockchain<B>>,
+		fetcher: Arc<F>,
+		pool: Arc<P>,
+		convert_transaction: CT,
+	) -> Self {
+		Self { client, remote_blockchain, fetcher, pool, convert_transaction, _marker: PhantomData }
+	}
+}
+
+impl<B, C, F, P, CT> LightEthApi<B, C, F, P, CT> where
+	B: BlockT<Hash=H256> + Send + Sync + 'static,
+	C: HeaderBackend<B> + Send + Sync + 'static,
+	F: Fetcher<B> + 'static,
+{
+	/// Substrate block of an Ethereum block number. Light clients keep no
+	/// mapping of Ethereum block hashes, so those can't be resolved.
+	fn native_block_id(&self, number: Option<BlockNumber>) -> Result<BlockId<B>> {
+		Ok(match number.unwrap_or(BlockNumber::Latest) {
+			BlockNumber::Hash { .. } => {
+				return Err(internal_err("block hashes are not supported by light clients"))
+			},
+			BlockNumber::Num(number) => BlockId::Number(number.unique_saturated_into()),
+			BlockNumber::Earliest => BlockId::Number(Zero::zero()),
+			BlockNumber::Latest | BlockNumber::Pending => BlockId::Hash(self.client.info().best_hash),
+		})
+	}
+
+	/// Header of a block, downloaded from a full peer if it isn't known locally.
+	fn header(&self, id: BlockId<B>) -> impl std::future::Future<Output=Result<B::Header>> {
+		let block = format!("{:?}", id);
+		future_header(&*self.remote_blockchain, &*self.fetcher, id)
+			.map(move |header| match header {
+				Ok(Some(header)) => Ok(header),
+				Ok(None) => Err(internal_err(format!("block {} not found", block))),
+				Err(err) => Err(internal_err(format!("fetch header failed: {:?}", err))),
+			})
+	}
+
+	/// Reads a storage value of a block, checked against a proof from a full peer.
+	fn remote_read(
+		&self,
+		id: BlockId<B>,
+		key: Vec<u8>,
+	) -> impl std::future::Future<Output=Result<Option<Vec<u8>>>> {
+		let header = self.header(id);
+		let fetcher = self.fetcher.clone();
+		async move {
+			let header = header.await?;
+			let mut values = fetcher.remote_read(RemoteReadRequest {
+				block: header.hash(),
+				header,
+				keys: vec![key.clone()],
+				retry_count: None,
+			}).await.map_err(|err: ClientError| internal_err(format!("fetch remote storage failed: {:?}", err)))?;
+			Ok(values.remove(&key).flatten())
+		}
+	}
+
+	/// Calls a method of `EthereumRuntimeRPCApi` at a block, executed by a
+	/// full peer and checked against its execution proof.
+	fn remote_call<R: Decode>(
+		&self,
+		id: BlockId<B>,
+		method: &'static str,
+		call_data: Vec<u8>,
+	) -> impl std::future::Future<Output=Result<R>> {
+		let header = self.header(id);
+		let fetcher = self.fetcher.clone();
+		async move {
+			let header = header.await?;
+			let result = fetcher.remote_call(RemoteCallRequest {
+				block: header.hash(),
+				header,
+				method: format!("EthereumRuntimeRPCApi_{}", method),
+				call_data,
+				retry_count: None,
+			}).await.map_err(|err: ClientError| internal_err(format!("fetch runtime {} failed: {:?}", method, err)))?;
+			R::decode(&mut &result[..])
+				.map_err(|err| internal_err(format!("decode runtime {} failed: {:?}", method, err)))
+		}
+	}
+
+	/// Reads and decodes a storage item of the Ethereum pallet.
+	fn ethereum_storage<T: Decode>(
+		&self,
+		id: BlockId<B>,
+		storage: &'static [u8],
+	) -> impl std::future::Future<Output=Result<Option<T>>> {
+		let value = self.remote_read(id, storage_prefix_build(b"Ethereum", storage));
+		async move {
+			match value.await? {
+				Some(bytes) => Decode::decode(&mut &bytes[..])
+					.map(Some)
+					.map_err(|err| internal_err(format!("decode ethereum storage failed: {:?}", err))),
+				None => Ok(None),
+			}
+		}
+	}
+}
+
+fn boxed<T: Send + 'static>(
+	future: impl std::future::Future<Output=Result<T>> + Send + 'static,
+) -> BoxFuture<T> {
+	Box::new(Box::pin(future).compat())
+}
+
+impl<B, C, F, P, CT> EthLightApiT for LightEthApi<B, C, F, P, CT> where
+	B: BlockT<Hash=H256> + Send + Sync + 'static,
+	C: HeaderBackend<B> + Send + Sync + 'static,
+	F: Fetcher<B> + 'static,
+	P: TransactionPool<Block=B> + Send + Sync + 'static,
+	CT: ConvertTransaction<<B as BlockT>::Extrinsic> + Send + Sync + 'static,
+{
+	fn chain_id(&self) -> BoxFuture<Option<U64>> {
+		let id = BlockId::Hash(self.client.info().best_hash);
+		let chain_id = self.remote_call::<u64>(id, "chain_id", Vec::new());
+		boxed(async move { Ok(Some(chain_id.await?.into())) })
+	}
+
+	fn gas_price(&self) -> BoxFuture<U256> {
+		let id = BlockId::Hash(self.client.info().best_hash);
+		boxed(self.remote_call::<U256>(id, "gas_price", Vec::new()))
+	}
+
+	fn block_number(&self) -> Result<U256> {
+		Ok(U256::from(UniqueSaturatedInto::<u128>::unique_saturated_into(self.client.info().best_number)))
+	}
+
+	fn balance(&self, address: H160, number: Option<BlockNumber>) -> BoxFuture<U256> {
+		let id = match self.native_block_id(number) {
+			Ok(id) => id,
+			Err(err) => return Box::new(future::result(Err(err))),
+		};
+		let account = self.remote_call::<fp_evm::Account>(id, "account_basic", address.encode());
+		boxed(async move { Ok(account.await?.balance) })
+	}
+
+	fn storage_at(&self, address: H160, index: U256, number: Option<BlockNumber>) -> BoxFuture<H256> {
+		let id = match self.native_block_id(number) {
+			Ok(id) => id,
+			Err(err) => return Box::new(future::result(Err(err))),
+		};
+		let value = self.remote_read(id, account_storages_key(address, index));
+		boxed(async move {
+			match value.await? {
+				Some(bytes) => H256::decode(&mut &bytes[..])
+					.map_err(|err| internal_err(format!("decode account storage failed: {:?}", err))),
+				None => Ok(H256::default()),
+			}
+		})
+	}
+
+	fn transaction_count(&self, address: H160, number: Option<BlockNumber>) -> BoxFuture<U256> {
+		let id = match self.native_block_id(number) {
+			Ok(id) => id,
+			Err(err) => return Box::new(future::result(Err(err))),
+		};
+		let account = self.remote_call::<fp_evm::Account>(id, "account_basic", address.encode());
+		boxed(async move { Ok(account.await?.nonce) })
+	}
+
+	fn code_at(&self, address: H160, number: Option<BlockNumber>) -> BoxFuture<Bytes> {
+		let id = match self.native_block_id(number) {
+			Ok(id) => id,
+			Err(err) => return Box::new(future::result(Err(err))),
+		};
+		let value = self.remote_read(id, account_codes_key(address));
+		boxed(async move {
+			match value.await? {
+				Some(bytes) => Vec::<u8>::decode(&mut &bytes[..])
+					.map(Bytes)
+					.map_err(|err| internal_err(format!("decode account code failed: {:?}", err))),
+				None => Ok(Bytes(Vec::new())),
+			}
+		})
+	}
+
+	fn block_by_number(&self, number: BlockNumber, full: bool) -> BoxFuture<Option<RichBlock>> {
+		let id = match self.native_block_id(Some(number)) {
+			Ok(id) => id,
+			Err(err) => return Box::new(future::result(Err(err))),
+		};
+		let block = self.ethereum_storage::<ethereum::Block>(id, b"CurrentBlock");
+		let statuses = self.ethereum_storage::<Vec<TransactionStatus>>(id, b"CurrentTransactionStatuses");
+		boxed(async move {
+			match (block.await?, statuses.await?) {
+				(Some(block), Some(statuses)) => {
+					let hash = H256::from_slice(
+						Keccak256::digest(&rlp::encode(&block.header)).as_slice(),
+					);
+
+					Ok(Some(rich_block_build(
+						block,
+						statuses.into_iter().map(|s| Some(s)).collect(),
+						Some(hash),
+						full,
+					)))
+				},
+				_ => Ok(None),
+			}
+		})
+	}
+
+	fn send_raw_transaction(&self, bytes: Bytes) -> BoxFuture<H256> {
+		let transaction = match rlp::decode::<ethereum::Transaction>(&bytes.0[..]) {
+			Ok(transaction) => transaction,
+			Err(_) => return Box::new(
+				future::result(Err(internal_err("decode transaction failed")))
+			),
+		};
+		let transaction_hash = H256::from_slice(
+			Keccak256::digest(&rlp::encode(&transaction)).as_slice()
+		);
+		let hash = self.client.info().best_hash;
+		let uxt = self.convert_transaction.convert_transaction(transaction);
+
+		Box::new(
+			self.pool
+				.submit_one(&BlockId::hash(hash), TransactionSource::Local, uxt)
+				.map_ok(move |_| transaction_hash)
+				.map_err(pool_err)
+				.compat()
+		)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sp_io::hashing::{blake2_128, twox_128};
+
+	fn blake2_128_concat(bytes: &[u8]) -> Vec<u8> {
+		[&blake2_128(bytes)[..], bytes].concat()
+	}
+
+	#[test]
+	fn remote_read_keys_match_the_runtime_storage() {
+		let address = H160::repeat_byte(0xaa);
+
+		assert_eq!(
+			storage_prefix_build(b"Ethereum", b"CurrentBlock"),
+			[twox_128(b"Ethereum"), twox_128(b"CurrentBlock")].concat(),
+		);
+		assert_eq!(
+			account_codes_key(address),
+			[
+				&twox_128(b"EVM")[..],
+				&twox_128(b"AccountCodes")[..],
+				&blake2_128_concat(address.as_bytes())[..],
+			].concat(),
+		);
+		// The runtime keys storage slots by their big-endian index.
+		let slot = H256::from_low_u64_be(0x0102);
+		assert_eq!(
+			account_storages_key(address, U256::from(0x0102)),
+			[
+				&twox_128(b"EVM")[..],
+				&twox_128(b"AccountStorages")[..],
+				&blake2_128_concat(address.as_bytes())[..],
+				&blake2_128_concat(slot.as_bytes())[..],
+			].concat(),
+		);
+	}
+}
@@ -26,10 +26,9 @@ use sp_runtime::traits::{
 	UniqueSaturatedInto
 };
 use sp_transaction_pool::TransactionPool;
-use sp_api::{ProvideRuntimeApi, BlockId};
+use sp_api::ProvideRuntimeApi;
 use sp_blockchain::{Error as BlockChainError, HeaderMetadata, HeaderBackend};
-use sp_storage::{StorageKey, StorageData};
-use sp_io::hashing::twox_128;
+use sp_storage::StorageKey;
 use sc_client_api::{
 	backend::{StorageProvider, Backend, StateBackend, AuxStore},
 	client::BlockchainEvents
@@ -44,19 +43,17 @@ use jsonrpc_pubsub::{
 use fc_rpc_core::EthPubSubApi::{self as EthPubSubApiT};
 use fc_rpc_core::types::{
 	Rich, Header, Bytes, Log, FilteredParams,
-	pubsub::{Kind, Params, Result as PubSubResult, PubSubSyncStatus}
+	pubsub::{Kind, Params, Result as PubSubResult}
 };
 use ethereum_types::{H256, U256};
-use codec::Decode;
 use sha3::{Keccak256, Digest};
 
 pub use fc_rpc_core::EthPubSubApiServer;
 use futures::{StreamExt as _, TryStreamExt as _};
 
 use jsonrpc_core::{Result as JsonRpcResult, futures::{Future, Sink}};
-use fp_rpc::{EthereumRuntimeRPCApi, TransactionStatus};
-
-use sc_network::{NetworkService, ExHashT};
+use fp_rpc::{EthereumRuntimeRPCApi, TransactionReceipt};
+use crate::{pool_transaction_hashes, storage_prefix_build, EthBlockDataCache, EthNetworkStatus};
 
 #[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
 pub struct HexEncodedIdProvider {
@@ -82,22 +79,24 @@ impl IdProvider for HexEncodedIdProvider {
 	}
 }
 
-pub struct EthPubSubApi<B: BlockT, P, C, BE, H: ExHashT> {
-	_pool: Arc<P>,
+pub struct EthPubSubApi<B: BlockT, P, C, BE> {
+	pool: Arc<P>,
 	client: Arc<C>,
-	network: Arc<NetworkService<B, H>>,
+	network_status: Arc<EthNetworkStatus>,
 	subscriptions: SubscriptionManager<HexEncodedIdProvider>,
+	block_data_cache: Arc<EthBlockDataCache<B>>,
 	_marker: PhantomData<(B, BE)>,
 }
 
-impl<B: BlockT, P, C, BE, H: ExHashT> EthPubSubApi<B, P, C, BE, H> {
+impl<B: BlockT, P, C, BE> EthPubSubApi<B, P, C, BE> {
 	pub fn new(
-		_pool: Arc<P>,
+		pool: Arc<P>,
 		client: Arc<C>,
-		network: Arc<NetworkService<B, H>>,
+		network_status: Arc<EthNetworkStatus>,
 		subscriptions: SubscriptionManager<HexEncodedIdProvider>,
+		block_data_cache: Arc<EthBlockDataCache<B>>,
 	) -> Self {
-		Self { _pool, client, network, subscriptions, _marker: PhantomData }
+		Self { pool, client, network_status, subscriptions, block_data_cache, _marker: PhantomData }
 	}
 }
 
@@ -144,7 +143,7 @@ impl SubscriptionResult {
 	pub fn logs(
 		&self,
 		block_input: Option<ethereum::Block>,
-		receipts: Vec<ethereum::Receipt>,
+		receipts: Vec<TransactionReceipt>,
 		params: &FilteredParams
 	) -> Vec<Log> {
 		if block_input.is_none() {
@@ -181,7 +180,7 @@ impl SubscriptionResult {
 						block_hash: block_hash,
 						block_number: Some(block.header.number),
 						transaction_hash: transaction_hash,
-						transaction_index: Some(U256::from(log_index)),
+						transaction_index: Some(U256::from(receipt_index)),
 						log_index: Some(U256::from(log_index)),
 						transaction_log_index: Some(U256::from(
 							transaction_log_index
@@ -228,10 +227,6 @@ impl SubscriptionResult {
 	}
 }
 
-fn storage_prefix_build(module: &[u8], storage: &[u8]) -> Vec<u8> {
-	[twox_128(module), twox_128(storage)].concat().to_vec()
-}
-
 macro_rules! stream_build {
 	($context:expr => $module:expr, $storage:expr) => {{
 		let key: StorageKey = StorageKey(
@@ -247,7 +242,7 @@ macro_rules! stream_build {
 	}};
 }
 
-impl<B: BlockT, P, C, BE, H: ExHashT> EthPubSubApiT for EthPubSubApi<B, P, C, BE, H>
+impl<B: BlockT, P, C, BE> EthPubSubApiT for EthPubSubApi<B, P, C, BE>
 	where
 		B: BlockT<Hash=H256> + Send + Sync + 'static,
 		P: TransactionPool<Block=B> + Send + Sync + 'static,
@@ -273,7 +268,7 @@ impl<B: BlockT, P, C, BE, H: ExHashT> EthPubSubApiT for EthPubSubApi<B, P, C, BE
 		};
 
 		let client = self.client.clone();
-		let network = self.network.clone();
+		let block_data_cache = self.block_data_cache.clone();
 		match kind {
 			Kind::Logs => {
 				if let Some(stream) = stream_build!(
@@ -281,21 +276,12 @@ impl<B: BlockT, P, C, BE, H: ExHashT> EthPubSubApiT for EthPubSubApi<B, P, C, BE
 				) {
 					self.subscriptions.add(subscriber, |sink| {
 						let stream = stream
-						.flat_map(move |(block_hash, changes)| {
-							let id = BlockId::Hash(block_hash);
-							let data = changes.iter().last().unwrap().2.unwrap();
-							let receipts: Vec<ethereum::Receipt> =
-								Decode::decode(&mut &data.0[..]).unwrap();
-							let block: Option<ethereum::Block> = if let Ok(Some(data)) = client.storage(
-								&id,
-								&StorageKey(
-									storage_prefix_build(b"Ethereum", b"CurrentBlock")
-								)
-							) {
-								if let Ok(result) = Decode::decode(&mut &data.0[..]) {
-									Some(result)
-								} else { None }
-							} else { None };
+						.flat_map(move |(block_hash, _changes)| {
+							// Read through the cache, which also decodes the
+							// receipts of runtimes before the current format.
+							let block = block_data_cache.current_block(client.as_ref(), block_hash);
+							let receipts = block_data_cache.current_receipts(client.as_ref(), block_hash)
+								.unwrap_or_default();
 							futures::stream::iter(
 								SubscriptionResult::new()
 									.logs(block, receipts, &filtered_params)
@@ -326,14 +312,14 @@ impl<B: BlockT, P, C, BE, H: ExHashT> EthPubSubApiT for EthPubSubApi<B, P, C, BE
 				) {
 					self.subscriptions.add(subscriber, |sink| {
 						let stream = stream
-						.map(|(_block, changes)| {
-							let data = changes.iter().last().unwrap().2.unwrap();
-							let block: ethereum::Block =
-								Decode::decode(&mut &data.0[..]).unwrap();
-							return Ok::<_, ()>(Ok(
-								SubscriptionResult::new()
-									.new_heads(block)
-							));
+						.filter_map(move |(substrate_hash, _changes)| {
+							let block = block_data_cache.current_block(client.as_ref(), substrate_hash);
+							futures::future::ready(block.map(|block| {
+								Ok::<_, ()>(Ok(
+									SubscriptionResult::new()
+										.new_heads(block)
+								))
+							}))
 						})
 						.compat();
 
@@ -347,92 +333,51 @@ impl<B: BlockT, P, C, BE, H: ExHashT> EthPubSubApiT for EthPubSubApi<B, P, C, BE
 				}
 			},
 			Kind::NewPendingTransactions => {
-				if let Some(stream) = stream_build!(
-					self => b"Ethereum", b"Pending"
-				) {
-					self.subscriptions.add(subscriber, |sink| {
-						let stream = stream
-						.flat_map(|(_block, changes)| {
-							let mut transactions: Vec<ethereum::Transaction> = vec![];
-							let storage: Vec<Option<StorageData>> = changes.iter()
-								.filter_map(|(o_sk, _k, v)| {
-									if o_sk.is_none() {
-										Some(v.cloned())
-									} else { None }
-								}).collect();
-							for change in storage {
-								if let Some(data) = change {
-									let storage: Vec<(
-										ethereum::Transaction,
-										TransactionStatus,
-										ethereum::Receipt
-									)> = Decode::decode(&mut &data.0[..]).unwrap();
-									let tmp: Vec<ethereum::Transaction> =
-										storage.iter().map(|x| x.0.clone()).collect();
-									transactions.extend(tmp);
-								}
-							}
-							futures::stream::iter(transactions)
-						})
-						.map(|transaction| {
-							return Ok::<Result<
-								PubSubResult,
-								jsonrpc_core::types::error::Error
-							>, ()>(Ok(
-								PubSubResult::TransactionHash(H256::from_slice(
-									Keccak256::digest(
-										&rlp::encode(&transaction)
-									).as_slice()
-								))
-							));
-						})
-						.compat();
-
-						sink
-							.sink_map_err(|e| warn!(
-								"Error sending notifications: {:?}", e
-							))
-							.send_all(stream)
-							.map(|_| ())
-					});
-				}
+				let pool = self.pool.clone();
+				let client = self.client.clone();
+				let stream = self.pool.import_notification_stream()
+					.flat_map(move |hash| futures::stream::iter(
+						pool_transaction_hashes(client.as_ref(), pool.as_ref(), &hash)
+					))
+					.map(|transaction_hash| {
+						return Ok::<Result<
+							PubSubResult,
+							jsonrpc_core::types::error::Error
+						>, ()>(Ok(
+							PubSubResult::TransactionHash(transaction_hash)
+						));
+					})
+					.compat();
+				self.subscriptions.add(subscriber, |sink| {
+					sink
+						.sink_map_err(|e| warn!(
+							"Error sending notifications: {:?}", e
+						))
+						.send_all(stream)
+						.map(|_| ())
+				});
 			},
 			Kind::Syncing => {
-				if let Some(stream) = stream_build!(
-					self => b"Ethereum", b"CurrentBlock"
-				) {
-					self.subscriptions.add(subscriber, |sink| {
-						let mut previous_syncing = network.is_major_syncing();
-						let stream = stream
-						.filter_map(move |(_, _)| {
-							let syncing = network.is_major_syncing();
-							if previous_syncing != syncing {
-								previous_syncing = syncing;
-								futures::future::ready(Some(syncing))
-							} else {
-								futures::future::ready(None)
-							}
-						})
-						.map(|syncing| {
-							return Ok::<Result<
-								PubSubResult,
-								jsonrpc_core::types::error::Error
-							>, ()>(Ok(
-								PubSubResult::SyncState(PubSubSyncStatus {
-									syncing: syncing
-								})
-							));
-						})
-						.compat();
-						sink
-							.sink_map_err(|e| warn!(
-								"Error sending notifications: {:?}", e
-							))
-							.send_all(stream)
-							.map(|_| ())
+				let stream = self.network_status.subscribe();
+				self.subscriptions.add(subscriber, |sink| {
+					let stream = stream
+					.map(|status| {
+						return Ok::<Result<
+							PubSubResult,
+							jsonrpc_core::types::error::Error
+						>, ()>(Ok(
+							PubSubResult::SyncState(status)
+						));
+					})
+					.compat();
+					sink
+						.sink_map_err(|e| warn!(
+							"Error sending notifications: {:?}", e
+						))
+						.send_all(stream)
+						.map(|_| ())
 
-					});
-				}
+				});
 			},
 		}
 	}
@@ -0,0 +1,138 @@
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+// This file is part of Frontier.
+//
+// Copyright (c) 2020 Parity Technologies (UK) Ltd.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! uniarts_evm rpc, the EVM view of the Substrate accounts.
+
+use std::{marker::PhantomData, sync::Arc};
+use codec::Codec;
+use ethereum_types::{H160, H256, U256};
+use jsonrpc_core::Result;
+use sp_api::{ProvideRuntimeApi, BlockId};
+use sp_blockchain::HeaderBackend;
+use sp_runtime::traits::Block as BlockT;
+use fp_rpc::{EthereumRuntimeRPCApi, EvmAccountApi as EvmAccountRuntimeApi};
+pub use fc_rpc_core::EvmAccountApiServer;
+use fc_rpc_core::EvmAccountApi as EvmAccountApiT;
+use fc_rpc_core::types::EvmBalances;
+use crate::internal_err;
+
+pub struct EvmAccount<B: BlockT, C, AccountId> {
+	client: Arc<C>,
+	_marker: PhantomData<(B, AccountId)>,
+}
+
+impl<B: BlockT, C, AccountId> EvmAccount<B, C, AccountId> {
+	pub fn new(client: Arc<C>) -> Self {
+		Self { client, _marker: PhantomData }
+	}
+}
+
+impl<B, C, AccountId> EvmAccount<B, C, AccountId> where
+	B: BlockT<Hash=H256>,
+	C: HeaderBackend<B>,
+{
+	fn block_id(&self, at: Option<H256>) -> BlockId<B> {
+		BlockId::Hash(at.unwrap_or_else(|| self.client.info().best_hash))
+	}
+}
+
+impl<B, C, AccountId> EvmAccountApiT<AccountId> for EvmAccount<B, C, AccountId> where
+	B: BlockT<Hash=H256> + Send + Sync + 'static,
+	C: ProvideRuntimeApi<B> + HeaderBackend<B> + Send + Sync + 'static,
+	C::Api: EthereumRuntimeRPCApi<B> + EvmAccountRuntimeApi<B, AccountId>,
+	AccountId: Codec + Clone + Send + Sync + 'static,
+{
+	fn account_id(&self, address: H160, at: Option<H256>) -> Result<AccountId> {
+		self.client.runtime_api().account_id(&self.block_id(at), address)
+			.map_err(|err| internal_err(format!("fetch runtime account id failed: {:?}", err)))
+	}
+
+	fn evm_address(&self, account: AccountId, at: Option<H256>) -> Result<Option<H160>> {
+		self.client.runtime_api().evm_address(&self.block_id(at), account)
+			.map_err(|err| internal_err(format!("fetch runtime evm address failed: {:?}", err)))
+	}
+
+	fn balances(&self, account: AccountId, at: Option<H256>) -> Result<EvmBalances> {
+		let id = self.block_id(at);
+		let api = self.client.runtime_api();
+		let substrate = api.free_balance(&id, account.clone())
+			.map_err(|err| internal_err(format!("fetch runtime free balance failed: {:?}", err)))?;
+		let address = api.evm_address(&id, account)
+			.map_err(|err| internal_err(format!("fetch runtime evm address failed: {:?}", err)))?;
+		let evm = match address {
+			Some(address) => api.account_basic(&id, address)
+				.map_err(|err| internal_err(format!("fetch runtime account basic failed: {:?}", err)))?
+				.balance,
+			None => Default::default(),
+		};
+
+		Ok(evm_balances(address, substrate, evm))
+	}
+}
+
+/// Balances of an account whose EVM address, if any, holds `evm`.
+fn evm_balances(address: Option<H160>, substrate: U256, evm: U256) -> EvmBalances {
+	EvmBalances {
+		address,
+		substrate,
+		evm,
+		total: substrate.saturating_add(evm),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use jsonrpc_core::serde_json;
+
+	#[test]
+	fn balances_sum_both_sides() {
+		let address = H160::repeat_byte(0x11);
+		let balances = evm_balances(Some(address), 40.into(), 2.into());
+
+		assert_eq!(balances.address, Some(address));
+		assert_eq!(balances.total, 42.into());
+	}
+
+	#[test]
+	fn balances_without_evm_address_only_count_substrate() {
+		let balances = evm_balances(None, 40.into(), U256::zero());
+
+		assert_eq!(balances.address, None);
+		assert_eq!(balances.total, 40.into());
+	}
+
+	#[test]
+	fn balances_total_saturates() {
+		let balances = evm_balances(None, U256::max_value(), 1.into());
+
+		assert_eq!(balances.total, U256::max_value());
+	}
+
+	#[test]
+	fn balances_serialize_in_camel_case() {
+		let balances = evm_balances(Some(H160::repeat_byte(0x11)), 1.into(), 2.into());
+
+		assert_eq!(serde_json::to_value(&balances).unwrap(), serde_json::json!({
+			"address": "0x1111111111111111111111111111111111111111",
+			"substrate": "0x1",
+			"evm": "0x2",
+			"total": "0x3",
+		}));
+	}
+}
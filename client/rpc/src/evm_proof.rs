@@ -0,0 +1,110 @@
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+// This file is part of Frontier.
+//
+// Copyright (c) 2020 Parity Technologies (UK) Ltd.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! uniarts_evm proof rpc, proofs of the EVM accounts in the Substrate state.
+
+use std::{marker::PhantomData, sync::Arc};
+use ethereum_types::{H160, H256, U256};
+use jsonrpc_core::Result;
+use sha3::{Digest, Keccak256};
+use sp_api::{ProvideRuntimeApi, BlockId, HeaderT};
+use sp_blockchain::HeaderBackend;
+use sp_runtime::traits::Block as BlockT;
+use sc_client_api::ProofProvider;
+use fp_rpc::EthereumRuntimeRPCApi;
+pub use fc_rpc_core::EvmProofApiServer;
+use fc_rpc_core::EvmProofApi as EvmProofApiT;
+use fc_rpc_core::types::{Bytes, EvmAccountProof, StorageProof};
+use crate::internal_err;
+use crate::eth::{account_codes_key, account_storages_key};
+
+pub struct EvmProof<B: BlockT, C> {
+	client: Arc<C>,
+	_marker: PhantomData<B>,
+}
+
+impl<B: BlockT, C> EvmProof<B, C> {
+	pub fn new(client: Arc<C>) -> Self {
+		Self { client, _marker: PhantomData }
+	}
+}
+
+impl<B, C> EvmProof<B, C> where
+	B: BlockT<Hash=H256>,
+	C: ProofProvider<B>,
+{
+	fn read_proof(&self, id: &BlockId<B>, keys: Vec<Vec<u8>>) -> Result<Vec<Bytes>> {
+		let proof = self.client.read_proof(id, &mut keys.iter().map(|key| &key[..]))
+			.map_err(|err| internal_err(format!("fetch storage proof failed: {:?}", err)))?;
+		Ok(proof.iter_nodes().map(Bytes).collect())
+	}
+}
+
+impl<B, C> EvmProofApiT for EvmProof<B, C> where
+	B: BlockT<Hash=H256> + Send + Sync + 'static,
+	C: ProvideRuntimeApi<B> + HeaderBackend<B> + ProofProvider<B> + Send + Sync + 'static,
+	C::Api: EthereumRuntimeRPCApi<B>,
+{
+	fn proof(&self, address: H160, storage_keys: Vec<U256>, at: Option<H256>) -> Result<EvmAccountProof> {
+		let block_hash = at.unwrap_or_else(|| self.client.info().best_hash);
+		let id = BlockId::Hash(block_hash);
+		let header = self.client.header(id)
+			.map_err(|err| internal_err(format!("fetch header failed: {:?}", err)))?
+			.ok_or_else(|| internal_err("block not found"))?;
+
+		let api = self.client.runtime_api();
+		let supported = api.has_api_with::<dyn EthereumRuntimeRPCApi<B>, _>(&id, |version| version >= 5)
+			.map_err(|err| internal_err(format!("fetch runtime version failed: {:?}", err)))?;
+		if !supported {
+			return Err(internal_err("proofs are not supported by the runtime of this block"));
+		}
+		let account_key = api.account_basic_key(&id, address)
+			.map_err(|err| internal_err(format!("fetch runtime account key failed: {:?}", err)))?;
+		let account = api.account_basic(&id, address)
+			.map_err(|err| internal_err(format!("fetch runtime account basic failed: {:?}", err)))?;
+		let code = api.account_code_at(&id, address)
+			.map_err(|err| internal_err(format!("fetch runtime account code failed: {:?}", err)))?;
+
+		let account_proof = self.read_proof(
+			&id,
+			vec![account_key, account_codes_key(address)],
+		)?;
+		let storage_proof = storage_keys.into_iter()
+			.map(|index| {
+				let value = api.storage_at(&id, address, index)
+					.map_err(|err| internal_err(format!("fetch runtime storage failed: {:?}", err)))?;
+				Ok(StorageProof {
+					key: index,
+					value: U256::from(value.as_bytes()),
+					proof: self.read_proof(&id, vec![account_storages_key(address, index)])?,
+				})
+			})
+			.collect::<Result<Vec<_>>>()?;
+
+		Ok(EvmAccountProof {
+			address,
+			balance: account.balance,
+			nonce: account.nonce,
+			code_hash: H256::from_slice(Keccak256::digest(&code).as_slice()),
+			block_hash,
+			state_root: *header.state_root(),
+			account_proof,
+			storage_proof,
+		})
+	}
+}
@@ -16,13 +16,32 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
+mod cache;
+mod eip712;
 mod eth;
+mod eth_light;
 mod eth_pubsub;
+mod evm_account;
+mod evm_proof;
+mod network;
+mod personal;
+mod signer;
+mod trace;
+mod txpool;
 
+pub use cache::EthBlockDataCache;
 pub use eth::{
 	EthApi, EthApiServer, EthFilterApi, EthFilterApiServer, NetApi, NetApiServer, Web3Api, Web3ApiServer
 };
+pub use eth_light::{LightEthApi, EthLightApiServer};
 pub use eth_pubsub::{EthPubSubApi, EthPubSubApiServer, HexEncodedIdProvider};
+pub use evm_account::{EvmAccount, EvmAccountApiServer};
+pub use evm_proof::{EvmProof, EvmProofApiServer};
+pub use network::EthNetworkStatus;
+pub use personal::{Personal, PersonalApiServer};
+pub use signer::{EthKeystoreSigner, ETHR};
+pub use trace::{Trace, TraceApiServer};
+pub use txpool::{TxPool, TxPoolApiServer};
 
 use ethereum_types::{H160, H256};
 use ethereum::{
@@ -31,7 +50,41 @@ use ethereum::{
 use jsonrpc_core::{ErrorCode, Error, Value};
 use rustc_hex::ToHex;
 use pallet_evm::ExitReason;
+use pallet_ethereum::TransactionValidationError;
+use sp_runtime::{transaction_validity::InvalidTransaction, traits::Block as BlockT, generic::BlockId};
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_transaction_pool::{TransactionPool, InPoolTransaction};
+use fp_rpc::EthereumRuntimeRPCApi;
+use sp_transaction_pool::error::{Error as PoolError, IntoPoolError};
 use sha3::{Digest, Keccak256};
+use sp_io::hashing::twox_128;
+
+/// Storage key prefix of a storage item of a runtime module.
+pub(crate) fn storage_prefix_build(module: &[u8], storage: &[u8]) -> Vec<u8> {
+	[twox_128(module), twox_128(storage)].concat().to_vec()
+}
+
+/// Hashes of the Ethereum transactions of a transaction imported in the pool,
+/// found by the runtime at the best block.
+pub fn pool_transaction_hashes<B, C, P>(client: &C, pool: &P, hash: &P::Hash) -> Vec<H256> where
+	B: BlockT,
+	C: ProvideRuntimeApi<B> + HeaderBackend<B>,
+	C::Api: EthereumRuntimeRPCApi<B>,
+	P: TransactionPool<Block=B>,
+{
+	let xt = match pool.ready_transaction(hash) {
+		Some(xt) => xt.data().clone(),
+		None => return Vec::new(),
+	};
+	let best = BlockId::Hash(client.info().best_hash);
+	// Runtimes predating `extrinsic_filter` have no pending transactions to report.
+	client.runtime_api().extrinsic_filter(&best, vec![xt])
+		.unwrap_or_default()
+		.iter()
+		.map(|transaction| H256::from_slice(Keccak256::digest(&rlp::encode(transaction)).as_slice()))
+		.collect()
+}
 
 pub fn internal_err<T: ToString>(message: T) -> Error {
 	Error {
@@ -41,6 +94,52 @@ pub fn internal_err<T: ToString>(message: T) -> Error {
 	}
 }
 
+/// Error returned when a query exceeds one of the node's limits.
+pub fn limit_exceeded_err<T: ToString>(message: T) -> Error {
+	Error {
+		code: ErrorCode::ServerError(-32005),
+		message: message.to_string(),
+		data: None
+	}
+}
+
+/// Error returned when the transaction pool rejects a transaction, with the
+/// reason of the rejection as in other Ethereum clients.
+pub fn pool_err<E: IntoPoolError + std::fmt::Debug>(err: E) -> Error {
+	let err = match err.into_pool_error() {
+		Ok(err) => err,
+		Err(err) => return internal_err(format!("submit transaction to pool failed: {:?}", err)),
+	};
+	let (message, data) = match err {
+		PoolError::InvalidTransaction(InvalidTransaction::Custom(code)) => {
+			let message = match TransactionValidationError::from_code(code) {
+				Some(TransactionValidationError::InvalidChainId) => "invalid chain id",
+				Some(TransactionValidationError::InvalidSignature) => "invalid sender",
+				Some(TransactionValidationError::GasLimitTooHigh) => "exceeds block gas limit",
+				Some(TransactionValidationError::GasLimitTooLow) => "intrinsic gas too low",
+				Some(TransactionValidationError::GasPriceTooLow) => "transaction underpriced",
+				Some(TransactionValidationError::TransactionTooLarge) => "oversized data",
+				Some(TransactionValidationError::UnknownError) | None => "invalid transaction",
+			};
+			(message.to_string(), Some(Value::from(code)))
+		},
+		PoolError::InvalidTransaction(InvalidTransaction::Stale) => ("nonce too low".to_string(), None),
+		PoolError::InvalidTransaction(InvalidTransaction::Payment) =>
+			("insufficient funds for gas * price + value".to_string(), None),
+		PoolError::InvalidTransaction(InvalidTransaction::ExhaustsResources) =>
+			("exceeds block gas limit".to_string(), None),
+		PoolError::TooLowPriority { .. } => ("replacement transaction underpriced".to_string(), None),
+		PoolError::AlreadyImported(_) => ("already known".to_string(), None),
+		PoolError::ImmediatelyDropped => ("txpool is full".to_string(), None),
+		err => (format!("submit transaction to pool failed: {:?}", err), None),
+	};
+	Error {
+		code: ErrorCode::ServerError(-32000),
+		message,
+		data,
+	}
+}
+
 pub fn error_on_execution_failure(reason: &ExitReason, data: &[u8]) -> Result<(), Error> {
 	match reason {
 		ExitReason::Succeed(_) => Ok(()),
@@ -101,6 +200,57 @@ pub trait EthSigner: Send + Sync {
 		message: ethereum::TransactionMessage,
 		address: &H160,
 	) -> Result<ethereum::Transaction, Error>;
+	/// Sign a 32-byte hash using the given account, returning `r ++ s ++ v`.
+	fn sign_hash(&self, hash: H256, address: &H160) -> Result<[u8; 65], Error>;
+}
+
+/// Address of the account of a secret key.
+pub(crate) fn secret_address(secret: &secp256k1::SecretKey) -> H160 {
+	let public = secp256k1::PublicKey::from_secret_key(secret);
+	let mut res = [0u8; 64];
+	res.copy_from_slice(&public.serialize()[1..65]);
+	H160::from(H256::from_slice(Keccak256::digest(&res).as_slice()))
+}
+
+/// Sign a transaction message with a secret key.
+pub(crate) fn sign_transaction_message(
+	message: ethereum::TransactionMessage,
+	secret: &secp256k1::SecretKey,
+) -> Result<ethereum::Transaction, Error> {
+	let signing_message = secp256k1::Message::parse_slice(&message.hash()[..])
+		.map_err(|_| internal_err("invalid signing message"))?;
+	let (signature, recid) = secp256k1::sign(&signing_message, secret);
+
+	let v = match message.chain_id {
+		None => 27 + recid.serialize() as u64,
+		Some(chain_id) => 2 * chain_id + 35 + recid.serialize() as u64,
+	};
+	let rs = signature.serialize();
+	let r = H256::from_slice(&rs[0..32]);
+	let s = H256::from_slice(&rs[32..64]);
+
+	Ok(ethereum::Transaction {
+		nonce: message.nonce,
+		gas_price: message.gas_price,
+		gas_limit: message.gas_limit,
+		action: message.action,
+		value: message.value,
+		input: message.input,
+		signature: ethereum::TransactionSignature::new(v, r, s)
+			.ok_or(internal_err("signer generated invalid signature"))?,
+	})
+}
+
+/// Sign a hash with a secret key, returning `r ++ s ++ v` with `v` 27 or 28.
+pub(crate) fn sign_hash_with(hash: H256, secret: &secp256k1::SecretKey) -> Result<[u8; 65], Error> {
+	let signing_message = secp256k1::Message::parse_slice(hash.as_bytes())
+		.map_err(|_| internal_err("invalid signing message"))?;
+	let (signature, recid) = secp256k1::sign(&signing_message, secret);
+
+	let mut res = [0u8; 65];
+	res[0..64].copy_from_slice(&signature.serialize()[..]);
+	res[64] = 27 + recid.serialize();
+	Ok(res)
 }
 
 pub struct EthDevSigner {
@@ -120,17 +270,17 @@ impl EthDevSigner {
 			],
 		}
 	}
+
+	fn secret(&self, address: &H160) -> Result<&secp256k1::SecretKey, Error> {
+		self.keys.iter()
+			.find(|secret| &secret_address(secret) == address)
+			.ok_or(internal_err("signer not available"))
+	}
 }
 
 impl EthSigner for EthDevSigner {
 	fn accounts(&self) -> Vec<H160> {
-		self.keys.iter().map(|secret| {
-			let public = secp256k1::PublicKey::from_secret_key(secret);
-			let mut res = [0u8; 64];
-			res.copy_from_slice(&public.serialize()[1..65]);
-
-			H160::from(H256::from_slice(Keccak256::digest(&res).as_slice()))
-		}).collect()
+		self.keys.iter().map(secret_address).collect()
 	}
 
 	fn sign(
@@ -138,44 +288,10 @@ impl EthSigner for EthDevSigner {
 		message: ethereum::TransactionMessage,
 		address: &H160,
 	) -> Result<ethereum::Transaction, Error> {
-		let mut transaction = None;
-
-		for secret in &self.keys {
-			let key_address = {
-				let public = secp256k1::PublicKey::from_secret_key(secret);
-				let mut res = [0u8; 64];
-				res.copy_from_slice(&public.serialize()[1..65]);
-				H160::from(H256::from_slice(Keccak256::digest(&res).as_slice()))
-			};
-
-			if &key_address == address {
-				let signing_message = secp256k1::Message::parse_slice(&message.hash()[..])
-					.map_err(|_| internal_err("invalid signing message"))?;
-				let (signature, recid) = secp256k1::sign(&signing_message, secret);
-
-				let v = match message.chain_id {
-					None => 27 + recid.serialize() as u64,
-					Some(chain_id) => 2 * chain_id + 35 + recid.serialize() as u64,
-				};
-				let rs = signature.serialize();
-				let r = H256::from_slice(&rs[0..32]);
-				let s = H256::from_slice(&rs[32..64]);
-
-				transaction = Some(ethereum::Transaction {
-					nonce: message.nonce,
-					gas_price: message.gas_price,
-					gas_limit: message.gas_limit,
-					action: message.action,
-					value: message.value,
-					input: message.input.clone(),
-					signature: ethereum::TransactionSignature::new(v, r, s)
-						.ok_or(internal_err("signer generated invalid signature"))?,
-				});
-
-				break
-			}
-		}
+		sign_transaction_message(message, self.secret(address)?)
+	}
 
-		transaction.ok_or(internal_err("signer not available"))
+	fn sign_hash(&self, hash: H256, address: &H160) -> Result<[u8; 65], Error> {
+		sign_hash_with(hash, self.secret(address)?)
 	}
 }
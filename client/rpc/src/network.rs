@@ -0,0 +1,193 @@
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+// This file is part of Frontier.
+//
+// Copyright (c) 2020 Parity Technologies (UK) Ltd.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Network and sync status reported by the Ethereum RPC.
+
+use std::sync::{Arc, Mutex};
+use ethereum_types::U256;
+use futures::{channel::mpsc, Stream, StreamExt};
+use sp_runtime::traits::{Block as BlockT, UniqueSaturatedInto};
+use sp_blockchain::HeaderBackend;
+use sc_network::{NetworkStatus, SyncState, network_state::NetworkState};
+use fc_rpc_core::types::{SyncInfo, SyncStatus, pubsub::PubSubSyncStatus};
+
+#[derive(Clone, Debug, Default)]
+struct State {
+	/// Whether the node listens on any address.
+	listening: Option<bool>,
+	/// Number of connected peers.
+	peers: Option<usize>,
+	/// Whether a status was received from the network worker.
+	reported: bool,
+	/// Best block when the current major sync started, while syncing.
+	starting_block: Option<U256>,
+	/// Best block announced by the peers.
+	highest_block: Option<U256>,
+}
+
+/// Network status of the node, kept up to date from the status streams of the
+/// network worker by `status_task` and `state_task`.
+#[derive(Default)]
+pub struct EthNetworkStatus {
+	state: Mutex<State>,
+	sinks: Mutex<Vec<mpsc::UnboundedSender<PubSubSyncStatus>>>,
+}
+
+fn sync_info<B, C>(client: &C, starting_block: U256, highest_block: Option<U256>) -> SyncInfo where
+	B: BlockT,
+	C: HeaderBackend<B>,
+{
+	let info = client.info();
+	let best = U256::from(UniqueSaturatedInto::<u128>::unique_saturated_into(info.best_number));
+	let finalized = U256::from(UniqueSaturatedInto::<u128>::unique_saturated_into(info.finalized_number));
+	SyncInfo {
+		starting_block,
+		current_block: best,
+		highest_block: highest_block.map_or(best, |highest| highest.max(best)),
+		warp_chunks_amount: None,
+		warp_chunks_processed: None,
+		finalization_lag: Some(best.saturating_sub(finalized)),
+	}
+}
+
+impl EthNetworkStatus {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Whether the node listens for connections, `None` until reported.
+	pub fn listening(&self) -> Option<bool> {
+		self.state.lock().ok().and_then(|state| state.listening)
+	}
+
+	/// Number of connected peers, `None` until reported.
+	pub fn peer_count(&self) -> Option<usize> {
+		self.state.lock().ok().and_then(|state| state.peers)
+	}
+
+	/// Sync status of the node, `None` until reported.
+	pub fn sync_status<B, C>(&self, client: &C) -> Option<SyncStatus> where
+		B: BlockT,
+		C: HeaderBackend<B>,
+	{
+		let state = self.state.lock().ok()?.clone();
+		if !state.reported {
+			return None;
+		}
+		Some(match state.starting_block {
+			Some(starting_block) => SyncStatus::Info(sync_info(client, starting_block, state.highest_block)),
+			None => SyncStatus::None,
+		})
+	}
+
+	/// Stream of the changes of the sync status, notifying only the
+	/// transitions between syncing and synced.
+	pub fn subscribe(&self) -> mpsc::UnboundedReceiver<PubSubSyncStatus> {
+		let (sink, stream) = mpsc::unbounded();
+		if let Ok(mut sinks) = self.sinks.lock() {
+			sinks.push(sink);
+		}
+		stream
+	}
+
+	fn notify(&self, status: PubSubSyncStatus) {
+		if let Ok(mut sinks) = self.sinks.lock() {
+			sinks.retain(|sink| sink.unbounded_send(status.clone()).is_ok());
+		}
+	}
+
+	/// Drops the sinks of the subscriptions which were cancelled.
+	fn remove_closed_sinks(&self) {
+		if let Ok(mut sinks) = self.sinks.lock() {
+			sinks.retain(|sink| !sink.is_closed());
+		}
+	}
+
+	/// Updates the sync status and peers from the network status stream.
+	pub async fn status_task<B, C, S>(self: Arc<Self>, client: Arc<C>, mut statuses: S) where
+		B: BlockT,
+		C: HeaderBackend<B>,
+		S: Stream<Item=NetworkStatus<B>> + Unpin,
+	{
+		while let Some(status) = statuses.next().await {
+			// Statuses are received periodically, unlike the transitions.
+			self.remove_closed_sinks();
+			let syncing = status.sync_state == SyncState::Downloading;
+			let transition = match self.state.lock() {
+				Ok(mut state) => {
+					state.reported = true;
+					state.peers = Some(status.num_connected_peers as usize);
+					state.highest_block = status.best_seen_block
+						.map(|number| U256::from(UniqueSaturatedInto::<u128>::unique_saturated_into(number)));
+					match (syncing, state.starting_block) {
+						(true, None) => {
+							let best = U256::from(
+								UniqueSaturatedInto::<u128>::unique_saturated_into(client.info().best_number)
+							);
+							state.starting_block = Some(best);
+							Some(PubSubSyncStatus {
+								syncing: true,
+								status: Some(sync_info(client.as_ref(), best, state.highest_block)),
+							})
+						},
+						(false, Some(_)) => {
+							state.starting_block = None;
+							Some(PubSubSyncStatus { syncing: false, status: None })
+						},
+						_ => None,
+					}
+				},
+				Err(_) => None,
+			};
+			if let Some(status) = transition {
+				self.notify(status);
+			}
+		}
+	}
+
+	/// Updates whether the node listens from the network state stream.
+	pub async fn state_task<S>(self: Arc<Self>, mut states: S) where
+		S: Stream<Item=NetworkState> + Unpin,
+	{
+		while let Some(network_state) = states.next().await {
+			if let Ok(mut state) = self.state.lock() {
+				state.listening = Some(!network_state.listened_addresses.is_empty());
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn cancelled_subscriptions_are_removed() {
+		let network_status = EthNetworkStatus::new();
+		let kept = network_status.subscribe();
+		let cancelled = network_status.subscribe();
+		drop(cancelled);
+
+		network_status.remove_closed_sinks();
+		assert_eq!(network_status.sinks.lock().unwrap().len(), 1);
+
+		drop(kept);
+		network_status.remove_closed_sinks();
+		assert!(network_status.sinks.lock().unwrap().is_empty());
+	}
+}
@@ -0,0 +1,53 @@
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+// This file is part of Frontier.
+//
+// Copyright (c) 2020 Parity Technologies (UK) Ltd.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+//! Personal rpc, managing the accounts of the keystore signer.
+
+use ethereum_types::H160;
+use jsonrpc_core::Result;
+use sc_rpc::DenyUnsafe;
+pub use fc_rpc_core::PersonalApiServer;
+use fc_rpc_core::PersonalApi as PersonalApiT;
+use crate::{EthSigner, EthKeystoreSigner};
+
+pub struct Personal {
+	signer: EthKeystoreSigner,
+	deny_unsafe: DenyUnsafe,
+}
+
+impl Personal {
+	pub fn new(signer: EthKeystoreSigner, deny_unsafe: DenyUnsafe) -> Self {
+		Self { signer, deny_unsafe }
+	}
+}
+
+impl PersonalApiT for Personal {
+	fn new_account(&self, password: String) -> Result<H160> {
+		self.deny_unsafe.check_if_safe()?;
+		self.signer.new_account(&password)
+	}
+
+	fn unlock_account(&self, address: H160, password: String, duration: Option<u64>) -> Result<bool> {
+		self.deny_unsafe.check_if_safe()?;
+		self.signer.unlock(address, &password, duration)
+	}
+
+	fn list_accounts(&self) -> Result<Vec<H160>> {
+		self.deny_unsafe.check_if_safe()?;
+		Ok(self.signer.accounts())
+	}
+}
@@ -0,0 +1,195 @@
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+// This file is part of Frontier.
+//
+// Copyright (c) 2020 Parity Technologies (UK) Ltd.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+//! Ethereum signer backed by the node's keystore.
+
+use std::{collections::HashMap, sync::{Arc, Mutex}, time::{Duration, Instant}};
+use ethereum_types::{H160, H256};
+use jsonrpc_core::Error;
+use sc_keystore::LocalKeystore;
+use sp_core::{crypto::{KeyTypeId, Pair as _}, ecdsa};
+use sp_keystore::SyncCryptoStore;
+use secrecy::{ExposeSecret, SecretString};
+use sha3::{Digest, Keccak256};
+use subtle::ConstantTimeEq;
+use crate::{EthSigner, internal_err, sign_transaction_message, sign_hash_with};
+
+/// Key type of the Ethereum accounts of the keystore.
+pub const ETHR: KeyTypeId = KeyTypeId(*b"ethr");
+
+mod app {
+	use sp_application_crypto::{app_crypto, ecdsa};
+	app_crypto!(ecdsa, super::ETHR);
+}
+
+/// Number of seconds an account stays unlocked when no duration is given.
+const DEFAULT_UNLOCK_DURATION: u64 = 300;
+
+/// Signer of the `ethr` keys of the node's keystore.
+///
+/// Accounts can only sign once unlocked with the keystore password, for a
+/// limited time or until the node stops. Nodes without a keystore password
+/// can't create nor unlock accounts.
+#[derive(Clone)]
+pub struct EthKeystoreSigner {
+	keystore: Arc<LocalKeystore>,
+	password: Arc<Option<SecretString>>,
+	unlocked: Arc<Mutex<HashMap<H160, Option<Instant>>>>,
+}
+
+impl EthKeystoreSigner {
+	pub fn new(keystore: Arc<LocalKeystore>, password: Option<SecretString>) -> Self {
+		Self {
+			keystore,
+			password: Arc::new(password),
+			unlocked: Arc::new(Mutex::new(HashMap::new())),
+		}
+	}
+
+	/// Create an account in the keystore, returning its address.
+	pub fn new_account(&self, password: &str) -> Result<H160, Error> {
+		self.check_password(password)?;
+		let public = SyncCryptoStore::ecdsa_generate_new(&*self.keystore, ETHR, None)
+			.map_err(|e| internal_err(format!("failed to generate key: {:?}", e)))?;
+		public_address(&public).ok_or(internal_err("keystore generated an invalid key"))
+	}
+
+	/// Unlock an account for `duration` seconds, or until the node stops if zero.
+	pub fn unlock(&self, address: H160, password: &str, duration: Option<u64>) -> Result<bool, Error> {
+		self.check_password(password)?;
+		if !self.accounts().contains(&address) {
+			return Err(internal_err("no key for given address"))
+		}
+
+		let expiry = match duration.unwrap_or(DEFAULT_UNLOCK_DURATION) {
+			0 => None,
+			seconds => Some(Instant::now() + Duration::from_secs(seconds)),
+		};
+		self.unlocked.lock()
+			.map_err(|_| internal_err("unlocked accounts are not available"))?
+			.insert(address, expiry);
+		Ok(true)
+	}
+
+	/// Check `password` against the keystore password in constant time. Without
+	/// a keystore password, accounts can't be created nor unlocked.
+	fn check_password(&self, password: &str) -> Result<(), Error> {
+		let expected = self.password.as_ref().as_ref()
+			.map(|password| password.expose_secret().as_bytes())
+			.filter(|password| !password.is_empty())
+			.ok_or(internal_err("no keystore password configured"))?;
+		if !bool::from(password.as_bytes().ct_eq(expected)) {
+			return Err(internal_err("invalid password"))
+		}
+		Ok(())
+	}
+
+	fn is_unlocked(&self, address: &H160) -> bool {
+		match self.unlocked.lock() {
+			Ok(unlocked) => unlocked.get(address)
+				.map_or(false, |expiry| expiry.map_or(true, |expiry| Instant::now() < expiry)),
+			Err(_) => false,
+		}
+	}
+
+	fn secret(&self, address: &H160) -> Result<secp256k1::SecretKey, Error> {
+		if !self.is_unlocked(address) {
+			return Err(internal_err("authentication needed: password or unlock"))
+		}
+
+		let public = SyncCryptoStore::ecdsa_public_keys(&*self.keystore, ETHR)
+			.into_iter()
+			.find(|public| public_address(public).as_ref() == Some(address))
+			.ok_or(internal_err("signer not available"))?;
+		let pair = self.keystore.key_pair::<app::Pair>(&public.into())
+			.map_err(|e| internal_err(format!("failed to read key: {:?}", e)))?
+			.ok_or(internal_err("signer not available"))?;
+		secp256k1::SecretKey::parse_slice(&pair.to_raw_vec())
+			.map_err(|_| internal_err("invalid secret key"))
+	}
+}
+
+impl EthSigner for EthKeystoreSigner {
+	fn accounts(&self) -> Vec<H160> {
+		SyncCryptoStore::ecdsa_public_keys(&*self.keystore, ETHR)
+			.iter()
+			.filter_map(public_address)
+			.collect()
+	}
+
+	fn sign(
+		&self,
+		message: ethereum::TransactionMessage,
+		address: &H160,
+	) -> Result<ethereum::Transaction, Error> {
+		sign_transaction_message(message, &self.secret(address)?)
+	}
+
+	fn sign_hash(&self, hash: H256, address: &H160) -> Result<[u8; 65], Error> {
+		sign_hash_with(hash, &self.secret(address)?)
+	}
+}
+
+/// Address of the account of a compressed ECDSA public key.
+fn public_address(public: &ecdsa::Public) -> Option<H160> {
+	let mut compressed = [0u8; 33];
+	compressed.copy_from_slice(public.as_ref());
+	let public = secp256k1::PublicKey::parse_compressed(&compressed).ok()?;
+	Some(H160::from(H256::from_slice(Keccak256::digest(&public.serialize()[1..65]).as_slice())))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn signer(password: Option<&str>) -> EthKeystoreSigner {
+		EthKeystoreSigner::new(
+			Arc::new(LocalKeystore::in_memory()),
+			password.map(|password| SecretString::new(password.into())),
+		)
+	}
+
+	#[test]
+	fn signs_with_unlocked_accounts_only() {
+		let signer = signer(Some("secret"));
+		assert!(signer.new_account("wrong").is_err());
+		assert!(signer.new_account("").is_err());
+		let address = signer.new_account("secret").unwrap();
+		assert_eq!(signer.accounts(), vec![address]);
+
+		let hash = H256::repeat_byte(1);
+		assert!(signer.sign_hash(hash, &address).is_err());
+		assert!(signer.unlock(address, "secre", None).is_err());
+		assert!(signer.unlock(address, "secret", None).unwrap());
+
+		let signature = signer.sign_hash(hash, &address).unwrap();
+		let mut sig = [0u8; 65];
+		sig.copy_from_slice(&signature);
+		sig[64] -= 27;
+		let public = sp_io::crypto::secp256k1_ecdsa_recover(&sig, hash.as_fixed_bytes()).unwrap();
+		assert_eq!(H160::from(H256::from_slice(Keccak256::digest(&public).as_slice())), address);
+	}
+
+	#[test]
+	fn accounts_are_locked_without_a_keystore_password() {
+		for password in vec![None, Some("")] {
+			let signer = signer(password);
+			assert!(signer.new_account("").is_err());
+			assert!(signer.accounts().is_empty());
+		}
+	}
+}
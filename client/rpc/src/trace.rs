@@ -0,0 +1,361 @@
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+// This file is part of Frontier.
+//
+// Copyright (c) 2020 Parity Technologies (UK) Ltd.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! trace rpc, the call frames of the executed Ethereum transactions.
+
+use std::{collections::BTreeMap, marker::PhantomData, sync::Arc};
+use ethereum_types::{H160, H256, U256};
+use jsonrpc_core::Result;
+use sp_api::{ProvideRuntimeApi, BlockId, ApiExt};
+use sp_blockchain::HeaderBackend;
+use sp_runtime::traits::{Block as BlockT, Header as HeaderT, UniqueSaturatedInto, Zero};
+use sc_client_api::BlockBackend;
+use sha3::{Keccak256, Digest};
+use fp_evm::{CallTrace, CallType as FrameType};
+use fp_rpc::{EthereumRuntimeRPCApi, TransactionStatus};
+pub use fc_rpc_core::TraceApiServer;
+use fc_rpc_core::TraceApi as TraceApiT;
+use fc_rpc_core::types::{
+	Action, BlockNumber, CallAction, CallResult, CallType, CreateAction, CreateResult, LocalizedTrace,
+	TraceFilter, TraceResult, TraceType,
+};
+use crate::{internal_err, limit_exceeded_err};
+
+pub struct Trace<B: BlockT, C> {
+	client: Arc<C>,
+	backend: Arc<fc_db::Backend<B>>,
+	max_block_range: u32,
+	_marker: PhantomData<B>,
+}
+
+impl<B: BlockT, C> Trace<B, C> {
+	pub fn new(client: Arc<C>, backend: Arc<fc_db::Backend<B>>, max_block_range: u32) -> Self {
+		Self { client, backend, max_block_range, _marker: PhantomData }
+	}
+}
+
+impl<B, C> Trace<B, C> where
+	B: BlockT<Hash=H256> + Send + Sync + 'static,
+	C: ProvideRuntimeApi<B> + HeaderBackend<B> + BlockBackend<B> + Send + Sync + 'static,
+	C::Api: EthereumRuntimeRPCApi<B>,
+{
+	fn native_block_id(&self, number: BlockNumber) -> Result<Option<BlockId<B>>> {
+		Ok(match number {
+			BlockNumber::Hash { hash, .. } => self.canonical_block(hash)?,
+			BlockNumber::Num(number) => Some(BlockId::Number(number.unique_saturated_into())),
+			BlockNumber::Latest => Some(BlockId::Hash(self.client.info().best_hash)),
+			BlockNumber::Earliest => Some(BlockId::Number(Zero::zero())),
+			BlockNumber::Pending => None,
+		})
+	}
+
+	/// Canonical Substrate block of an Ethereum block hash.
+	fn canonical_block(&self, ethereum_hash: H256) -> Result<Option<BlockId<B>>> {
+		let hashes = fc_consensus::load_block_hash::<B, _>(self.client.as_ref(), ethereum_hash)
+			.map_err(|err| internal_err(format!("fetch aux store failed: {:?}", err)))?
+			.unwrap_or_default();
+		for hash in hashes {
+			if let Ok(Some(number)) = self.client.number(hash) {
+				if self.client.hash(number).ok().flatten() == Some(hash) {
+					return Ok(Some(BlockId::Hash(hash)));
+				}
+			}
+		}
+		Ok(None)
+	}
+
+	/// Ethereum block of a block, and the status and call traces of each of its
+	/// transactions. Blocks are replayed on top of their parent the first time
+	/// they are traced, and their traces kept in the Frontier database afterwards.
+	fn traced_block(
+		&self,
+		id: &BlockId<B>,
+	) -> Result<Option<(ethereum::Block, Vec<(TransactionStatus, Vec<CallTrace>)>)>> {
+		let substrate_hash = match self.client.block_hash_from_id(id)
+			.map_err(|err| internal_err(format!("fetch block hash failed: {:?}", err)))?
+		{
+			Some(hash) => hash,
+			None => return Ok(None),
+		};
+		let (block, statuses) = match self.ethereum_block(substrate_hash)? {
+			Some(data) => data,
+			None => return Ok(None),
+		};
+		if statuses.is_empty() {
+			return Ok(Some((block, Vec::new())));
+		}
+
+		let ethereum_hash = block.header.hash();
+		if let Some(traces) = self.backend.traces(&ethereum_hash).map_err(internal_err)? {
+			return Ok(Some((block, match_traces(statuses, traces))));
+		}
+
+		let (header, extrinsics) = match self.client.block(id)
+			.map_err(|err| internal_err(format!("fetch block failed: {:?}", err)))?
+		{
+			Some(signed) => signed.block.deconstruct(),
+			None => return Ok(None),
+		};
+		let parent = BlockId::Hash(*header.parent_hash());
+		let api = self.client.runtime_api();
+		let version = |min: u32| api.has_api_with::<dyn EthereumRuntimeRPCApi<B>, _>(&parent, |v| v >= min)
+			.map_err(|err| internal_err(format!("fetch runtime version failed: {:?}", err)));
+		let traces = if version(6)? {
+			api.trace_block(&parent, header, extrinsics)
+				.map_err(|err| internal_err(format!("runtime trace block failed: {:?}", err)))?
+		} else if version(4)? {
+			// The traces of older runtimes are in the order of the Ethereum
+			// transactions of the block.
+			let transactions = api.extrinsic_filter(&parent, extrinsics.clone())
+				.map_err(|err| internal_err(format!("runtime extrinsic filter failed: {:?}", err)))?;
+			#[allow(deprecated)]
+			let traces = api.trace_block_before_version_6(&parent, header, extrinsics)
+				.map_err(|err| internal_err(format!("runtime trace block failed: {:?}", err)))?;
+			transactions.iter()
+				.map(|transaction| H256::from_slice(Keccak256::digest(&rlp::encode(transaction)).as_slice()))
+				.zip(traces)
+				.collect()
+		} else {
+			return Err(internal_err("tracing is not supported by the runtime of this block"));
+		};
+		self.backend.write_traces(ethereum_hash, &traces).map_err(internal_err)?;
+
+		Ok(Some((block, match_traces(statuses, traces))))
+	}
+
+	fn ethereum_block(&self, substrate_hash: H256) -> Result<Option<(ethereum::Block, Vec<TransactionStatus>)>> {
+		if let Some(ethereum_hash) = self.backend.ethereum_hash(&substrate_hash).map_err(internal_err)? {
+			let block = self.backend.block(&ethereum_hash).map_err(internal_err)?;
+			let statuses = self.backend.statuses(&ethereum_hash).map_err(internal_err)?;
+			if let (Some(block), Some(statuses)) = (block, statuses) {
+				return Ok(Some((block, statuses)));
+			}
+		}
+
+		let id = BlockId::Hash(substrate_hash);
+		let api = self.client.runtime_api();
+		let block = api.current_block(&id)
+			.map_err(|err| internal_err(format!("fetch runtime block failed: {:?}", err)))?;
+		let statuses = api.current_transaction_statuses(&id)
+			.map_err(|err| internal_err(format!("fetch runtime statuses failed: {:?}", err)))?;
+		Ok(block.zip(statuses))
+	}
+}
+
+/// Pair the status of each transaction with its call traces, by transaction hash.
+/// Transactions which weren't traced are left out.
+fn match_traces(
+	statuses: Vec<TransactionStatus>,
+	traces: Vec<(H256, Vec<CallTrace>)>,
+) -> Vec<(TransactionStatus, Vec<CallTrace>)> {
+	let mut traces: BTreeMap<H256, Vec<CallTrace>> = traces.into_iter().collect();
+	statuses.into_iter()
+		.filter_map(|status| {
+			let frames = traces.remove(&status.transaction_hash)?;
+			Some((status, frames))
+		})
+		.collect()
+}
+
+fn localize(
+	block: &ethereum::Block,
+	status: &TransactionStatus,
+	frames: Vec<CallTrace>,
+) -> impl Iterator<Item = LocalizedTrace> {
+	let block_hash = block.header.hash();
+	let block_number = block.header.number;
+	let transaction_hash = status.transaction_hash;
+	let transaction_position = status.transaction_index;
+
+	frames.into_iter().map(move |frame| {
+		let (action, result, trace_type) = match frame.call_type {
+			FrameType::Create => (
+				Action::Create(CreateAction { from: frame.from, value: frame.value, gas: frame.gas }),
+				TraceResult::Create(CreateResult { address: frame.to, gas_used: frame.gas_used }),
+				TraceType::Create,
+			),
+			call_type => (
+				Action::Call(CallAction {
+					call_type: match call_type {
+						FrameType::StaticCall => CallType::StaticCall,
+						FrameType::DelegateCall => CallType::DelegateCall,
+						FrameType::CallCode => CallType::CallCode,
+						_ => CallType::Call,
+					},
+					from: frame.from,
+					to: frame.to,
+					value: frame.value,
+					gas: frame.gas,
+				}),
+				TraceResult::Call(CallResult { gas_used: frame.gas_used }),
+				TraceType::Call,
+			),
+		};
+
+		LocalizedTrace {
+			action,
+			result: if frame.success { Some(result) } else { None },
+			error: if frame.success { None } else { Some("Reverted".to_string()) },
+			subtraces: frame.subtraces,
+			trace_address: frame.trace_address,
+			trace_type,
+			block_hash,
+			block_number,
+			transaction_hash,
+			transaction_position,
+		}
+	})
+}
+
+fn frame_addresses(trace: &LocalizedTrace) -> (H160, H160) {
+	match (&trace.action, &trace.result) {
+		(Action::Call(call), _) => (call.from, call.to),
+		(Action::Create(create), Some(TraceResult::Create(result))) => (create.from, result.address),
+		(Action::Create(create), _) => (create.from, H160::default()),
+	}
+}
+
+impl<B, C> TraceApiT for Trace<B, C> where
+	B: BlockT<Hash=H256> + Send + Sync + 'static,
+	C: ProvideRuntimeApi<B> + HeaderBackend<B> + BlockBackend<B> + Send + Sync + 'static,
+	C::Api: EthereumRuntimeRPCApi<B>,
+{
+	fn block_traces(&self, number: BlockNumber) -> Result<Option<Vec<LocalizedTrace>>> {
+		let id = match self.native_block_id(number)? {
+			Some(id) => id,
+			None => return Ok(None),
+		};
+		Ok(self.traced_block(&id)?.map(|(block, transactions)| {
+			transactions.into_iter()
+				.flat_map(|(status, frames)| localize(&block, &status, frames))
+				.collect()
+		}))
+	}
+
+	fn transaction_traces(&self, hash: H256) -> Result<Option<Vec<LocalizedTrace>>> {
+		let metadata = fc_consensus::load_transaction_metadata(self.client.as_ref(), hash)
+			.map_err(|err| internal_err(format!("fetch aux store failed: {:?}", err)))?
+			.unwrap_or_default();
+		for (ethereum_hash, _) in metadata {
+			let id = match self.canonical_block(ethereum_hash)? {
+				Some(id) => id,
+				None => continue,
+			};
+			if let Some((block, transactions)) = self.traced_block(&id)? {
+				let traced = transactions.into_iter()
+					.find(|(status, _)| status.transaction_hash == hash);
+				if let Some((status, frames)) = traced {
+					return Ok(Some(localize(&block, &status, frames).collect()));
+				}
+			}
+		}
+		Ok(None)
+	}
+
+	fn filter(&self, filter: TraceFilter) -> Result<Vec<LocalizedTrace>> {
+		let best_number: u64 = self.client.info().best_number.unique_saturated_into();
+		let to_number = filter.to_block.clone()
+			.and_then(|v| v.to_min_block_num())
+			.unwrap_or(best_number)
+			.min(best_number);
+		let from_number = filter.from_block.clone()
+			.and_then(|v| v.to_min_block_num())
+			.unwrap_or(best_number);
+		if from_number > to_number {
+			return Ok(Vec::new());
+		}
+		if to_number - from_number >= self.max_block_range as u64 {
+			return Err(limit_exceeded_err(format!(
+				"query exceeds max block range {}", self.max_block_range
+			)));
+		}
+
+		let mut skip = filter.after.unwrap_or(0);
+		let count = filter.count.unwrap_or(usize::max_value());
+		if count == 0 {
+			return Ok(Vec::new());
+		}
+		let mut matches = Vec::new();
+		for number in from_number..=to_number {
+			let id = BlockId::Number(number.unique_saturated_into());
+			let (block, transactions) = match self.traced_block(&id)? {
+				Some(data) => data,
+				None => continue,
+			};
+			for (status, frames) in transactions {
+				for trace in localize(&block, &status, frames) {
+					let (from, to) = frame_addresses(&trace);
+					if !filter.matches(&from, &to) {
+						continue;
+					}
+					if skip > 0 {
+						skip -= 1;
+						continue;
+					}
+					matches.push(trace);
+					if matches.len() >= count {
+						return Ok(matches);
+					}
+				}
+			}
+		}
+
+		Ok(matches)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn status(hash: u8) -> TransactionStatus {
+		TransactionStatus { transaction_hash: H256::repeat_byte(hash), ..Default::default() }
+	}
+
+	fn frame(to: u8) -> CallTrace {
+		CallTrace {
+			call_type: FrameType::Call,
+			from: H160::default(),
+			to: H160::repeat_byte(to),
+			value: U256::zero(),
+			gas: U256::zero(),
+			gas_used: U256::zero(),
+			success: true,
+			trace_address: Vec::new(),
+			subtraces: 0,
+		}
+	}
+
+	#[test]
+	fn traces_are_matched_to_statuses_by_transaction_hash() {
+		// The transaction 2 failed to apply and has no status.
+		let statuses = vec![status(1), status(3)];
+		let traces = vec![
+			(H256::repeat_byte(1), vec![frame(1)]),
+			(H256::repeat_byte(2), vec![frame(2)]),
+			(H256::repeat_byte(3), vec![frame(3)]),
+		];
+
+		let matched = match_traces(statuses, traces);
+		assert_eq!(matched.len(), 2);
+		assert_eq!(matched[0].0.transaction_hash, H256::repeat_byte(1));
+		assert_eq!(matched[0].1, vec![frame(1)]);
+		assert_eq!(matched[1].0.transaction_hash, H256::repeat_byte(3));
+		assert_eq!(matched[1].1, vec![frame(3)]);
+	}
+}
@@ -0,0 +1,109 @@
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+// This file is part of Frontier.
+//
+// Copyright (c) 2020 Parity Technologies (UK) Ltd.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+//! txpool rpc, the Ethereum view of the transaction pool.
+
+use std::{marker::PhantomData, sync::Arc};
+use ethereum_types::U256;
+use jsonrpc_core::Result;
+use sp_api::{ProvideRuntimeApi, BlockId};
+use sp_blockchain::HeaderBackend;
+use sp_runtime::traits::Block as BlockT;
+use sc_transaction_graph::{ChainApi, Pool};
+use fp_rpc::EthereumRuntimeRPCApi;
+pub use fc_rpc_core::TxPoolApiServer;
+use fc_rpc_core::TxPoolApi as TxPoolApiT;
+use fc_rpc_core::types::{Summary, Transaction, TransactionMap, TxPoolResult};
+use crate::{internal_err, eth::transaction_build};
+
+pub struct TxPool<B: BlockT, C, A: ChainApi> {
+	client: Arc<C>,
+	graph: Arc<Pool<A>>,
+	_marker: PhantomData<B>,
+}
+
+impl<B: BlockT, C, A: ChainApi> TxPool<B, C, A> {
+	pub fn new(client: Arc<C>, graph: Arc<Pool<A>>) -> Self {
+		Self { client, graph, _marker: PhantomData }
+	}
+}
+
+impl<B, C, A> TxPool<B, C, A> where
+	B: BlockT,
+	C: ProvideRuntimeApi<B> + HeaderBackend<B> + Send + Sync + 'static,
+	C::Api: EthereumRuntimeRPCApi<B>,
+	A: ChainApi<Block = B> + 'static,
+{
+	/// The Ethereum transactions of the ready and future pool transactions,
+	/// grouped by sender and nonce.
+	fn map<T, F>(&self, f: F) -> Result<TxPoolResult<TransactionMap<T>>> where
+		F: Fn(Transaction) -> T,
+	{
+		let pool = self.graph.validated_pool();
+		let ready: Vec<B::Extrinsic> = pool.ready().map(|transaction| transaction.data.clone()).collect();
+		let future: Vec<B::Extrinsic> = pool.futures().into_iter().map(|(_, xt)| xt).collect();
+
+		let group = |xts: Vec<B::Extrinsic>| -> Result<TransactionMap<T>> {
+			let best = BlockId::Hash(self.client.info().best_hash);
+			let transactions = self.client.runtime_api().extrinsic_filter(&best, xts)
+				.map_err(|err| internal_err(format!("fetch runtime extrinsic filter failed: {:?}", err)))?;
+
+			let mut map = TransactionMap::new();
+			for transaction in transactions {
+				let transaction = transaction_build(transaction, None, None);
+				map.entry(transaction.from)
+					.or_insert_with(Default::default)
+					.insert(transaction.nonce.to_string(), f(transaction));
+			}
+			Ok(map)
+		};
+
+		Ok(TxPoolResult {
+			pending: group(ready)?,
+			queued: group(future)?,
+		})
+	}
+}
+
+impl<B, C, A> TxPoolApiT for TxPool<B, C, A> where
+	B: BlockT,
+	C: ProvideRuntimeApi<B> + HeaderBackend<B> + Send + Sync + 'static,
+	C::Api: EthereumRuntimeRPCApi<B>,
+	A: ChainApi<Block = B> + 'static,
+{
+	fn content(&self) -> Result<TxPoolResult<TransactionMap<Transaction>>> {
+		self.map(|transaction| transaction)
+	}
+
+	fn inspect(&self) -> Result<TxPoolResult<TransactionMap<Summary>>> {
+		self.map(|transaction| Summary {
+			to: transaction.to,
+			value: transaction.value,
+			gas: transaction.gas,
+			gas_price: transaction.gas_price,
+		})
+	}
+
+	fn status(&self) -> Result<TxPoolResult<U256>> {
+		let count = |map: TransactionMap<()>| U256::from(map.values().map(|nonces| nonces.len()).sum::<usize>());
+		let result = self.map(|_| ())?;
+		Ok(TxPoolResult {
+			pending: count(result.pending),
+			queued: count(result.queued),
+		})
+	}
+}
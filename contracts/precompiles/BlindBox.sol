@@ -0,0 +1,27 @@
+pragma solidity >=0.5.0;
+
+/// Blind box precompile of the fuxi runtime, backed by pallet-nft-blindbox.
+///
+/// Address: 0x0000000000000000000000000000000000000404
+///
+/// Boxes are bought by the Substrate account mapped from `msg.sender`, which
+/// pays the box price and receives the drawn NFT. Draws use the runtime
+/// randomness, see Randomness.sol for its bias properties.
+interface BlindBox {
+    /// Buy and open blind box `blindBoxId`, returning the card drawn.
+    function openBlindBox(uint64 blindBoxId)
+        external
+        returns (uint256 boxId, uint256 classId, uint256 tokenId);
+
+    /// Owner, price and number of cards left of blind box `blindBoxId`.
+    function blindBox(uint64 blindBoxId)
+        external
+        view
+        returns (bool exists, bytes32 owner, uint256 price, uint256 remaining);
+
+    /// Last card drawn through this precompile by `account`.
+    function lastDraw(address account)
+        external
+        view
+        returns (bool exists, uint256 boxId, uint256 classId, uint256 tokenId);
+}
@@ -0,0 +1,17 @@
+pragma solidity >=0.5.0;
+
+/// Cross-VM precompile of the fuxi runtime calling pallet-contracts (ink!)
+/// contracts.
+///
+/// Address: 0x0000000000000000000000000000000000000405
+///
+/// The ink! contract is called by the Substrate account mapped from
+/// `msg.sender`, which also pays `value`. The gas left to the call is
+/// converted to the contract weight limit and the weight used is charged
+/// back as gas. If the ink! contract reverts, the call fails with the revert
+/// data hex encoded in the error message.
+interface InkContracts {
+    /// Call ink! contract `dest` with SCALE encoded `input` (selector and
+    /// arguments), returning the SCALE encoded output.
+    function call(bytes32 dest, uint256 value, bytes calldata input) external returns (bytes memory);
+}
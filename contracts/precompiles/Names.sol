@@ -0,0 +1,38 @@
+pragma solidity >=0.5.0;
+
+/// Name service precompile of the fuxi runtime, backed by pallet-names.
+///
+/// Address: 0x0000000000000000000000000000000000000400
+///
+/// The precompile has no code, so `extcodesize` checks inserted by the
+/// Solidity compiler fail for high-level calls from older compilers. Use a
+/// low-level `call`/`staticcall` with `abi.encodeWithSelector` in that case.
+///
+/// Registration and update fees are charged by pallet-names from the
+/// Substrate account mapped from `msg.sender`.
+interface Names {
+    /// Raw value of `name`, empty if the name is not registered.
+    function value(bytes calldata name) external view returns (bytes memory);
+
+    /// Value of `name` as an address, zero if it is not exactly 20 bytes.
+    function addr(bytes calldata name) external view returns (address);
+
+    /// Substrate account owning `name`, zero if it is not registered.
+    function owner(bytes calldata name) external view returns (bytes32);
+
+    /// Block at which `name` expires, if it expires at all.
+    function expiration(bytes calldata name) external view returns (bool expires, uint256 blockNumber);
+
+    /// Reverse record of `account`. Empty unless the name still resolves to `account`.
+    function nameOf(address account) external view returns (bytes memory);
+
+    /// Register a new name. Reverts if the name already exists.
+    function register(bytes calldata name, bytes calldata value) external;
+
+    /// Update a name owned by the caller.
+    function update(bytes calldata name, bytes calldata value) external;
+
+    /// Set the caller's reverse record. The name must resolve to the caller;
+    /// an empty name clears the record.
+    function setName(bytes calldata name) external;
+}
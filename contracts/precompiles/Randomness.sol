@@ -0,0 +1,21 @@
+pragma solidity >=0.5.0;
+
+/// Randomness precompile of the fuxi runtime.
+///
+/// Address: 0x0000000000000000000000000000000000000403
+///
+/// Values come from RandomnessCollectiveFlip, which mixes the hashes of the
+/// previous 81 blocks. They are NOT secure randomness:
+/// - they are public as soon as the parent block is known, so commit to an
+///   action first and use randomness from a later block to settle it;
+/// - block authors can bias them by withholding blocks;
+/// - within a block the same subject always returns the same value, so
+///   include a nonce and `msg.sender` in the subject.
+/// Do not use them for draws worth more than a block reward.
+interface Randomness {
+    /// Random value for `subject`, fixed for the current block.
+    function random(bytes calldata subject) external view returns (bytes32);
+
+    /// Random value for the empty subject.
+    function randomSeed() external view returns (bytes32);
+}
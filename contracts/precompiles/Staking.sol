@@ -0,0 +1,36 @@
+pragma solidity >=0.5.0;
+
+/// Staking and mining rewards precompile of the fuxi runtime, backed by
+/// pallet-staking and pallet-rewards.
+///
+/// Address: 0x0000000000000000000000000000000000000402
+///
+/// All operations act on the Substrate account mapped from `msg.sender`.
+/// Amounts are in the smallest UART unit and must fit into `uint128`.
+/// The precompile has no code, so use low-level calls from compilers that
+/// check `extcodesize` before high-level calls.
+interface Staking {
+    /// Stake `amount` into pool `poolId`. Pending rewards are claimed first.
+    function stake(uint32 poolId, uint256 amount) external;
+
+    /// Withdraw `amount` from pool `poolId`.
+    function unstake(uint32 poolId, uint256 amount) external;
+
+    /// Claim the pending staking rewards of pool `poolId`.
+    function claim(uint32 poolId) external;
+
+    /// Amount staked by `staker` in pool `poolId`.
+    function stakedAmount(uint32 poolId, address staker) external view returns (uint256);
+
+    /// Staking rewards accrued by `staker` in pool `poolId` and not claimed yet.
+    function pendingRewards(uint32 poolId, address staker) external view returns (uint256);
+
+    /// Substrate account holding the funds of pool `poolId`.
+    function poolAccount(uint32 poolId) external view returns (bytes32);
+
+    /// Claim the mining rewards of the caller from pallet-rewards.
+    function claimMiningRewards() external;
+
+    /// Mining rewards of `miner` that can be claimed.
+    function miningRewards(address miner) external view returns (uint256);
+}
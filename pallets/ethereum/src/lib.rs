@@ -24,7 +24,7 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 
 use frame_support::{
-	decl_module, decl_storage, decl_error, decl_event,
+	decl_module, decl_storage, decl_error, decl_event, ensure, storage, StorageValue,
 	traits::Get, traits::FindAuthor, weights::Weight,
 	dispatch::DispatchResultWithPostInfo,
 };
@@ -33,19 +33,20 @@ use frame_system::ensure_none;
 use ethereum_types::{H160, H64, H256, U256, Bloom, BloomInput};
 use sp_runtime::{
 	transaction_validity::{
-		TransactionValidity, TransactionSource, InvalidTransaction, ValidTransactionBuilder,
+		TransactionValidity, TransactionValidityError, TransactionSource, InvalidTransaction,
+		ValidTransactionBuilder, TransactionLongevity,
 	},
 	generic::DigestItem, traits::UniqueSaturatedInto, DispatchError,
 };
 use evm::ExitReason;
 use fp_evm::CallOrCreateInfo;
-use pallet_evm::{Runner, GasWeightMapping};
+use pallet_evm::{Runner, GasWeightMapping, FeeCalculator};
 use sha3::{Digest, Keccak256};
-use codec::{Encode, Decode};
+use codec::{Encode, Decode, DecodeAll};
 use fp_consensus::{FRONTIER_ENGINE_ID, ConsensusLog};
 
-pub use fp_rpc::TransactionStatus;
-pub use ethereum::{Transaction, Log, Block, Receipt, TransactionAction, TransactionMessage};
+pub use fp_rpc::{TransactionStatus, TransactionReceipt};
+pub use ethereum::{Transaction, Log, Block, TransactionAction, TransactionMessage};
 
 #[cfg(all(feature = "std", test))]
 mod tests;
@@ -71,6 +72,28 @@ impl Get<H256> for IntermediateStateRoot {
 	}
 }
 
+/// Keccak-256 hasher for the Ethereum Merkle-Patricia tries.
+pub struct Keccak256Hasher;
+
+impl hash_db::Hasher for Keccak256Hasher {
+	type Out = H256;
+	type StdHasher = hash256_std_hasher::Hash256StdHasher;
+	const LENGTH: usize = 32;
+
+	fn hash(x: &[u8]) -> H256 {
+		H256::from_slice(Keccak256::digest(x).as_slice())
+	}
+}
+
+/// Root of the trie mapping `rlp(index)` to each item, as used for the
+/// transactions and receipts roots of an Ethereum block.
+pub fn ordered_trie_root<I>(items: I) -> H256 where
+	I: IntoIterator,
+	I::Item: AsRef<[u8]>,
+{
+	triehash::ordered_trie_root::<Keccak256Hasher, I>(items)
+}
+
 /// Configuration trait for Ethereum pallet.
 pub trait Config: frame_system::Config<Hash=H256> + pallet_balances::Config + pallet_timestamp::Config + pallet_evm::Config {
 	/// The overarching event type.
@@ -81,17 +104,21 @@ pub trait Config: frame_system::Config<Hash=H256> + pallet_balances::Config + pa
 	type StateRoot: Get<H256>;
 	/// The block gas limit. Can be a simple constant, or an adjustment algorithm in another pallet.
 	type BlockGasLimit: Get<U256>;
+	/// Number of blocks a transaction stays valid in the pool.
+	type TransactionLongevity: Get<TransactionLongevity>;
 }
 
 decl_storage! {
 	trait Store for Module<T: Config> as Ethereum {
 		/// Current building block's transactions and receipts.
-		Pending: Vec<(ethereum::Transaction, TransactionStatus, ethereum::Receipt)>;
+		Pending: Vec<(ethereum::Transaction, TransactionStatus, TransactionReceipt)>;
+		/// Gas used by the transactions of the current building block.
+		PendingGasUsed: U256;
 
 		/// The current Ethereum block.
 		CurrentBlock: Option<ethereum::Block>;
 		/// The current Ethereum receipts.
-		CurrentReceipts: Option<Vec<ethereum::Receipt>>;
+		CurrentReceipts: Option<Vec<TransactionReceipt>>;
 		/// The current transaction statuses.
 		CurrentTransactionStatuses: Option<Vec<TransactionStatus>>;
 	}
@@ -116,6 +143,8 @@ decl_error! {
 	pub enum Error for Module<T: Config> {
 		/// Signature is invalid.
 		InvalidSignature,
+		/// The transaction gas limit exceeds the gas left in the block.
+		BlockGasLimitExceeded,
 	}
 }
 
@@ -132,10 +161,9 @@ decl_module! {
 
 			let source = Self::recover_signer(&transaction)
 				.ok_or_else(|| Error::<T>::InvalidSignature)?;
+			ensure!(Self::fits_in_block(transaction.gas_limit), Error::<T>::BlockGasLimitExceeded);
 
-			let transaction_hash = H256::from_slice(
-				Keccak256::digest(&rlp::encode(&transaction)).as_slice()
-			);
+			let transaction_hash = Self::transaction_hash(&transaction);
 			let transaction_index = Pending::get().len() as u32;
 
 			let (to, contract_address, info) = Self::execute(
@@ -149,55 +177,38 @@ decl_module! {
 				None,
 			)?;
 
-			let (reason, status, used_gas) = match info {
-				CallOrCreateInfo::Call(info) => {
-					(info.exit_reason, TransactionStatus {
-						transaction_hash,
-						transaction_index,
-						from: source,
-						to,
-						contract_address: None,
-						logs: info.logs.clone(),
-						logs_bloom: {
-							let mut bloom: Bloom = Bloom::default();
-							Self::logs_bloom(
-								info.logs,
-								&mut bloom
-							);
-							bloom
-						},
-					}, info.used_gas)
-				},
-				CallOrCreateInfo::Create(info) => {
-					(info.exit_reason, TransactionStatus {
-						transaction_hash,
-						transaction_index,
-						from: source,
-						to,
-						contract_address: Some(info.value),
-						logs: info.logs.clone(),
-						logs_bloom: {
-							let mut bloom: Bloom = Bloom::default();
-							Self::logs_bloom(
-								info.logs,
-								&mut bloom
-							);
-							bloom
-						},
-					}, info.used_gas)
-				},
+			let (reason, created, logs, used_gas) = match info {
+				CallOrCreateInfo::Call(info) =>
+					(info.exit_reason, None, info.logs, info.used_gas),
+				CallOrCreateInfo::Create(info) =>
+					(info.exit_reason, Some(info.value), info.logs, info.used_gas),
 			};
-
-			let receipt = ethereum::Receipt {
-				state_root: match reason {
-					ExitReason::Succeed(_) => H256::from_low_u64_be(1),
-					ExitReason::Error(_) => H256::from_low_u64_le(0),
-					ExitReason::Revert(_) => H256::from_low_u64_le(0),
-					ExitReason::Fatal(_) => H256::from_low_u64_le(0),
-				},
+			let succeeded = matches!(reason, ExitReason::Succeed(_));
+			// The state changes of a failed transaction are reverted, so are its logs.
+			let logs = if succeeded { logs } else { Vec::new() };
+			let mut logs_bloom = Bloom::default();
+			Self::logs_bloom(logs.clone(), &mut logs_bloom);
+
+			let status = TransactionStatus {
+				transaction_hash,
+				transaction_index,
+				from: source,
+				to,
+				contract_address: created,
+				logs: logs.clone(),
+				logs_bloom,
+			};
+			let cumulative_gas_used = PendingGasUsed::mutate(|gas_used| {
+				*gas_used = gas_used.saturating_add(used_gas);
+				*gas_used
+			});
+			let receipt = TransactionReceipt {
+				status_code: if succeeded { 1 } else { 0 },
+				cumulative_gas_used,
 				used_gas,
-				logs_bloom: status.clone().logs_bloom,
-				logs: status.clone().logs,
+				effective_gas_price: transaction.gas_price,
+				logs_bloom,
+				logs,
 			};
 
 			Pending::append((transaction, status, receipt));
@@ -212,17 +223,47 @@ decl_module! {
 
 		fn on_initialize(n: T::BlockNumber) -> Weight {
 			Pending::kill();
+			PendingGasUsed::kill();
 			0
 		}
+
+		fn on_runtime_upgrade() -> Weight {
+			<Module<T>>::migrate_receipts()
+		}
 	}
 }
 
+/// Maximum size of an RLP-encoded transaction accepted in the pool.
+pub const MAX_TRANSACTION_SIZE: usize = 128 * 1024;
+
+/// Codes of the `InvalidTransaction::Custom` errors of `validate_unsigned`.
 #[repr(u8)]
-enum TransactionValidationError {
-	#[allow(dead_code)]
+#[derive(Eq, PartialEq, Clone, Copy, sp_runtime::RuntimeDebug)]
+pub enum TransactionValidationError {
 	UnknownError,
+	/// The chain id of the signature is not the one of this chain.
 	InvalidChainId,
+	/// The signer can not be recovered from the signature.
 	InvalidSignature,
+	/// The gas limit exceeds the block gas limit.
+	GasLimitTooHigh,
+	/// The gas limit is below the intrinsic gas of the transaction.
+	GasLimitTooLow,
+	/// The gas price is below the minimum gas price.
+	GasPriceTooLow,
+	/// The encoded transaction exceeds `MAX_TRANSACTION_SIZE`.
+	TransactionTooLarge,
+}
+
+impl TransactionValidationError {
+	/// The error with the given custom code, if any.
+	pub fn from_code(code: u8) -> Option<Self> {
+		use TransactionValidationError::*;
+		[
+			UnknownError, InvalidChainId, InvalidSignature, GasLimitTooHigh, GasLimitTooLow,
+			GasPriceTooLow, TransactionTooLarge,
+		].iter().copied().find(|error| *error as u8 == code)
+	}
 }
 
 impl<T: Config> frame_support::unsigned::ValidateUnsigned for Module<T> {
@@ -236,6 +277,22 @@ impl<T: Config> frame_support::unsigned::ValidateUnsigned for Module<T> {
 				}
 			}
 
+			if rlp::encode(transaction).len() > MAX_TRANSACTION_SIZE {
+				return InvalidTransaction::Custom(TransactionValidationError::TransactionTooLarge as u8).into();
+			}
+
+			if transaction.gas_limit > T::BlockGasLimit::get() {
+				return InvalidTransaction::Custom(TransactionValidationError::GasLimitTooHigh as u8).into();
+			}
+
+			if transaction.gas_limit < U256::from(Self::intrinsic_gas(transaction)) {
+				return InvalidTransaction::Custom(TransactionValidationError::GasLimitTooLow as u8).into();
+			}
+
+			if transaction.gas_price < T::FeeCalculator::min_gas_price() {
+				return InvalidTransaction::Custom(TransactionValidationError::GasPriceTooLow as u8).into();
+			}
+
 			let origin = Self::recover_signer(&transaction)
 				.ok_or_else(|| InvalidTransaction::Custom(TransactionValidationError::InvalidSignature as u8))?;
 
@@ -245,14 +302,18 @@ impl<T: Config> frame_support::unsigned::ValidateUnsigned for Module<T> {
 				return InvalidTransaction::Stale.into();
 			}
 
-			let fee = transaction.gas_price.saturating_mul(transaction.gas_limit);
+			let cost = transaction.gas_price.checked_mul(transaction.gas_limit)
+				.and_then(|fee| fee.checked_add(transaction.value))
+				.ok_or(InvalidTransaction::Payment)?;
 
-			if account_data.balance < fee {
+			if account_data.balance < cost {
 				return InvalidTransaction::Payment.into();
 			}
 
 			let mut builder = ValidTransactionBuilder::default()
-				.and_provides((origin, transaction.nonce));
+				.and_provides((origin, transaction.nonce))
+				.priority(transaction.gas_price.unique_saturated_into())
+				.longevity(T::TransactionLongevity::get());
 
 			if transaction.nonce > account_data.nonce {
 				if let Some(prev_nonce) = transaction.nonce.checked_sub(1.into()) {
@@ -265,9 +326,25 @@ impl<T: Config> frame_support::unsigned::ValidateUnsigned for Module<T> {
 			Err(InvalidTransaction::Call.into())
 		}
 	}
+
+	fn pre_dispatch(call: &Self::Call) -> Result<(), TransactionValidityError> {
+		Self::validate_unsigned(TransactionSource::InBlock, call)?;
+		if let Call::transact(transaction) = call {
+			// Left in the pool to be retried in a later block.
+			if !Self::fits_in_block(transaction.gas_limit) {
+				return Err(InvalidTransaction::ExhaustsResources.into());
+			}
+		}
+		Ok(())
+	}
 }
 
 impl<T: Config> Module<T> {
+	/// Hash of an Ethereum transaction.
+	pub fn transaction_hash(transaction: &ethereum::Transaction) -> H256 {
+		H256::from_slice(Keccak256::digest(&rlp::encode(transaction)).as_slice())
+	}
+
 	fn recover_signer(transaction: &ethereum::Transaction) -> Option<H160> {
 		let mut sig = [0u8; 65];
 		let mut msg = [0u8; 32];
@@ -280,6 +357,48 @@ impl<T: Config> Module<T> {
 		Some(H160::from(H256::from_slice(Keccak256::digest(&pubkey).as_slice())))
 	}
 
+	/// Gas charged before executing the transaction: the base cost of a call
+	/// or create, and the cost of its input.
+	fn intrinsic_gas(transaction: &ethereum::Transaction) -> u64 {
+		let config = T::config();
+		let base = match transaction.action {
+			TransactionAction::Call(_) => config.gas_transaction_call,
+			TransactionAction::Create => config.gas_transaction_create,
+		};
+		transaction.input.iter().fold(base, |gas, byte| gas.saturating_add(
+			if *byte == 0 { config.gas_transaction_zero_data } else { config.gas_transaction_non_zero_data }
+		))
+	}
+
+	/// Whether a transaction with the given gas limit fits in the gas left in
+	/// the current building block.
+	fn fits_in_block(gas_limit: U256) -> bool {
+		PendingGasUsed::get()
+			.checked_add(gas_limit)
+			.map_or(false, |gas| gas <= T::BlockGasLimit::get())
+	}
+
+	/// Translate `CurrentReceipts` stored as `ethereum::Receipt`, before receipts
+	/// held their status code and cumulative gas. `Pending` is emptied on
+	/// initialize, so it never holds receipts of the previous format.
+	fn migrate_receipts() -> Weight {
+		let key = CurrentReceipts::hashed_key();
+		let data = match storage::unhashed::get_raw(&key) {
+			Some(data) => data,
+			None => return T::DbWeight::get().reads(1),
+		};
+		if Vec::<TransactionReceipt>::decode_all(&data).is_ok() {
+			return T::DbWeight::get().reads(1)
+		}
+
+		match (Vec::<ethereum::Receipt>::decode_all(&data), CurrentBlock::get()) {
+			(Ok(receipts), Some(block)) =>
+				CurrentReceipts::put(TransactionReceipt::from_legacy(&block, receipts)),
+			_ => CurrentReceipts::kill(),
+		}
+		T::DbWeight::get().reads_writes(2, 1)
+	}
+
 	fn store_block() {
 		let mut transactions = Vec::new();
 		let mut statuses = Vec::new();
@@ -299,11 +418,9 @@ impl<T: Config> Module<T> {
 		let partial_header = ethereum::PartialHeader {
 			parent_hash: Self::current_block_hash().unwrap_or_default(),
 			beneficiary: <Module<T>>::find_author(),
-			// TODO: figure out if there's better way to get a sort-of-valid state root.
+			// Set below, once the block is built.
 			state_root: H256::default(),
-			receipts_root: H256::from_slice(
-				Keccak256::digest(&rlp::encode_list(&receipts)[..]).as_slice(),
-			), // TODO: check receipts hash.
+			receipts_root: ordered_trie_root(receipts.iter().map(|receipt| rlp::encode(receipt))),
 			logs_bloom,
 			difficulty: U256::zero(),
 			number: U256::from(
@@ -312,15 +429,19 @@ impl<T: Config> Module<T> {
 				)
 			),
 			gas_limit: T::BlockGasLimit::get(),
-			gas_used: receipts.clone().into_iter().fold(U256::zero(), |acc, r| acc + r.used_gas),
+			gas_used: PendingGasUsed::get(),
 			timestamp: UniqueSaturatedInto::<u64>::unique_saturated_into(
 				pallet_timestamp::Module::<T>::get()
 			),
 			extra_data: Vec::new(),
+			// Blocks are not sealed by proof of work.
 			mix_hash: H256::default(),
 			nonce: H64::default(),
 		};
+		// `Block::new` computes the transactions root with `ordered_trie_root`.
 		let mut block = ethereum::Block::new(partial_header, transactions.clone(), ommers);
+		// There is no Ethereum world state trie: the root is the intermediate
+		// Substrate state root, so it can't be used to check account proofs.
 		block.header.state_root = T::StateRoot::get();
 
 		let mut transaction_hashes = Vec::new();
@@ -379,7 +500,7 @@ impl<T: Config> Module<T> {
 	}
 
 	/// Get receipts by number.
-	pub fn current_receipts() -> Option<Vec<ethereum::Receipt>> {
+	pub fn current_receipts() -> Option<Vec<TransactionReceipt>> {
 		CurrentReceipts::get()
 	}
 
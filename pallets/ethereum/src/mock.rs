@@ -157,8 +157,26 @@ impl pallet_evm::Config for Test {
 	type ChainId = ChainId;
 }
 
+thread_local! {
+	static BLOCK_GAS_LIMIT: std::cell::RefCell<U256> = std::cell::RefCell::new(U256::MAX);
+}
+
+pub struct BlockGasLimit;
+
+impl BlockGasLimit {
+	pub fn set(limit: U256) {
+		BLOCK_GAS_LIMIT.with(|v| *v.borrow_mut() = limit);
+	}
+}
+
+impl Get<U256> for BlockGasLimit {
+	fn get() -> U256 {
+		BLOCK_GAS_LIMIT.with(|v| *v.borrow())
+	}
+}
+
 parameter_types! {
-	pub const BlockGasLimit: U256 = U256::MAX;
+	pub const EthereumTransactionLongevity: u64 = 64;
 }
 
 impl Config for Test {
@@ -166,6 +184,7 @@ impl Config for Test {
 	type FindAuthor = EthereumFindAuthor;
 	type StateRoot = IntermediateStateRoot;
 	type BlockGasLimit = BlockGasLimit;
+	type TransactionLongevity = EthereumTransactionLongevity;
 }
 
 pub type System = frame_system::Module<Test>;
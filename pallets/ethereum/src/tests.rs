@@ -85,6 +85,56 @@ fn transaction_without_enough_gas_should_not_work() {
 	});
 }
 
+#[test]
+fn transaction_value_should_count_towards_balance() {
+	let (pairs, mut ext) = new_test_ext(1);
+	let alice = &pairs[0];
+
+	ext.execute_with(|| {
+		let balance = Evm::account_basic(&alice.address).balance;
+		let mut transaction = default_erc20_creation_unsigned_transaction();
+		transaction.value = balance - transaction.gas_limit + 1;
+
+		assert_err!(
+			Ethereum::validate_unsigned(TransactionSource::External, &Call::transact(transaction.sign(&alice.private_key))),
+			InvalidTransaction::Payment
+		);
+	});
+}
+
+#[test]
+fn transaction_below_intrinsic_gas_or_min_gas_price_should_not_work() {
+	let (pairs, mut ext) = new_test_ext(1);
+	let alice = &pairs[0];
+
+	ext.execute_with(|| {
+		let mut transaction = default_erc20_creation_unsigned_transaction();
+		transaction.gas_limit = U256::from(53_000);
+		assert_err!(
+			Ethereum::validate_unsigned(TransactionSource::External, &Call::transact(transaction.sign(&alice.private_key))),
+			InvalidTransaction::Custom(TransactionValidationError::GasLimitTooLow as u8)
+		);
+
+		let mut transaction = default_erc20_creation_unsigned_transaction();
+		transaction.gas_price = U256::zero();
+		assert_err!(
+			Ethereum::validate_unsigned(TransactionSource::External, &Call::transact(transaction.sign(&alice.private_key))),
+			InvalidTransaction::Custom(TransactionValidationError::GasPriceTooLow as u8)
+		);
+	});
+}
+
+#[test]
+fn transaction_validation_error_codes_should_roundtrip() {
+	for code in 0..=u8::max_value() {
+		if let Some(error) = TransactionValidationError::from_code(code) {
+			assert_eq!(error as u8, code);
+		}
+	}
+	assert_eq!(TransactionValidationError::from_code(6), Some(TransactionValidationError::TransactionTooLarge));
+	assert_eq!(TransactionValidationError::from_code(7), None);
+}
+
 #[test]
 fn transaction_with_invalid_nonce_should_not_work() {
 	let (pairs, mut ext) = new_test_ext(1);
@@ -101,6 +151,8 @@ fn transaction_with_invalid_nonce_should_not_work() {
 			Ethereum::validate_unsigned(TransactionSource::External, &Call::transact(signed)),
 			ValidTransactionBuilder::default()
 				.and_provides((alice.address, U256::from(1)))
+				.priority(1)
+				.longevity(EthereumTransactionLongevity::get())
 				.and_requires((alice.address, U256::from(0)))
 				.build()
 		);
@@ -314,3 +366,119 @@ fn call_should_handle_errors() {
 		).ok().unwrap();
 	});
 }
+
+#[test]
+fn empty_trie_root_should_be_keccak_null_rlp() {
+	assert_eq!(
+		ordered_trie_root(Vec::<Vec<u8>>::new()),
+		H256::from_str("56e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421").unwrap(),
+	);
+}
+
+#[test]
+fn block_should_have_transactions_and_receipts_trie_roots() {
+	let (pairs, mut ext) = new_test_ext(1);
+	let alice = &pairs[0];
+
+	ext.execute_with(|| {
+		let t = default_erc20_creation_transaction(alice);
+		assert_ok!(Ethereum::transact(Origin::none(), t.clone()));
+		<Ethereum as frame_support::traits::OnFinalize<_>>::on_finalize(1);
+
+		let block = Ethereum::current_block().unwrap();
+		let receipts = Ethereum::current_receipts().unwrap();
+		assert_eq!(block.header.transactions_root, ordered_trie_root(vec![rlp::encode(&t)]));
+		assert_eq!(
+			block.header.receipts_root,
+			ordered_trie_root(receipts.iter().map(|receipt| rlp::encode(receipt))),
+		);
+		assert_ne!(
+			block.header.receipts_root,
+			H256::from_slice(Keccak256::digest(&rlp::encode_list(&receipts)[..]).as_slice()),
+		);
+	});
+}
+
+#[test]
+fn receipts_should_have_status_and_cumulative_gas() {
+	let (pairs, mut ext) = new_test_ext(1);
+	let alice = &pairs[0];
+
+	ext.execute_with(|| {
+		let mut unsigned = default_erc20_creation_unsigned_transaction();
+		unsigned.nonce = U256::from(1);
+		assert_ok!(Ethereum::transact(Origin::none(), default_erc20_creation_transaction(alice)));
+		assert_ok!(Ethereum::transact(Origin::none(), unsigned.sign(&alice.private_key)));
+		<Ethereum as frame_support::traits::OnFinalize<_>>::on_finalize(1);
+
+		let receipts = Ethereum::current_receipts().unwrap();
+		assert_eq!(receipts[0].status_code, 1);
+		assert_eq!(receipts[0].cumulative_gas_used, receipts[0].used_gas);
+		assert_eq!(receipts[1].cumulative_gas_used, receipts[0].used_gas + receipts[1].used_gas);
+		assert_eq!(receipts[1].effective_gas_price, U256::from(1));
+	});
+}
+
+#[test]
+fn runtime_upgrade_should_translate_legacy_receipts() {
+	let (pairs, mut ext) = new_test_ext(1);
+	let alice = &pairs[0];
+
+	ext.execute_with(|| {
+		assert_ok!(Ethereum::transact(Origin::none(), default_erc20_creation_transaction(alice)));
+		<Ethereum as frame_support::traits::OnFinalize<_>>::on_finalize(1);
+		let receipts = Ethereum::current_receipts().unwrap();
+
+		let legacy: Vec<ethereum::Receipt> = receipts.iter().map(|receipt| ethereum::Receipt {
+			state_root: H256::from_low_u64_be(receipt.status_code as u64),
+			used_gas: receipt.used_gas,
+			logs_bloom: receipt.logs_bloom,
+			logs: receipt.logs.clone(),
+		}).collect();
+		frame_support::storage::unhashed::put_raw(&CurrentReceipts::hashed_key(), &legacy.encode());
+
+		<Ethereum as frame_support::traits::OnRuntimeUpgrade>::on_runtime_upgrade();
+		assert_eq!(Ethereum::current_receipts(), Some(receipts.clone()));
+
+		// Receipts already in the current format are left as they are.
+		<Ethereum as frame_support::traits::OnRuntimeUpgrade>::on_runtime_upgrade();
+		assert_eq!(Ethereum::current_receipts(), Some(receipts));
+	});
+}
+
+#[test]
+fn transaction_with_gas_limit_above_block_limit_should_not_work() {
+	let (pairs, mut ext) = new_test_ext(1);
+	let alice = &pairs[0];
+
+	ext.execute_with(|| {
+		BlockGasLimit::set(U256::from(0xfffff));
+		let transaction = default_erc20_creation_transaction(alice);
+
+		assert_err!(
+			Ethereum::validate_unsigned(TransactionSource::External, &Call::transact(transaction)),
+			InvalidTransaction::Custom(TransactionValidationError::GasLimitTooHigh as u8)
+		);
+	});
+}
+
+#[test]
+fn transaction_exceeding_block_gas_left_should_be_deferred() {
+	let (pairs, mut ext) = new_test_ext(1);
+	let alice = &pairs[0];
+
+	ext.execute_with(|| {
+		BlockGasLimit::set(U256::from(0x180000));
+		let mut unsigned = default_erc20_creation_unsigned_transaction();
+		unsigned.nonce = U256::from(1);
+		let second = Call::transact(unsigned.sign(&alice.private_key));
+
+		assert_ok!(Ethereum::transact(Origin::none(), default_erc20_creation_transaction(alice)));
+		assert!(Ethereum::validate_unsigned(TransactionSource::External, &second).is_ok());
+		assert_err!(Ethereum::pre_dispatch(&second), InvalidTransaction::ExhaustsResources);
+
+		// The next block starts with no gas used.
+		<Ethereum as frame_support::traits::OnInitialize<_>>::on_initialize(2);
+		assert_ok!(Ethereum::pre_dispatch(&second));
+	});
+}
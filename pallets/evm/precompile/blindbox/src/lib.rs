@@ -0,0 +1,263 @@
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Blind box precompile, exposing `pallet_nft_blindbox` to Solidity.
+//!
+//! Opening a box dispatches `buy_blind_box` signed by the account mapped from
+//! the EVM caller. The card drawn is taken from the `BlindBoxDraw` event of
+//! the dispatch, returned to the caller and kept as the caller's last draw so
+//! it can also be queried later. Finding the event decodes the events of the
+//! block, each charged as a database read. Draws use the runtime randomness,
+//! see the randomness precompile for its bias properties. The Solidity
+//! interface is `contracts/precompiles/BlindBox.sol`.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use sp_std::{convert::TryInto, marker::PhantomData, vec::Vec};
+use sp_core::{H160, H256};
+use sp_io::hashing::{blake2_128, twox_128};
+use sp_runtime::SaturatedConversion;
+use fp_evm::Precompile;
+use evm::{ExitSucceed, ExitError, Context};
+use frame_support::{
+	dispatch::{Dispatchable, GetDispatchInfo, PostDispatchInfo},
+	storage::unhashed,
+};
+use pallet_evm::AddressMapping;
+use codec::{Decode, Encode};
+use pallet_evm_precompile_utils::{
+	EvmDataReader, EvmDataWriter, read_selector, ensure_gas, db_cost, dispatch,
+};
+
+/// `openBlindBox(uint64)`
+const SELECTOR_OPEN_BLIND_BOX: u32 = 0xe6fbbd6e;
+/// `blindBox(uint64)`
+const SELECTOR_BLIND_BOX: u32 = 0xb9f03482;
+/// `lastDraw(address)`
+const SELECTOR_LAST_DRAW: u32 = 0x8b438a35;
+
+/// A card drawn from a blind box.
+#[derive(Encode, Decode, Clone, Copy, Default, PartialEq, Debug)]
+struct Draw {
+	blind_box_id: u64,
+	class_id: u64,
+	token_id: u64,
+}
+
+/// Storage key of the last draw of an EVM address.
+fn last_draw_key(address: &H160) -> Vec<u8> {
+	let mut key = [twox_128(b"BlindBoxPrecompile"), twox_128(b"LastDraw")].concat();
+	key.extend_from_slice(&blake2_128(address.as_bytes()));
+	key.extend_from_slice(address.as_bytes());
+	key
+}
+
+fn write_draw(writer: EvmDataWriter, draw: Draw) -> EvmDataWriter {
+	writer
+		.write_u256(draw.blind_box_id)
+		.write_u256(draw.class_id)
+		.write_u256(draw.token_id)
+}
+
+pub struct BlindBox<T> {
+	_marker: PhantomData<T>,
+}
+
+impl<T> BlindBox<T> where
+	T: pallet_evm::Config + pallet_nft_blindbox::Config,
+	<T as frame_system::Config>::Event: TryInto<pallet_nft_blindbox::Event<T>>,
+{
+	/// The draw made by `buyer` in the events deposited after the first `skip`
+	/// events of the current block, if any.
+	fn draw_event(buyer: &T::AccountId, skip: u32) -> Option<Draw> {
+		frame_system::Module::<T>::events().into_iter().skip(skip as usize).rev()
+			.filter_map(|record| record.event.try_into().ok())
+			.find_map(|event| match event {
+				pallet_nft_blindbox::Event::<T>::BlindBoxDraw(blind_box_id, class_id, token_id, who)
+					if &who == buyer => Some(Draw { blind_box_id, class_id, token_id }),
+				_ => None,
+			})
+	}
+}
+
+impl<T> Precompile for BlindBox<T> where
+	T: pallet_evm::Config + pallet_nft_blindbox::Config,
+	T::AccountId: Into<[u8; 32]>,
+	<T as frame_system::Config>::Event: TryInto<pallet_nft_blindbox::Event<T>>,
+	T::Call: Dispatchable<PostInfo=PostDispatchInfo> + GetDispatchInfo + From<pallet_nft_blindbox::Call<T>>,
+	<T::Call as Dispatchable>::Origin: From<Option<T::AccountId>>,
+{
+	fn execute(
+		input: &[u8],
+		target_gas: Option<u64>,
+		context: &Context,
+	) -> core::result::Result<(ExitSucceed, Vec<u8>, u64), ExitError> {
+		let selector = read_selector(input)?;
+		let mut reader = EvmDataReader::new(input);
+
+		match selector {
+			SELECTOR_OPEN_BLIND_BOX => {
+				// Reading the event count, the block events and storing the draw.
+				let record_cost = ensure_gas(target_gas, db_cost::<T>(2, 1))?;
+				let blind_box_id = reader.read_u64()?;
+				let events_before = frame_system::Module::<T>::event_count();
+
+				let dispatch_cost = dispatch::<T>(
+					context.caller,
+					pallet_nft_blindbox::Call::<T>::buy_blind_box(blind_box_id).into(),
+					target_gas.map(|gas| gas.saturating_sub(record_cost)),
+				)?;
+
+				// Every event of the block is decoded to find the draw, each
+				// charged as a database read. Running out of gas here fails the
+				// call, whose frame reverts the dispatch.
+				let events = frame_system::Module::<T>::event_count();
+				let cost = ensure_gas(
+					target_gas,
+					record_cost
+						.saturating_add(dispatch_cost)
+						.saturating_add(db_cost::<T>(events.into(), 0)),
+				)?;
+
+				let buyer = T::AddressMapping::into_account_id(context.caller);
+				let draw = Self::draw_event(&buyer, events_before)
+					.ok_or(ExitError::Other("blind box draw not found".into()))?;
+				unhashed::put(&last_draw_key(&context.caller), &draw);
+
+				let output = write_draw(EvmDataWriter::new(), draw).build();
+				Ok((ExitSucceed::Returned, output, cost))
+			},
+			SELECTOR_BLIND_BOX => {
+				let cost = ensure_gas(target_gas, db_cost::<T>(1, 0))?;
+				let blind_box_id = reader.read_u64()?;
+				let item = pallet_nft_blindbox::Module::<T>::blind_box_list(blind_box_id);
+
+				let output = match item {
+					Some(item) => EvmDataWriter::new()
+						.write_bool(true)
+						.write_h256(H256::from(item.owner.into()))
+						.write_u256(item.price.saturated_into::<u128>())
+						.write_u256(item.remainder_count),
+					None => EvmDataWriter::new()
+						.write_bool(false)
+						.write_h256(H256::zero())
+						.write_u256(0u8)
+						.write_u256(0u8),
+				}.build();
+				Ok((ExitSucceed::Returned, output, cost))
+			},
+			SELECTOR_LAST_DRAW => {
+				let cost = ensure_gas(target_gas, db_cost::<T>(1, 0))?;
+				let address = reader.read_address()?;
+				let draw: Option<Draw> = unhashed::get(&last_draw_key(&address));
+
+				let output = write_draw(
+					EvmDataWriter::new().write_bool(draw.is_some()),
+					draw.unwrap_or_default(),
+				).build();
+				Ok((ExitSucceed::Returned, output, cost))
+			},
+			_ => Err(ExitError::Other("unknown selector".into())),
+		}
+	}
+}
+
+#[cfg(test)]
+mod mock;
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::mock::*;
+
+	fn open_blind_box(caller: H160, blind_box_id: u64, target_gas: Option<u64>)
+		-> Result<(ExitSucceed, Vec<u8>, u64), ExitError>
+	{
+		let input = call_data(SELECTOR_OPEN_BLIND_BOX, EvmDataWriter::new().write_u256(blind_box_id));
+		BlindBox::<Test>::execute(&input, target_gas, &context(caller))
+	}
+
+	fn last_draw_output(address: H160) -> Vec<u8> {
+		let input = call_data(SELECTOR_LAST_DRAW, EvmDataWriter::new().write_address(address));
+		BlindBox::<Test>::execute(&input, None, &context(BOB)).unwrap().1
+	}
+
+	#[test]
+	fn test_draw_output_layout() {
+		let draw = Draw { blind_box_id: 1, class_id: 2, token_id: 3 };
+		let output = write_draw(EvmDataWriter::new().write_bool(true), draw).build();
+
+		let mut input = SELECTOR_LAST_DRAW.to_be_bytes().to_vec();
+		input.extend_from_slice(&output);
+		let mut reader = EvmDataReader::new(&input);
+		assert!(reader.read_bool().unwrap());
+		assert_eq!(reader.read_u256().unwrap(), 1.into());
+		assert_eq!(reader.read_u256().unwrap(), 2.into());
+		assert_eq!(reader.read_u256().unwrap(), 3.into());
+	}
+
+	#[test]
+	fn test_last_draw_key_is_per_address() {
+		assert_ne!(last_draw_key(&H160::repeat_byte(1)), last_draw_key(&H160::repeat_byte(2)));
+	}
+
+	#[test]
+	fn test_open_blind_box_returns_and_records_the_draw_of_the_caller() {
+		new_test_ext().execute_with(|| {
+			// An earlier draw of the same buyer in the block is not reported.
+			deposit_draw(7, account_of(ALICE));
+
+			let (_, output, _) = open_blind_box(ALICE, 3, None).unwrap();
+			let draw = Draw { blind_box_id: 3, class_id: DRAWN_CLASS, token_id: DRAWN_TOKEN };
+			assert_eq!(output, write_draw(EvmDataWriter::new(), draw).build());
+
+			assert_eq!(
+				last_draw_output(ALICE),
+				write_draw(EvmDataWriter::new().write_bool(true), draw).build(),
+			);
+			assert_eq!(
+				last_draw_output(BOB),
+				write_draw(EvmDataWriter::new().write_bool(false), Draw::default()).build(),
+			);
+		});
+	}
+
+	#[test]
+	fn test_open_blind_box_charges_the_events_of_the_block() {
+		new_test_ext().execute_with(|| {
+			let (_, _, cost) = open_blind_box(ALICE, 3, None).unwrap();
+
+			for _ in 0..5 {
+				deposit_draw(7, account_of(BOB));
+			}
+			let (_, _, cost_after_events) = open_blind_box(ALICE, 3, None).unwrap();
+
+			// One more draw of the first opening and the five deposited since.
+			assert_eq!(cost_after_events - cost, db_cost::<Test>(6, 0));
+		});
+	}
+
+	#[test]
+	fn test_open_blind_box_fails_without_gas_for_the_events() {
+		new_test_ext().execute_with(|| {
+			for _ in 0..5 {
+				deposit_draw(7, account_of(BOB));
+			}
+			let (_, _, cost) = open_blind_box(ALICE, 3, None).unwrap();
+
+			// The next opening decodes one more event, the draw of this one.
+			assert_eq!(open_blind_box(ALICE, 3, Some(cost)), Err(ExitError::OutOfGas));
+		});
+	}
+}
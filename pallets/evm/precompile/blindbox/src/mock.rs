@@ -0,0 +1,279 @@
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Test runtime with the blind box pallet behind the precompile. Buying a
+//! blind box is simulated by the runtime call, which deposits the event of a
+//! fixed draw: the precompile only relies on the call and its event.
+
+use super::*;
+use evm::Context;
+use frame_support::{
+	dispatch::DispatchResultWithPostInfo, impl_outer_event, impl_outer_origin, parameter_types,
+	traits::Randomness, weights::{constants::RocksDbWeight, DispatchInfo},
+};
+use orml_currencies::BasicCurrencyAdapter;
+use orml_traits::parameter_type_with_key;
+use pallet_evm::{EnsureAddressTruncated, FeeCalculator, HashedAddressMapping};
+use sp_core::{H160, U256};
+use sp_runtime::{
+	testing::Header,
+	traits::{BlakeTwo256, Hash, IdentityLookup, Zero},
+	AccountId32, DispatchError, ModuleId,
+};
+use uniarts_primitives::CurrencyId;
+
+impl_outer_origin! {
+	pub enum Origin for Test where system = frame_system {}
+}
+
+impl_outer_event! {
+	pub enum Event for Test {
+		frame_system<T>,
+		pallet_balances<T>,
+		pallet_evm<T>,
+		orml_tokens<T>,
+		orml_currencies<T>,
+		pallet_nft_multi<T>,
+		pallet_nft_blindbox<T>,
+	}
+}
+
+/// Class of the cards drawn by the test runtime.
+pub const DRAWN_CLASS: u64 = 11;
+/// Token of the cards drawn by the test runtime.
+pub const DRAWN_TOKEN: u64 = 12;
+
+/// Runtime call of the tests, only supporting `buy_blind_box`.
+#[derive(Clone, PartialEq, Eq, Encode, Decode, Debug)]
+pub struct Call(pallet_nft_blindbox::Call<Test>);
+
+impl From<pallet_nft_blindbox::Call<Test>> for Call {
+	fn from(call: pallet_nft_blindbox::Call<Test>) -> Self {
+		Call(call)
+	}
+}
+
+impl GetDispatchInfo for Call {
+	fn get_dispatch_info(&self) -> DispatchInfo {
+		self.0.get_dispatch_info()
+	}
+}
+
+impl Dispatchable for Call {
+	type Origin = Origin;
+	type Config = ();
+	type Info = DispatchInfo;
+	type PostInfo = PostDispatchInfo;
+
+	fn dispatch(self, origin: Origin) -> DispatchResultWithPostInfo {
+		let buyer = frame_system::ensure_signed(origin)?;
+		match self.0 {
+			pallet_nft_blindbox::Call::buy_blind_box(blind_box_id) => {
+				deposit_draw(blind_box_id, buyer);
+				Ok(().into())
+			},
+			_ => Err(DispatchError::Other("unsupported call").into()),
+		}
+	}
+}
+
+/// Deposit the event of `buyer` drawing the test card from `blind_box_id`.
+pub fn deposit_draw(blind_box_id: u64, buyer: AccountId32) {
+	System::deposit_event(pallet_nft_blindbox::Event::<Test>::BlindBoxDraw(
+		blind_box_id,
+		DRAWN_CLASS,
+		DRAWN_TOKEN,
+		buyer,
+	));
+}
+
+pub struct PalletInfo;
+
+impl frame_support::traits::PalletInfo for PalletInfo {
+	fn index<P: 'static>() -> Option<usize> {
+		return Some(0)
+	}
+
+	fn name<P: 'static>() -> Option<&'static str> {
+		return Some("TestName")
+	}
+}
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct Test;
+parameter_types! {
+	pub const BlockHashCount: u64 = 250;
+}
+impl frame_system::Config for Test {
+	type BaseCallFilter = ();
+	type BlockWeights = ();
+	type BlockLength = ();
+	type DbWeight = RocksDbWeight;
+	type Origin = Origin;
+	type Index = u64;
+	type BlockNumber = u64;
+	type Hash = H256;
+	type Call = Call;
+	type Hashing = BlakeTwo256;
+	type AccountId = AccountId32;
+	type Lookup = IdentityLookup<Self::AccountId>;
+	type Header = Header;
+	type Event = Event;
+	type BlockHashCount = BlockHashCount;
+	type Version = ();
+	type PalletInfo = PalletInfo;
+	type AccountData = pallet_balances::AccountData<u128>;
+	type OnNewAccount = ();
+	type OnKilledAccount = ();
+	type SystemWeightInfo = ();
+	type SS58Prefix = ();
+}
+
+parameter_types! {
+	pub const ExistentialDeposit: u128 = 1;
+}
+impl pallet_balances::Config for Test {
+	type MaxLocks = ();
+	type Balance = u128;
+	type DustRemoval = ();
+	type Event = Event;
+	type ExistentialDeposit = ExistentialDeposit;
+	type AccountStore = System;
+	type WeightInfo = ();
+}
+
+parameter_types! {
+	pub const MinimumPeriod: u64 = 1000;
+}
+impl pallet_timestamp::Config for Test {
+	type Moment = u64;
+	type OnTimestampSet = ();
+	type MinimumPeriod = MinimumPeriod;
+	type WeightInfo = ();
+}
+
+pub struct FixedGasPrice;
+impl FeeCalculator for FixedGasPrice {
+	fn min_gas_price() -> U256 {
+		1.into()
+	}
+}
+
+impl pallet_evm::Config for Test {
+	type FeeCalculator = FixedGasPrice;
+	type GasWeightMapping = ();
+	type CallOrigin = EnsureAddressTruncated;
+	type WithdrawOrigin = EnsureAddressTruncated;
+	type AddressMapping = HashedAddressMapping<BlakeTwo256>;
+	type Currency = Balances;
+	type Event = Event;
+	type Precompiles = ();
+	type Runner = pallet_evm::runner::stack::Runner<Self>;
+	type ChainId = ();
+}
+
+parameter_type_with_key! {
+	pub ExistentialDeposits: |_currency_id: CurrencyId| -> u128 {
+		Zero::zero()
+	};
+}
+
+impl orml_tokens::Config for Test {
+	type Event = Event;
+	type Balance = u128;
+	type Amount = i128;
+	type CurrencyId = CurrencyId;
+	type ExistentialDeposits = ExistentialDeposits;
+	type OnDust = ();
+	type WeightInfo = ();
+}
+
+parameter_types! {
+	pub const GetNativeCurrencyId: CurrencyId = CurrencyId::Native;
+}
+impl orml_currencies::Config for Test {
+	type Event = Event;
+	type MultiCurrency = Tokens;
+	type NativeCurrency = BasicCurrencyAdapter<Test, Balances, i128, u64>;
+	type GetNativeCurrencyId = GetNativeCurrencyId;
+	type WeightInfo = ();
+}
+
+parameter_types! {
+	pub const NftModuleId: ModuleId = ModuleId(*b"art/nftb");
+	pub const BlindBoxModuleId: ModuleId = ModuleId(*b"art/bbxb");
+}
+impl pallet_nft_multi::Config for Test {
+	type ModuleId = NftModuleId;
+	type MultiCurrency = Currencies;
+	type Event = Event;
+	type WeightInfo = ();
+}
+
+/// Randomness returning the hash of the subject.
+pub struct HashRandomness;
+
+impl Randomness<H256> for HashRandomness {
+	fn random(subject: &[u8]) -> H256 {
+		BlakeTwo256::hash(subject)
+	}
+}
+
+impl pallet_nft_blindbox::Config for Test {
+	type LockModuleId = BlindBoxModuleId;
+	type NftHandler = Nft;
+	type Event = Event;
+	type Randomness = HashRandomness;
+	type WeightInfo = ();
+}
+
+pub type System = frame_system::Module<Test>;
+pub type Balances = pallet_balances::Module<Test>;
+pub type Tokens = orml_tokens::Module<Test>;
+pub type Currencies = orml_currencies::Module<Test>;
+pub type Nft = pallet_nft_multi::Module<Test>;
+
+/// EVM callers.
+pub const ALICE: H160 = H160([1u8; 20]);
+pub const BOB: H160 = H160([2u8; 20]);
+
+/// Substrate account the precompile signs the calls of `address` with.
+pub fn account_of(address: H160) -> AccountId32 {
+	<Test as pallet_evm::Config>::AddressMapping::into_account_id(address)
+}
+
+/// Precompile input calling `selector` with the given arguments.
+pub fn call_data(selector: u32, arguments: EvmDataWriter) -> Vec<u8> {
+	let mut input = selector.to_be_bytes().to_vec();
+	input.extend_from_slice(&arguments.build());
+	input
+}
+
+/// Execution context of a call made by `caller` to the precompile.
+pub fn context(caller: H160) -> Context {
+	Context {
+		address: H160::from_low_u64_be(1028),
+		caller,
+		apparent_value: U256::zero(),
+	}
+}
+
+pub fn new_test_ext() -> sp_io::TestExternalities {
+	let storage = frame_system::GenesisConfig::default().build_storage::<Test>().unwrap();
+
+	let mut ext = sp_io::TestExternalities::new(storage);
+	// Events are only deposited from block 1.
+	ext.execute_with(|| System::set_block_number(1));
+	ext
+}
@@ -0,0 +1,177 @@
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Cross-VM precompile calling `pallet_contracts` (ink!) contracts from the EVM.
+//!
+//! The call is made with `bare_call` from the account mapped from the EVM
+//! caller. The remaining EVM gas, capped to `Config::MaxGas`, is converted to
+//! the contract weight limit with `GasWeightMapping`, and the weight consumed
+//! is charged back as gas. A reverting contract reverts the call with its
+//! output. Calls count against the depth of the executions re-entering the
+//! runtime from the EVM, shared with the dispatch precompile and the EVM
+//! chain extension of the contracts. The Solidity interface is
+//! `contracts/precompiles/InkContracts.sol`.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+use alloc::format;
+use sp_std::{marker::PhantomData, vec::Vec};
+use sp_core::H160;
+use sp_runtime::SaturatedConversion;
+use fp_evm::Precompile;
+use evm::{ExitSucceed, ExitError, Context};
+use frame_support::traits::Get;
+use pallet_contracts::chain_extension::UncheckedFrom;
+use pallet_evm::{AddressMapping, GasWeightMapping};
+use pallet_evm_precompile_utils::{EvmDataReader, EvmDataWriter, read_selector};
+
+/// `call(bytes32,uint256,bytes)`
+const SELECTOR_CALL: u32 = 0x185dc5a8;
+
+pub trait Config: pallet_evm::Config + pallet_contracts::Config {
+	/// Maximum gas given to a contract call, also used when the EVM does not
+	/// bound the gas of the precompile.
+	type MaxGas: Get<u64>;
+	/// Maximum number of nested executions re-entering the runtime from the
+	/// EVM, shared with the other precompiles calling into the runtime.
+	type MaxCallDepth: Get<u32>;
+}
+
+/// Error reported when the contract reverts. The output of the contract is
+/// returned as revert data, so callers get it as if an EVM contract reverted.
+fn contract_reverted(address: H160, output: Vec<u8>) -> ExitError {
+	pallet_evm::set_precompile_revert(address, output);
+	ExitError::Other("contract reverted".into())
+}
+
+pub struct InkContracts<T> {
+	_marker: PhantomData<T>,
+}
+
+impl<T> Precompile for InkContracts<T> where
+	T: Config,
+	T::AccountId: From<[u8; 32]> + UncheckedFrom<T::Hash> + AsRef<[u8]>,
+{
+	fn execute(
+		input: &[u8],
+		target_gas: Option<u64>,
+		context: &Context,
+	) -> core::result::Result<(ExitSucceed, Vec<u8>, u64), ExitError> {
+		if read_selector(input)? != SELECTOR_CALL {
+			return Err(ExitError::Other("unknown selector".into()))
+		}
+
+		let mut reader = EvmDataReader::new(input);
+		let dest = T::AccountId::from(reader.read_h256()?.to_fixed_bytes());
+		let value = reader.read_u128()?.saturated_into();
+		let data = reader.read_bytes()?;
+
+		let gas = target_gas.unwrap_or(u64::max_value()).min(T::MaxGas::get());
+		let gas_limit = T::GasWeightMapping::gas_to_weight(gas);
+		let origin = T::AddressMapping::into_account_id(context.caller);
+
+		let result = pallet_evm::with_reentrancy_guard(
+			T::MaxCallDepth::get(),
+			|| pallet_contracts::Module::<T>::bare_call(origin, dest, value, gas_limit, data),
+		).ok_or(ExitError::Other("call depth exceeded".into()))?;
+		let cost = T::GasWeightMapping::weight_to_gas(result.gas_consumed);
+
+		match result.exec_result {
+			Ok(output) if output.is_success() =>
+				Ok((ExitSucceed::Returned, EvmDataWriter::new().write_bytes(output.data).build(), cost)),
+			Ok(output) => Err(contract_reverted(context.address, output.data)),
+			Err(e) => Err(ExitError::Other(
+				format!("contract call failed: {}", Into::<&'static str>::into(e.error)).into()
+			)),
+		}
+	}
+}
+
+#[cfg(test)]
+mod mock;
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::mock::*;
+	use sp_core::H256;
+	use sp_io::hashing::keccak_256;
+
+	#[test]
+	fn test_selector_matches_interface() {
+		assert_eq!(&keccak_256(b"call(bytes32,uint256,bytes)")[0..4], &SELECTOR_CALL.to_be_bytes());
+	}
+
+	#[test]
+	fn test_contract_revert_is_returned_as_revert_data() {
+		let address = H160::from_low_u64_be(1029);
+		let (exit, revert) = pallet_evm::catch_precompile_revert(|| contract_reverted(address, vec![1, 2, 3]));
+		assert_eq!(exit, ExitError::Other("contract reverted".into()));
+		assert_eq!(revert, Some((address, vec![1, 2, 3])));
+	}
+
+	#[test]
+	fn test_unknown_selector() {
+		new_test_ext().execute_with(|| {
+			assert_eq!(
+				Ink::execute(&[0u8; 4], None, &context()),
+				Err(ExitError::Other("unknown selector".into())),
+			);
+		});
+	}
+
+	#[test]
+	fn test_missing_contract_fails() {
+		new_test_ext().execute_with(|| {
+			let input = call_input(H256::repeat_byte(9), vec![1, 2, 3]);
+			match Ink::execute(&input, Some(100_000), &context()) {
+				Err(ExitError::Other(message)) => assert!(message.starts_with("contract call failed")),
+				result => panic!("unexpected result: {:?}", result),
+			}
+		});
+	}
+
+	#[test]
+	fn test_gas_is_capped() {
+		new_test_ext().execute_with(|| {
+			let input = call_input(H256::repeat_byte(9), Vec::new());
+
+			let _ = Ink::execute(&input, None, &context());
+			assert_eq!(RecordingGasWeightMapping::last_gas(), Some(MaxGas::get()));
+
+			let _ = Ink::execute(&input, Some(MaxGas::get() * 2), &context());
+			assert_eq!(RecordingGasWeightMapping::last_gas(), Some(MaxGas::get()));
+
+			let _ = Ink::execute(&input, Some(10_000), &context());
+			assert_eq!(RecordingGasWeightMapping::last_gas(), Some(10_000));
+		});
+	}
+
+	#[test]
+	fn test_call_depth_is_shared_with_other_reentrant_executions() {
+		new_test_ext().execute_with(|| {
+			let input = call_input(H256::repeat_byte(9), Vec::new());
+			let nested = pallet_evm::with_reentrancy_guard(MaxCallDepth::get(), || {
+				pallet_evm::with_reentrancy_guard(MaxCallDepth::get(), || {
+					Ink::execute(&input, None, &context())
+				})
+			});
+			assert_eq!(nested, Some(Some(Err(ExitError::Other("call depth exceeded".into())))));
+
+			assert!(pallet_evm::with_reentrancy_guard(MaxCallDepth::get(), || ()).is_some());
+		});
+	}
+}
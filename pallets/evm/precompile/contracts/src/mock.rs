@@ -0,0 +1,223 @@
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Test runtime with the contracts pallet behind the precompile.
+
+use super::*;
+use std::cell::RefCell;
+use frame_support::{impl_outer_origin, parameter_types, traits::Randomness, weights::Weight};
+use pallet_evm::{EnsureAddressNever, EnsureAddressRoot, FeeCalculator, HashedAddressMapping};
+use sp_core::{H160, H256, U256};
+use sp_runtime::{
+	testing::Header,
+	traits::{BlakeTwo256, Convert, IdentityLookup},
+	AccountId32, Perbill,
+};
+
+impl_outer_origin! {
+	pub enum Origin for Test where system = frame_system {}
+}
+
+pub struct PalletInfo;
+
+impl frame_support::traits::PalletInfo for PalletInfo {
+	fn index<P: 'static>() -> Option<usize> {
+		return Some(0)
+	}
+
+	fn name<P: 'static>() -> Option<&'static str> {
+		return Some("TestName")
+	}
+}
+
+#[derive(Clone, Eq, PartialEq)]
+pub struct Test;
+parameter_types! {
+	pub const BlockHashCount: u64 = 250;
+}
+impl frame_system::Config for Test {
+	type BaseCallFilter = ();
+	type BlockWeights = ();
+	type BlockLength = ();
+	type DbWeight = ();
+	type Origin = Origin;
+	type Index = u64;
+	type BlockNumber = u64;
+	type Hash = H256;
+	type Call = ();
+	type Hashing = BlakeTwo256;
+	type AccountId = AccountId32;
+	type Lookup = IdentityLookup<Self::AccountId>;
+	type Header = Header;
+	type Event = ();
+	type BlockHashCount = BlockHashCount;
+	type Version = ();
+	type PalletInfo = PalletInfo;
+	type AccountData = pallet_balances::AccountData<u64>;
+	type OnNewAccount = ();
+	type OnKilledAccount = ();
+	type SystemWeightInfo = ();
+	type SS58Prefix = ();
+}
+
+parameter_types! {
+	pub const ExistentialDeposit: u64 = 1;
+}
+impl pallet_balances::Config for Test {
+	type MaxLocks = ();
+	type Balance = u64;
+	type DustRemoval = ();
+	type Event = ();
+	type ExistentialDeposit = ExistentialDeposit;
+	type AccountStore = frame_system::Module<Test>;
+	type WeightInfo = ();
+}
+
+parameter_types! {
+	pub const MinimumPeriod: u64 = 1000;
+}
+impl pallet_timestamp::Config for Test {
+	type Moment = u64;
+	type OnTimestampSet = ();
+	type MinimumPeriod = MinimumPeriod;
+	type WeightInfo = ();
+}
+
+pub struct FixedGasPrice;
+impl FeeCalculator for FixedGasPrice {
+	fn min_gas_price() -> U256 {
+		0.into()
+	}
+}
+
+thread_local! {
+	static LAST_GAS: RefCell<Option<u64>> = RefCell::new(None);
+}
+
+/// One weight per gas, recording the last gas converted to weight.
+pub struct RecordingGasWeightMapping;
+
+impl RecordingGasWeightMapping {
+	pub fn last_gas() -> Option<u64> {
+		LAST_GAS.with(|gas| *gas.borrow())
+	}
+}
+
+impl GasWeightMapping for RecordingGasWeightMapping {
+	fn gas_to_weight(gas: u64) -> Weight {
+		LAST_GAS.with(|last| *last.borrow_mut() = Some(gas));
+		gas
+	}
+
+	fn weight_to_gas(weight: Weight) -> u64 {
+		weight
+	}
+}
+
+impl pallet_evm::Config for Test {
+	type FeeCalculator = FixedGasPrice;
+	type GasWeightMapping = RecordingGasWeightMapping;
+	type CallOrigin = EnsureAddressRoot<Self::AccountId>;
+	type WithdrawOrigin = EnsureAddressNever<Self::AccountId>;
+	type AddressMapping = HashedAddressMapping<BlakeTwo256>;
+	type Currency = pallet_balances::Module<Test>;
+	type Event = ();
+	type Precompiles = ();
+	type Runner = pallet_evm::runner::stack::Runner<Self>;
+	type ChainId = ();
+}
+
+pub struct TestRandomness;
+
+impl Randomness<H256> for TestRandomness {
+	fn random(_subject: &[u8]) -> H256 {
+		H256::zero()
+	}
+}
+
+pub struct WeightToBalance;
+
+impl Convert<Weight, u64> for WeightToBalance {
+	fn convert(weight: Weight) -> u64 {
+		weight
+	}
+}
+
+parameter_types! {
+	pub const SignedClaimHandicap: u64 = 2;
+	pub const TombstoneDeposit: u64 = 16;
+	pub const DepositPerContract: u64 = 8;
+	pub const DepositPerStorageByte: u64 = 1;
+	pub const DepositPerStorageItem: u64 = 2;
+	pub RentFraction: Perbill = Perbill::from_rational_approximation(1u32, 30);
+	pub const SurchargeReward: u64 = 150;
+	pub const MaxDepth: u32 = 32;
+	pub const MaxValueSize: u32 = 16 * 1024;
+	pub const DeletionQueueDepth: u32 = 1024;
+	pub const DeletionWeightLimit: Weight = 500_000_000_000;
+	pub const MaxCodeSize: u32 = 2 * 1024;
+}
+impl pallet_contracts::Config for Test {
+	type Time = pallet_timestamp::Module<Test>;
+	type Randomness = TestRandomness;
+	type Currency = pallet_balances::Module<Test>;
+	type Event = ();
+	type RentPayment = ();
+	type SignedClaimHandicap = SignedClaimHandicap;
+	type TombstoneDeposit = TombstoneDeposit;
+	type DepositPerContract = DepositPerContract;
+	type DepositPerStorageByte = DepositPerStorageByte;
+	type DepositPerStorageItem = DepositPerStorageItem;
+	type RentFraction = RentFraction;
+	type SurchargeReward = SurchargeReward;
+	type MaxDepth = MaxDepth;
+	type MaxValueSize = MaxValueSize;
+	type WeightPrice = WeightToBalance;
+	type WeightInfo = ();
+	type ChainExtension = ();
+	type DeletionQueueDepth = DeletionQueueDepth;
+	type DeletionWeightLimit = DeletionWeightLimit;
+	type MaxCodeSize = MaxCodeSize;
+}
+
+parameter_types! {
+	pub const MaxGas: u64 = 1_000_000;
+	pub const MaxCallDepth: u32 = 2;
+}
+impl Config for Test {
+	type MaxGas = MaxGas;
+	type MaxCallDepth = MaxCallDepth;
+}
+
+pub type Ink = InkContracts<Test>;
+
+/// Input calling the ink! contract `dest` with `data`.
+pub fn call_input(dest: H256, data: Vec<u8>) -> Vec<u8> {
+	let mut input = SELECTOR_CALL.to_be_bytes().to_vec();
+	input.extend_from_slice(&EvmDataWriter::new().write_h256(dest).write_u256(0u8).write_bytes(data).build());
+	input
+}
+
+pub fn context() -> Context {
+	Context {
+		address: H160::from_low_u64_be(1029),
+		caller: H160::repeat_byte(1),
+		apparent_value: U256::zero(),
+	}
+}
+
+pub fn new_test_ext() -> sp_io::TestExternalities {
+	let storage = frame_system::GenesisConfig::default().build_storage::<Test>().unwrap();
+	sp_io::TestExternalities::new(storage)
+}
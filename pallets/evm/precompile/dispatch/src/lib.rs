@@ -23,16 +23,43 @@ use alloc::vec::Vec;
 use core::marker::PhantomData;
 use fp_evm::Precompile;
 use evm::{ExitSucceed, ExitError, Context};
-use frame_support::{dispatch::{Dispatchable, GetDispatchInfo, PostDispatchInfo}, weights::{Pays, DispatchClass}};
+use frame_support::{
+	dispatch::{Dispatchable, GetDispatchInfo, PostDispatchInfo},
+	traits::{Filter, Get},
+	weights::{Pays, DispatchClass},
+};
 use pallet_evm::{AddressMapping, GasWeightMapping};
-use codec::Decode;
+use sp_core::H160;
+use sp_runtime::DispatchError;
+use codec::{Decode, Encode};
 
-pub struct Dispatch<T: pallet_evm::Config> {
+pub trait Config: pallet_evm::Config {
+	/// Calls contracts are allowed to dispatch through the precompile.
+	type CallFilter: Filter<Self::Call>;
+	/// Calls that execute EVM code (`pallet_evm`, `pallet_ethereum`, or wrappers
+	/// around them). They are always rejected, so the precompile can not be used
+	/// to re-enter the EVM.
+	type EvmCalls: Filter<Self::Call>;
+	/// Maximum number of nested executions re-entering the runtime from the
+	/// EVM, shared with the other precompiles calling into the runtime.
+	type MaxDispatchDepth: Get<u32>;
+}
+
+/// Error reported when the dispatched call fails. The SCALE encoded
+/// `DispatchError` is returned as revert data, so callers can decode the
+/// exact module error.
+fn dispatch_error(address: H160, error: DispatchError) -> ExitError {
+	pallet_evm::set_precompile_revert(address, error.encode());
+	let reason: &'static str = error.into();
+	ExitError::Other(reason.into())
+}
+
+pub struct Dispatch<T: Config> {
 	_marker: PhantomData<T>,
 }
 
 impl<T> Precompile for Dispatch<T> where
-	T: pallet_evm::Config,
+	T: Config,
 	T::Call: Dispatchable<PostInfo=PostDispatchInfo> + GetDispatchInfo + Decode,
 	<T::Call as Dispatchable>::Origin: From<Option<T::AccountId>>,
 {
@@ -49,6 +76,14 @@ impl<T> Precompile for Dispatch<T> where
 			return Err(ExitError::Other("invalid call".into()))
 		}
 
+		if T::EvmCalls::filter(&call) {
+			return Err(ExitError::Other("reentrant call".into()))
+		}
+
+		if !T::CallFilter::filter(&call) {
+			return Err(ExitError::Other("call filtered".into()))
+		}
+
 		if let Some(gas) = target_gas {
 			let valid_weight = info.weight <= T::GasWeightMapping::gas_to_weight(gas);
 			if !valid_weight {
@@ -58,12 +93,31 @@ impl<T> Precompile for Dispatch<T> where
 
 		let origin = T::AddressMapping::into_account_id(context.caller);
 
-		match call.dispatch(Some(origin).into()) {
+		let result = pallet_evm::with_reentrancy_guard(
+			T::MaxDispatchDepth::get(),
+			|| call.dispatch(Some(origin).into()),
+		).ok_or(ExitError::Other("dispatch depth exceeded".into()))?;
+
+		match result {
 			Ok(post_info) => {
 				let cost = T::GasWeightMapping::weight_to_gas(post_info.actual_weight.unwrap_or(info.weight));
 				Ok((ExitSucceed::Stopped, Default::default(), cost))
 			},
-			Err(_) => Err(ExitError::Other("dispatch execution failed".into())),
+			Err(e) => Err(dispatch_error(context.address, e.error)),
 		}
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_dispatch_error_is_returned_as_revert_data() {
+		let error = DispatchError::Module { index: 3, error: 7, message: Some("InsufficientBalance") };
+		let address = H160::from_low_u64_be(1025);
+		let (exit, revert) = pallet_evm::catch_precompile_revert(|| dispatch_error(address, error));
+		assert_eq!(exit, ExitError::Other("InsufficientBalance".into()));
+		assert_eq!(revert, Some((address, vec![3, 3, 7])));
+	}
+}
@@ -0,0 +1,405 @@
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! ENS-like name service precompile backed by `pallet_names`.
+//!
+//! Lookups read the pallet storage directly. Registrations and updates are
+//! dispatched as `pallet_names::Call::update` signed by the account mapped
+//! from the EVM caller, so the runtime's `get_name_fee` and `get_expiration`
+//! rules apply unchanged. The Solidity interface is `contracts/precompiles/Names.sol`.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use sp_std::{marker::PhantomData, vec::Vec};
+use sp_core::{H160, H256};
+use sp_io::hashing::{blake2_128, twox_128};
+use fp_evm::Precompile;
+use evm::{ExitSucceed, ExitError, Context};
+use frame_support::{
+	dispatch::{Dispatchable, GetDispatchInfo, PostDispatchInfo},
+	storage::unhashed,
+};
+use pallet_evm::AddressMapping;
+use pallet_evm_precompile_utils::{
+	EvmDataReader, EvmDataWriter, read_selector, ensure_gas, db_cost, dispatch,
+};
+
+/// `value(bytes)`
+const SELECTOR_VALUE: u32 = 0xec6acad6;
+/// `addr(bytes)`
+const SELECTOR_ADDR: u32 = 0x85337958;
+/// `owner(bytes)`
+const SELECTOR_OWNER: u32 = 0xbd27b241;
+/// `expiration(bytes)`
+const SELECTOR_EXPIRATION: u32 = 0x004aa449;
+/// `nameOf(address)`
+const SELECTOR_NAME_OF: u32 = 0xf5c57382;
+/// `register(bytes,bytes)`
+const SELECTOR_REGISTER: u32 = 0xa3747fef;
+/// `update(bytes,bytes)`
+const SELECTOR_UPDATE: u32 = 0x3f37dce2;
+/// `setName(bytes)`
+const SELECTOR_SET_NAME: u32 = 0xe802e716;
+
+/// Storage key of the reverse record (`address => name`) of an EVM address.
+fn reverse_record_key(address: &H160) -> Vec<u8> {
+	let mut key = [twox_128(b"NamesPrecompile"), twox_128(b"ReverseRecords")].concat();
+	key.extend_from_slice(&blake2_128(address.as_bytes()));
+	key.extend_from_slice(address.as_bytes());
+	key
+}
+
+/// A name resolves to an EVM address when its value is exactly 20 bytes long.
+fn resolve_address(value: &[u8]) -> Option<H160> {
+	if value.len() == 20 {
+		Some(H160::from_slice(value))
+	} else {
+		None
+	}
+}
+
+pub struct Names<T> {
+	_marker: PhantomData<T>,
+}
+
+impl<T> Names<T> where
+	T: pallet_evm::Config + pallet_names::Config<Name=Vec<u8>, Value=Vec<u8>>,
+	T::AccountId: Into<[u8; 32]>,
+	T::Call: Dispatchable<PostInfo=PostDispatchInfo> + GetDispatchInfo + From<pallet_names::Call<T>>,
+	<T::Call as Dispatchable>::Origin: From<Option<T::AccountId>>,
+{
+	fn name_of(address: &H160) -> Option<Vec<u8>> {
+		let name: Vec<u8> = unhashed::get(&reverse_record_key(address))?;
+		let data = pallet_names::Module::<T>::lookup(&name)?;
+
+		// Stale reverse records (expired or re-pointed names) are not reported.
+		if resolve_address(&data.value) == Some(*address) {
+			Some(name)
+		} else {
+			None
+		}
+	}
+}
+
+impl<T> Precompile for Names<T> where
+	T: pallet_evm::Config + pallet_names::Config<Name=Vec<u8>, Value=Vec<u8>>,
+	T::AccountId: Into<[u8; 32]>,
+	T::Call: Dispatchable<PostInfo=PostDispatchInfo> + GetDispatchInfo + From<pallet_names::Call<T>>,
+	<T::Call as Dispatchable>::Origin: From<Option<T::AccountId>>,
+{
+	fn execute(
+		input: &[u8],
+		target_gas: Option<u64>,
+		context: &Context,
+	) -> core::result::Result<(ExitSucceed, Vec<u8>, u64), ExitError> {
+		let selector = read_selector(input)?;
+		let mut reader = EvmDataReader::new(input);
+
+		match selector {
+			SELECTOR_VALUE => {
+				let cost = ensure_gas(target_gas, db_cost::<T>(1, 0))?;
+				let name = reader.read_bytes()?;
+				let value = pallet_names::Module::<T>::lookup(&name)
+					.map(|data| data.value)
+					.unwrap_or_default();
+
+				Ok((ExitSucceed::Returned, EvmDataWriter::new().write_bytes(value).build(), cost))
+			},
+			SELECTOR_ADDR => {
+				let cost = ensure_gas(target_gas, db_cost::<T>(1, 0))?;
+				let name = reader.read_bytes()?;
+				let address = pallet_names::Module::<T>::lookup(&name)
+					.and_then(|data| resolve_address(&data.value))
+					.unwrap_or_default();
+
+				Ok((ExitSucceed::Returned, EvmDataWriter::new().write_address(address).build(), cost))
+			},
+			SELECTOR_OWNER => {
+				let cost = ensure_gas(target_gas, db_cost::<T>(1, 0))?;
+				let name = reader.read_bytes()?;
+				let owner = pallet_names::Module::<T>::lookup(&name)
+					.map(|data| H256::from(Into::<[u8; 32]>::into(data.owner)))
+					.unwrap_or_default();
+
+				Ok((ExitSucceed::Returned, EvmDataWriter::new().write_h256(owner).build(), cost))
+			},
+			SELECTOR_EXPIRATION => {
+				let cost = ensure_gas(target_gas, db_cost::<T>(1, 0))?;
+				let name = reader.read_bytes()?;
+				let expiration = pallet_names::Module::<T>::lookup(&name)
+					.and_then(|data| data.expiration)
+					.map(block_number_to_u64);
+
+				let output = EvmDataWriter::new()
+					.write_bool(expiration.is_some())
+					.write_u256(expiration.unwrap_or_default())
+					.build();
+				Ok((ExitSucceed::Returned, output, cost))
+			},
+			SELECTOR_NAME_OF => {
+				let cost = ensure_gas(target_gas, db_cost::<T>(2, 0))?;
+				let address = reader.read_address()?;
+				let name = Self::name_of(&address).unwrap_or_default();
+
+				Ok((ExitSucceed::Returned, EvmDataWriter::new().write_bytes(name).build(), cost))
+			},
+			SELECTOR_REGISTER | SELECTOR_UPDATE => {
+				let lookup_cost = ensure_gas(target_gas, db_cost::<T>(1, 0))?;
+				let name = reader.read_bytes()?;
+				let value = reader.read_bytes()?;
+
+				match (selector, pallet_names::Module::<T>::lookup(&name)) {
+					(SELECTOR_REGISTER, Some(_)) =>
+						return Err(ExitError::Other("name already registered".into())),
+					(SELECTOR_UPDATE, None) =>
+						return Err(ExitError::Other("name not registered".into())),
+					(SELECTOR_UPDATE, Some(data))
+						if data.owner != T::AddressMapping::into_account_id(context.caller) =>
+						return Err(ExitError::Other("caller is not the name owner".into())),
+					_ => (),
+				}
+
+				let dispatch_cost = dispatch::<T>(
+					context.caller,
+					pallet_names::Call::<T>::update(name, value).into(),
+					target_gas.map(|gas| gas.saturating_sub(lookup_cost)),
+				)?;
+
+				Ok((ExitSucceed::Returned, Vec::new(), lookup_cost.saturating_add(dispatch_cost)))
+			},
+			SELECTOR_SET_NAME => {
+				let cost = ensure_gas(target_gas, db_cost::<T>(1, 1))?;
+				let name = reader.read_bytes()?;
+				let key = reverse_record_key(&context.caller);
+
+				if name.is_empty() {
+					unhashed::kill(&key);
+				} else {
+					let resolved = pallet_names::Module::<T>::lookup(&name)
+						.and_then(|data| resolve_address(&data.value));
+					if resolved != Some(context.caller) {
+						return Err(ExitError::Other("name does not resolve to caller".into()));
+					}
+					unhashed::put(&key, &name);
+				}
+
+				Ok((ExitSucceed::Returned, Vec::new(), cost))
+			},
+			_ => Err(ExitError::Other("unknown selector".into())),
+		}
+	}
+}
+
+/// Block numbers are reported as `uint256`, saturating at `u64::MAX`.
+fn block_number_to_u64<N: sp_std::convert::TryInto<u64>>(n: N) -> u64 {
+	n.try_into().unwrap_or(u64::max_value())
+}
+
+#[cfg(test)]
+mod mock;
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::mock::*;
+	use sp_io::hashing::keccak_256;
+
+	fn selector_of(signature: &str) -> u32 {
+		let hash = keccak_256(signature.as_bytes());
+		u32::from_be_bytes([hash[0], hash[1], hash[2], hash[3]])
+	}
+
+	fn register(caller: H160, name: &[u8], value: &[u8]) -> Result<(ExitSucceed, Vec<u8>, u64), ExitError> {
+		let input = call_data(
+			SELECTOR_REGISTER,
+			EvmDataWriter::new().write_bytes(name.to_vec()).write_bytes(value.to_vec()),
+		);
+		Names::<Test>::execute(&input, None, &context(caller))
+	}
+
+	fn update(caller: H160, name: &[u8], value: &[u8]) -> Result<(ExitSucceed, Vec<u8>, u64), ExitError> {
+		let input = call_data(
+			SELECTOR_UPDATE,
+			EvmDataWriter::new().write_bytes(name.to_vec()).write_bytes(value.to_vec()),
+		);
+		Names::<Test>::execute(&input, None, &context(caller))
+	}
+
+	fn set_name(caller: H160, name: &[u8]) -> Result<(ExitSucceed, Vec<u8>, u64), ExitError> {
+		let input = call_data(SELECTOR_SET_NAME, EvmDataWriter::new().write_bytes(name.to_vec()));
+		Names::<Test>::execute(&input, None, &context(caller))
+	}
+
+	fn name_of_output(address: H160) -> Vec<u8> {
+		let input = call_data(SELECTOR_NAME_OF, EvmDataWriter::new().write_address(address));
+		Names::<Test>::execute(&input, None, &context(CHARLIE)).unwrap().1
+	}
+
+	#[test]
+	fn test_selectors_match_interface() {
+		assert_eq!(selector_of("value(bytes)"), SELECTOR_VALUE);
+		assert_eq!(selector_of("addr(bytes)"), SELECTOR_ADDR);
+		assert_eq!(selector_of("owner(bytes)"), SELECTOR_OWNER);
+		assert_eq!(selector_of("expiration(bytes)"), SELECTOR_EXPIRATION);
+		assert_eq!(selector_of("nameOf(address)"), SELECTOR_NAME_OF);
+		assert_eq!(selector_of("register(bytes,bytes)"), SELECTOR_REGISTER);
+		assert_eq!(selector_of("update(bytes,bytes)"), SELECTOR_UPDATE);
+		assert_eq!(selector_of("setName(bytes)"), SELECTOR_SET_NAME);
+	}
+
+	#[test]
+	fn test_resolve_address() {
+		assert_eq!(resolve_address(&[0x11; 20]), Some(H160::repeat_byte(0x11)));
+		assert_eq!(resolve_address(b"not an address"), None);
+	}
+
+	#[test]
+	fn test_reverse_record_key_is_per_address() {
+		let alice = reverse_record_key(&H160::repeat_byte(0x01));
+		let bob = reverse_record_key(&H160::repeat_byte(0x02));
+		assert_eq!(alice.len(), 32 + 16 + 20);
+		assert_ne!(alice, bob);
+	}
+
+	#[test]
+	fn test_register_charges_the_registration_fee_of_the_mapped_caller() {
+		new_test_ext().execute_with(|| {
+			assert!(register(ALICE, b"alice", ALICE.as_bytes()).is_ok());
+
+			let data = NamesModule::lookup(&b"alice".to_vec()).unwrap();
+			assert_eq!(data.owner, account_of(ALICE));
+			assert_eq!(data.value, ALICE.as_bytes().to_vec());
+			assert_eq!(Balances::free_balance(account_of(ALICE)), 1_000_000 - REGISTRATION_FEE);
+
+			let input = call_data(SELECTOR_ADDR, EvmDataWriter::new().write_bytes(b"alice".to_vec()));
+			let (_, output, _) = Names::<Test>::execute(&input, None, &context(BOB)).unwrap();
+			assert_eq!(output, EvmDataWriter::new().write_address(ALICE).build());
+
+			let input = call_data(SELECTOR_OWNER, EvmDataWriter::new().write_bytes(b"alice".to_vec()));
+			let (_, output, _) = Names::<Test>::execute(&input, None, &context(BOB)).unwrap();
+			let owner: [u8; 32] = account_of(ALICE).into();
+			assert_eq!(output, EvmDataWriter::new().write_h256(owner.into()).build());
+		});
+	}
+
+	#[test]
+	fn test_register_fails_for_taken_names_and_without_funds() {
+		new_test_ext().execute_with(|| {
+			assert!(register(ALICE, b"alice", b"first").is_ok());
+			assert_eq!(
+				register(BOB, b"alice", b"second"),
+				Err(ExitError::Other("name already registered".into())),
+			);
+			assert_eq!(NamesModule::lookup(&b"alice".to_vec()).unwrap().value, b"first".to_vec());
+
+			assert!(register(CHARLIE, b"charlie", b"value").is_err());
+			assert!(NamesModule::lookup(&b"charlie".to_vec()).is_none());
+		});
+	}
+
+	#[test]
+	fn test_update_is_restricted_to_the_owner_and_charges_the_update_fee() {
+		new_test_ext().execute_with(|| {
+			assert_eq!(
+				update(ALICE, b"alice", b"value"),
+				Err(ExitError::Other("name not registered".into())),
+			);
+
+			assert!(register(ALICE, b"alice", b"first").is_ok());
+			assert_eq!(
+				update(BOB, b"alice", b"second"),
+				Err(ExitError::Other("caller is not the name owner".into())),
+			);
+			assert_eq!(Balances::free_balance(account_of(BOB)), 1_000_000);
+
+			assert!(update(ALICE, b"alice", b"second").is_ok());
+			assert_eq!(NamesModule::lookup(&b"alice".to_vec()).unwrap().value, b"second".to_vec());
+			assert_eq!(
+				Balances::free_balance(account_of(ALICE)),
+				1_000_000 - REGISTRATION_FEE - UPDATE_FEE,
+			);
+		});
+	}
+
+	#[test]
+	fn test_expiration_of_short_and_long_names() {
+		new_test_ext().execute_with(|| {
+			assert!(register(ALICE, b"ab", b"value").is_ok());
+			assert!(register(ALICE, b"alice", b"value").is_ok());
+
+			let expires_at = NamesModule::lookup(&b"ab".to_vec()).unwrap().expiration.unwrap();
+			let input = call_data(SELECTOR_EXPIRATION, EvmDataWriter::new().write_bytes(b"ab".to_vec()));
+			let (_, output, _) = Names::<Test>::execute(&input, None, &context(BOB)).unwrap();
+			assert_eq!(output, EvmDataWriter::new().write_bool(true).write_u256(expires_at).build());
+
+			let input = call_data(SELECTOR_EXPIRATION, EvmDataWriter::new().write_bytes(b"alice".to_vec()));
+			let (_, output, _) = Names::<Test>::execute(&input, None, &context(BOB)).unwrap();
+			assert_eq!(output, EvmDataWriter::new().write_bool(false).write_u256(0u8).build());
+		});
+	}
+
+	#[test]
+	fn test_set_name_requires_the_name_to_resolve_to_the_caller() {
+		new_test_ext().execute_with(|| {
+			assert!(register(ALICE, b"alice", ALICE.as_bytes()).is_ok());
+			assert_eq!(
+				set_name(BOB, b"alice"),
+				Err(ExitError::Other("name does not resolve to caller".into())),
+			);
+			assert_eq!(name_of_output(BOB), EvmDataWriter::new().write_bytes(Vec::new()).build());
+
+			assert!(set_name(ALICE, b"alice").is_ok());
+			assert_eq!(name_of_output(ALICE), EvmDataWriter::new().write_bytes(b"alice".to_vec()).build());
+
+			assert!(set_name(ALICE, b"").is_ok());
+			assert_eq!(name_of_output(ALICE), EvmDataWriter::new().write_bytes(Vec::new()).build());
+		});
+	}
+
+	#[test]
+	fn test_reverse_record_is_stale_after_a_transfer() {
+		new_test_ext().execute_with(|| {
+			assert!(register(ALICE, b"alice", ALICE.as_bytes()).is_ok());
+			assert!(set_name(ALICE, b"alice").is_ok());
+
+			assert!(NamesModule::transfer(
+				Origin::signed(account_of(ALICE)),
+				b"alice".to_vec(),
+				account_of(BOB),
+			).is_ok());
+			assert_eq!(
+				update(ALICE, b"alice", ALICE.as_bytes()),
+				Err(ExitError::Other("caller is not the name owner".into())),
+			);
+
+			// The name still resolves to ALICE until its new owner re-points it.
+			assert_eq!(name_of_output(ALICE), EvmDataWriter::new().write_bytes(b"alice".to_vec()).build());
+			assert!(update(BOB, b"alice", BOB.as_bytes()).is_ok());
+			assert_eq!(name_of_output(ALICE), EvmDataWriter::new().write_bytes(Vec::new()).build());
+		});
+	}
+
+	#[test]
+	fn test_reverse_record_is_stale_after_expiry() {
+		new_test_ext().execute_with(|| {
+			assert!(register(ALICE, b"ab", ALICE.as_bytes()).is_ok());
+			assert!(set_name(ALICE, b"ab").is_ok());
+			assert_eq!(name_of_output(ALICE), EvmDataWriter::new().write_bytes(b"ab".to_vec()).build());
+
+			run_to_block(1 + SHORT_NAME_LIFETIME + 1);
+			assert!(NamesModule::lookup(&b"ab".to_vec()).is_none());
+			assert_eq!(name_of_output(ALICE), EvmDataWriter::new().write_bytes(Vec::new()).build());
+		});
+	}
+}
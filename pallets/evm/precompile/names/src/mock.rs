@@ -0,0 +1,212 @@
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Test runtime with the names pallet behind the precompile.
+
+use super::*;
+use evm::Context;
+use frame_support::{
+	impl_outer_dispatch, impl_outer_origin, parameter_types,
+	traits::{Currency, OnFinalize, OnInitialize},
+	weights::constants::RocksDbWeight,
+};
+use pallet_evm::{EnsureAddressTruncated, FeeCalculator, HashedAddressMapping};
+use sp_core::{H160, U256};
+use sp_runtime::{
+	testing::Header,
+	traits::{BlakeTwo256, IdentityLookup},
+	AccountId32,
+};
+
+impl_outer_origin! {
+	pub enum Origin for Test where system = frame_system {}
+}
+
+impl_outer_dispatch! {
+	pub enum Call for Test where origin: Origin {
+		pallet_names::NamesModule,
+	}
+}
+
+pub struct PalletInfo;
+
+impl frame_support::traits::PalletInfo for PalletInfo {
+	fn index<P: 'static>() -> Option<usize> {
+		return Some(0)
+	}
+
+	fn name<P: 'static>() -> Option<&'static str> {
+		return Some("TestName")
+	}
+}
+
+#[derive(Clone, Eq, PartialEq)]
+pub struct Test;
+parameter_types! {
+	pub const BlockHashCount: u64 = 250;
+}
+impl frame_system::Config for Test {
+	type BaseCallFilter = ();
+	type BlockWeights = ();
+	type BlockLength = ();
+	type DbWeight = RocksDbWeight;
+	type Origin = Origin;
+	type Index = u64;
+	type BlockNumber = u64;
+	type Hash = H256;
+	type Call = Call;
+	type Hashing = BlakeTwo256;
+	type AccountId = AccountId32;
+	type Lookup = IdentityLookup<Self::AccountId>;
+	type Header = Header;
+	type Event = ();
+	type BlockHashCount = BlockHashCount;
+	type Version = ();
+	type PalletInfo = PalletInfo;
+	type AccountData = pallet_balances::AccountData<u128>;
+	type OnNewAccount = ();
+	type OnKilledAccount = ();
+	type SystemWeightInfo = ();
+	type SS58Prefix = ();
+}
+
+parameter_types! {
+	pub const ExistentialDeposit: u128 = 1;
+}
+impl pallet_balances::Config for Test {
+	type MaxLocks = ();
+	type Balance = u128;
+	type DustRemoval = ();
+	type Event = ();
+	type ExistentialDeposit = ExistentialDeposit;
+	type AccountStore = System;
+	type WeightInfo = ();
+}
+
+parameter_types! {
+	pub const MinimumPeriod: u64 = 1000;
+}
+impl pallet_timestamp::Config for Test {
+	type Moment = u64;
+	type OnTimestampSet = ();
+	type MinimumPeriod = MinimumPeriod;
+	type WeightInfo = ();
+}
+
+pub struct FixedGasPrice;
+impl FeeCalculator for FixedGasPrice {
+	fn min_gas_price() -> U256 {
+		1.into()
+	}
+}
+
+impl pallet_evm::Config for Test {
+	type FeeCalculator = FixedGasPrice;
+	type GasWeightMapping = ();
+	type CallOrigin = EnsureAddressTruncated;
+	type WithdrawOrigin = EnsureAddressTruncated;
+	type AddressMapping = HashedAddressMapping<BlakeTwo256>;
+	type Currency = Balances;
+	type Event = ();
+	type Precompiles = ();
+	type Runner = pallet_evm::runner::stack::Runner<Self>;
+	type ChainId = ();
+}
+
+/// Fee of registering a name.
+pub const REGISTRATION_FEE: u128 = 100;
+/// Fee of updating a name.
+pub const UPDATE_FEE: u128 = 10;
+/// Blocks after which short names expire.
+pub const SHORT_NAME_LIFETIME: u64 = 10;
+
+impl pallet_names::Config for Test {
+	type Name = Vec<u8>;
+	type Value = Vec<u8>;
+	type Currency = Balances;
+	type Event = ();
+
+	fn get_name_fee(op: &pallet_names::Operation<Self>) -> Option<u128> {
+		// Same rules as the Fuxi runtime, with smaller fees.
+		if op.name.len() < 2 {
+			return None
+		}
+
+		Some(match op.operation {
+			pallet_names::OperationType::Registration => REGISTRATION_FEE,
+			pallet_names::OperationType::Update => UPDATE_FEE,
+		})
+	}
+
+	fn get_expiration(op: &pallet_names::Operation<Self>) -> Option<u64> {
+		if op.name.len() <= 3 {
+			Some(SHORT_NAME_LIFETIME)
+		} else {
+			None
+		}
+	}
+
+	fn deposit_fee(_b: <Self::Currency as Currency<AccountId32>>::NegativeImbalance) {}
+}
+
+pub type System = frame_system::Module<Test>;
+pub type Balances = pallet_balances::Module<Test>;
+pub type NamesModule = pallet_names::Module<Test>;
+
+/// EVM callers, `CHARLIE` has no funds.
+pub const ALICE: H160 = H160([1u8; 20]);
+pub const BOB: H160 = H160([2u8; 20]);
+pub const CHARLIE: H160 = H160([3u8; 20]);
+
+/// Substrate account the precompile signs the calls of `address` with.
+pub fn account_of(address: H160) -> AccountId32 {
+	<Test as pallet_evm::Config>::AddressMapping::into_account_id(address)
+}
+
+/// Precompile input calling `selector` with the given arguments.
+pub fn call_data(selector: u32, arguments: EvmDataWriter) -> Vec<u8> {
+	let mut input = selector.to_be_bytes().to_vec();
+	input.extend_from_slice(&arguments.build());
+	input
+}
+
+/// Execution context of a call made by `caller` to the precompile.
+pub fn context(caller: H160) -> Context {
+	Context {
+		address: H160::from_low_u64_be(1024),
+		caller,
+		apparent_value: U256::zero(),
+	}
+}
+
+/// Runs the block hooks up to block `n`, expiring names on the way.
+pub fn run_to_block(n: u64) {
+	while System::block_number() < n {
+		NamesModule::on_finalize(System::block_number());
+		System::set_block_number(System::block_number() + 1);
+		NamesModule::on_initialize(System::block_number());
+	}
+}
+
+pub fn new_test_ext() -> sp_io::TestExternalities {
+	let mut storage = frame_system::GenesisConfig::default().build_storage::<Test>().unwrap();
+
+	pallet_balances::GenesisConfig::<Test> {
+		balances: vec![(account_of(ALICE), 1_000_000), (account_of(BOB), 1_000_000)],
+	}.assimilate_storage(&mut storage).unwrap();
+
+	let mut ext = sp_io::TestExternalities::new(storage);
+	ext.execute_with(|| System::set_block_number(1));
+	ext
+}
@@ -0,0 +1,144 @@
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Randomness precompile, returning `R::random(subject)` to Solidity.
+//!
+//! On fuxi `R` is `RandomnessCollectiveFlip`, which mixes the hashes of the
+//! previous 81 blocks. Its output is NOT secure randomness:
+//!
+//! - it is known to everyone as soon as the parent block is imported, so a
+//!   contract must commit to an action before the block providing the
+//!   randomness is produced (e.g. reveal in a later block);
+//! - the block author can choose not to publish a block whose hash makes the
+//!   outcome unfavourable to them, biasing the result by withholding;
+//! - within a block the output only depends on `subject`, so the same subject
+//!   yields the same value for every caller and every call.
+//!
+//! Callers should include a nonce and their own address in `subject`, and
+//! should not use it for draws whose value exceeds the block reward.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use sp_std::{marker::PhantomData, vec::Vec};
+use sp_core::H256;
+use fp_evm::Precompile;
+use evm::{ExitSucceed, ExitError, Context};
+use frame_support::traits::Randomness;
+use pallet_evm_precompile_utils::{
+	EvmDataReader, EvmDataWriter, read_selector, ensure_gas, db_cost,
+};
+
+/// `random(bytes)`
+const SELECTOR_RANDOM: u32 = 0x414bdd34;
+/// `randomSeed()`
+const SELECTOR_RANDOM_SEED: u32 = 0x0b747d91;
+
+pub struct RandomnessSource<T, R> {
+	_marker: PhantomData<(T, R)>,
+}
+
+impl<T, R> Precompile for RandomnessSource<T, R> where
+	T: pallet_evm::Config,
+	R: Randomness<H256>,
+{
+	fn execute(
+		input: &[u8],
+		target_gas: Option<u64>,
+		_context: &Context,
+	) -> core::result::Result<(ExitSucceed, Vec<u8>, u64), ExitError> {
+		// The random material is a single storage value.
+		let cost = ensure_gas(target_gas, db_cost::<T>(1, 0))?;
+
+		let output = match read_selector(input)? {
+			SELECTOR_RANDOM => {
+				let subject = EvmDataReader::new(input).read_bytes()?;
+				R::random(&subject)
+			},
+			SELECTOR_RANDOM_SEED => R::random_seed(),
+			_ => return Err(ExitError::Other("unknown selector".into())),
+		};
+
+		Ok((ExitSucceed::Returned, EvmDataWriter::new().write_h256(output).build(), cost))
+	}
+}
+
+#[cfg(test)]
+mod mock;
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::mock::*;
+	use sp_io::hashing::keccak_256;
+	use sp_runtime::traits::{BlakeTwo256, Hash};
+
+	fn selector_of(signature: &str) -> u32 {
+		let hash = keccak_256(signature.as_bytes());
+		u32::from_be_bytes([hash[0], hash[1], hash[2], hash[3]])
+	}
+
+	fn random_input(subject: &[u8]) -> Vec<u8> {
+		let mut input = SELECTOR_RANDOM.to_be_bytes().to_vec();
+		input.extend_from_slice(&EvmDataWriter::new().write_bytes(subject.to_vec()).build());
+		input
+	}
+
+	#[test]
+	fn test_selectors_match_interface() {
+		assert_eq!(selector_of("random(bytes)"), SELECTOR_RANDOM);
+		assert_eq!(selector_of("randomSeed()"), SELECTOR_RANDOM_SEED);
+	}
+
+	#[test]
+	fn test_random_returns_randomness_of_subject() {
+		sp_io::TestExternalities::default().execute_with(|| {
+			let (_, output, cost) = Random::execute(&random_input(b"uniarts"), None, &context()).unwrap();
+			assert_eq!(output, BlakeTwo256::hash(b"uniarts").as_bytes().to_vec());
+			assert_eq!(cost, db_cost::<Test>(1, 0));
+
+			let (_, other, _) = Random::execute(&random_input(b"fuxi"), None, &context()).unwrap();
+			assert_ne!(output, other);
+		});
+	}
+
+	#[test]
+	fn test_random_seed() {
+		sp_io::TestExternalities::default().execute_with(|| {
+			let input = SELECTOR_RANDOM_SEED.to_be_bytes().to_vec();
+			let (_, output, _) = Random::execute(&input, None, &context()).unwrap();
+			assert_eq!(output, H256::repeat_byte(7).as_bytes().to_vec());
+		});
+	}
+
+	#[test]
+	fn test_random_charges_storage_read() {
+		sp_io::TestExternalities::default().execute_with(|| {
+			let cost = db_cost::<Test>(1, 0);
+			assert!(cost > 0);
+			assert_eq!(
+				Random::execute(&random_input(b"uniarts"), Some(cost - 1), &context()),
+				Err(ExitError::OutOfGas),
+			);
+			assert!(Random::execute(&random_input(b"uniarts"), Some(cost), &context()).is_ok());
+		});
+	}
+
+	#[test]
+	fn test_invalid_input() {
+		sp_io::TestExternalities::default().execute_with(|| {
+			assert!(Random::execute(&[0u8; 4], None, &context()).is_err());
+			assert!(Random::execute(&SELECTOR_RANDOM.to_be_bytes(), None, &context()).is_err());
+		});
+	}
+}
@@ -0,0 +1,266 @@
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Staking precompile, exposing `pallet_staking` pools and `pallet_rewards`
+//! mining rewards to Solidity.
+//!
+//! Stake, unstake and claim operations are dispatched as the runtime calls
+//! signed by the account mapped from the EVM caller, so the pallets keep
+//! custody of the funds exactly as for a Substrate wallet. The Solidity
+//! interface is `contracts/precompiles/Staking.sol`.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use sp_std::{marker::PhantomData, vec::Vec};
+use sp_core::H256;
+use sp_runtime::SaturatedConversion;
+use fp_evm::Precompile;
+use evm::{ExitSucceed, ExitError, Context};
+use frame_support::{
+	dispatch::{Dispatchable, GetDispatchInfo, PostDispatchInfo},
+	traits::Currency,
+};
+use pallet_evm::AddressMapping;
+use pallet_evm_precompile_utils::{
+	EvmDataReader, EvmDataWriter, read_selector, ensure_gas, db_cost, dispatch,
+};
+
+/// `stake(uint32,uint256)`
+const SELECTOR_STAKE: u32 = 0x7ec6840f;
+/// `unstake(uint32,uint256)`
+const SELECTOR_UNSTAKE: u32 = 0x9c2f614f;
+/// `claim(uint32)`
+const SELECTOR_CLAIM: u32 = 0x04951891;
+/// `stakedAmount(uint32,address)`
+const SELECTOR_STAKED_AMOUNT: u32 = 0xa7bdca22;
+/// `pendingRewards(uint32,address)`
+const SELECTOR_PENDING_REWARDS: u32 = 0xd42aa87c;
+/// `poolAccount(uint32)`
+const SELECTOR_POOL_ACCOUNT: u32 = 0x5a6a8012;
+/// `claimMiningRewards()`
+const SELECTOR_CLAIM_MINING_REWARDS: u32 = 0x9a983025;
+/// `miningRewards(address)`
+const SELECTOR_MINING_REWARDS: u32 = 0xa73296b2;
+
+type StakingBalanceOf<T> = <<T as pallet_staking::Config>::Currency as Currency<
+	<T as frame_system::Config>::AccountId,
+>>::Balance;
+
+pub struct Staking<T> {
+	_marker: PhantomData<T>,
+}
+
+impl<T> Precompile for Staking<T> where
+	T: pallet_evm::Config + pallet_staking::Config<Id=u32> + pallet_rewards::Config,
+	T::AccountId: Into<[u8; 32]>,
+	T::Call: Dispatchable<PostInfo=PostDispatchInfo> + GetDispatchInfo
+		+ From<pallet_staking::Call<T>> + From<pallet_rewards::Call<T>>,
+	<T::Call as Dispatchable>::Origin: From<Option<T::AccountId>>,
+{
+	fn execute(
+		input: &[u8],
+		target_gas: Option<u64>,
+		context: &Context,
+	) -> core::result::Result<(ExitSucceed, Vec<u8>, u64), ExitError> {
+		let selector = read_selector(input)?;
+		let mut reader = EvmDataReader::new(input);
+
+		match selector {
+			SELECTOR_STAKE | SELECTOR_UNSTAKE => {
+				let pool_id = reader.read_u32()?;
+				let amount: StakingBalanceOf<T> = reader.read_u128()?.saturated_into();
+
+				let call = if selector == SELECTOR_STAKE {
+					pallet_staking::Call::<T>::stake(pool_id, amount)
+				} else {
+					pallet_staking::Call::<T>::unstake(pool_id, amount)
+				};
+				let cost = dispatch::<T>(context.caller, call.into(), target_gas)?;
+
+				Ok((ExitSucceed::Returned, Vec::new(), cost))
+			},
+			SELECTOR_CLAIM => {
+				let pool_id = reader.read_u32()?;
+				let cost = dispatch::<T>(
+					context.caller,
+					pallet_staking::Call::<T>::claim(pool_id).into(),
+					target_gas,
+				)?;
+
+				Ok((ExitSucceed::Returned, Vec::new(), cost))
+			},
+			SELECTOR_STAKED_AMOUNT => {
+				let cost = ensure_gas(target_gas, db_cost::<T>(1, 0))?;
+				let pool_id = reader.read_u32()?;
+				let staker = T::AddressMapping::into_account_id(reader.read_address()?);
+				let amount = pallet_staking::Module::<T>::staking_info(pool_id, staker).amount;
+
+				let output = EvmDataWriter::new()
+					.write_u256(amount.saturated_into::<u128>())
+					.build();
+				Ok((ExitSucceed::Returned, output, cost))
+			},
+			SELECTOR_PENDING_REWARDS => {
+				// Pool, staker position and the current block are read to
+				// compute the accrued rewards.
+				let cost = ensure_gas(target_gas, db_cost::<T>(3, 0))?;
+				let pool_id = reader.read_u32()?;
+				let staker = T::AddressMapping::into_account_id(reader.read_address()?);
+				let pending = pallet_staking::Module::<T>::pending_rewards(pool_id, staker);
+
+				let output = EvmDataWriter::new()
+					.write_u256(pending.saturated_into::<u128>())
+					.build();
+				Ok((ExitSucceed::Returned, output, cost))
+			},
+			SELECTOR_POOL_ACCOUNT => {
+				// Charged as a storage read, like the other queries, so the
+				// precompile is never free to call.
+				let cost = ensure_gas(target_gas, db_cost::<T>(1, 0))?;
+				let pool_id = reader.read_u32()?;
+				let account = pallet_staking::Module::<T>::pool_account_id(pool_id);
+
+				let output = EvmDataWriter::new().write_h256(H256::from(account.into())).build();
+				Ok((ExitSucceed::Returned, output, cost))
+			},
+			SELECTOR_CLAIM_MINING_REWARDS => {
+				let cost = dispatch::<T>(
+					context.caller,
+					pallet_rewards::Call::<T>::claim().into(),
+					target_gas,
+				)?;
+
+				Ok((ExitSucceed::Returned, Vec::new(), cost))
+			},
+			SELECTOR_MINING_REWARDS => {
+				let cost = ensure_gas(target_gas, db_cost::<T>(1, 0))?;
+				let miner = T::AddressMapping::into_account_id(reader.read_address()?);
+				let rewards = pallet_rewards::Module::<T>::account_rewards(miner);
+
+				let output = EvmDataWriter::new()
+					.write_u256(rewards.saturated_into::<u128>())
+					.build();
+				Ok((ExitSucceed::Returned, output, cost))
+			},
+			_ => Err(ExitError::Other("unknown selector".into())),
+		}
+	}
+}
+
+#[cfg(test)]
+mod mock;
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::mock::*;
+	use frame_support::dispatch::GetDispatchInfo;
+	use sp_io::hashing::keccak_256;
+
+	fn selector_of(signature: &str) -> u32 {
+		let hash = keccak_256(signature.as_bytes());
+		u32::from_be_bytes([hash[0], hash[1], hash[2], hash[3]])
+	}
+
+	#[test]
+	fn test_selectors_match_interface() {
+		assert_eq!(selector_of("stake(uint32,uint256)"), SELECTOR_STAKE);
+		assert_eq!(selector_of("unstake(uint32,uint256)"), SELECTOR_UNSTAKE);
+		assert_eq!(selector_of("claim(uint32)"), SELECTOR_CLAIM);
+		assert_eq!(selector_of("stakedAmount(uint32,address)"), SELECTOR_STAKED_AMOUNT);
+		assert_eq!(selector_of("pendingRewards(uint32,address)"), SELECTOR_PENDING_REWARDS);
+		assert_eq!(selector_of("poolAccount(uint32)"), SELECTOR_POOL_ACCOUNT);
+		assert_eq!(selector_of("claimMiningRewards()"), SELECTOR_CLAIM_MINING_REWARDS);
+		assert_eq!(selector_of("miningRewards(address)"), SELECTOR_MINING_REWARDS);
+	}
+
+	#[test]
+	fn test_amount_argument_layout() {
+		let mut input = SELECTOR_STAKE.to_be_bytes().to_vec();
+		input.extend_from_slice(&EvmDataWriter::new().write_u256(7u32).write_u256(1_000u32).build());
+
+		assert_eq!(read_selector(&input).unwrap(), SELECTOR_STAKE);
+		let mut reader = EvmDataReader::new(&input);
+		assert_eq!(reader.read_u32().unwrap(), 7);
+		assert_eq!(reader.read_u128().unwrap(), 1_000);
+	}
+
+	#[test]
+	fn test_stake_and_unstake_are_signed_by_mapped_caller() {
+		new_test_ext().execute_with(|| {
+			let staker = account_of(ALICE);
+			let stake = call_data(SELECTOR_STAKE, EvmDataWriter::new().write_u256(POOL).write_u256(1_000u32));
+			assert!(Staking::<Test>::execute(&stake, None, &context(ALICE)).is_ok());
+
+			assert_eq!(pallet_staking::Module::<Test>::staking_info(POOL, staker.clone()).amount, 1_000);
+			assert_eq!(pallet_staking::Module::<Test>::staking_info(POOL, account_of(BOB)).amount, 0);
+
+			let staked = call_data(SELECTOR_STAKED_AMOUNT, EvmDataWriter::new().write_u256(POOL).write_address(ALICE));
+			let (_, output, _) = Staking::<Test>::execute(&staked, None, &context(BOB)).unwrap();
+			assert_eq!(output, EvmDataWriter::new().write_u256(1_000u32).build());
+
+			let unstake = call_data(SELECTOR_UNSTAKE, EvmDataWriter::new().write_u256(POOL).write_u256(400u32));
+			assert!(Staking::<Test>::execute(&unstake, None, &context(ALICE)).is_ok());
+			assert_eq!(pallet_staking::Module::<Test>::staking_info(POOL, staker).amount, 600);
+		});
+	}
+
+	#[test]
+	fn test_stake_fails_without_funds_of_mapped_caller() {
+		new_test_ext().execute_with(|| {
+			let stake = call_data(SELECTOR_STAKE, EvmDataWriter::new().write_u256(POOL).write_u256(1_000u32));
+			assert!(Staking::<Test>::execute(&stake, None, &context(CHARLIE)).is_err());
+			assert_eq!(pallet_staking::Module::<Test>::staking_info(POOL, account_of(CHARLIE)).amount, 0);
+		});
+	}
+
+	#[test]
+	fn test_stake_charges_call_weight() {
+		new_test_ext().execute_with(|| {
+			let weight = <Test as frame_system::Config>::Call::from(
+				pallet_staking::Call::<Test>::stake(POOL, 1_000)
+			).get_dispatch_info().weight;
+			assert!(weight > 0);
+
+			let stake = call_data(SELECTOR_STAKE, EvmDataWriter::new().write_u256(POOL).write_u256(1_000u32));
+			assert_eq!(
+				Staking::<Test>::execute(&stake, Some(weight - 1), &context(ALICE)),
+				Err(ExitError::OutOfGas),
+			);
+			assert_eq!(pallet_staking::Module::<Test>::staking_info(POOL, account_of(ALICE)).amount, 0);
+
+			let (_, _, cost) = Staking::<Test>::execute(&stake, Some(weight), &context(ALICE)).unwrap();
+			assert!(cost > 0 && cost <= weight);
+		});
+	}
+
+	#[test]
+	fn test_pool_account_charges_storage_read() {
+		new_test_ext().execute_with(|| {
+			let expected = db_cost::<Test>(1, 0);
+			assert!(expected > 0);
+
+			let input = call_data(SELECTOR_POOL_ACCOUNT, EvmDataWriter::new().write_u256(POOL));
+			let (_, output, cost) = Staking::<Test>::execute(&input, None, &context(ALICE)).unwrap();
+			let account: [u8; 32] = pallet_staking::Module::<Test>::pool_account_id(POOL).into();
+			assert_eq!(output, account.to_vec());
+			assert_eq!(cost, expected);
+
+			assert_eq!(
+				Staking::<Test>::execute(&input, Some(expected - 1), &context(ALICE)),
+				Err(ExitError::OutOfGas),
+			);
+		});
+	}
+}
@@ -0,0 +1,218 @@
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Test runtime with the staking and rewards pallets behind the precompile.
+
+use super::*;
+use evm::Context;
+use frame_support::{
+	impl_outer_dispatch, impl_outer_origin, parameter_types,
+	weights::constants::RocksDbWeight,
+};
+use pallet_evm::{EnsureAddressTruncated, FeeCalculator, HashedAddressMapping};
+use sp_core::{H160, U256};
+use sp_runtime::{
+	testing::Header,
+	traits::{BlakeTwo256, Bounded, Convert, IdentityLookup, SaturatedConversion},
+	AccountId32, ModuleId,
+};
+
+impl_outer_origin! {
+	pub enum Origin for Test where system = frame_system {}
+}
+
+impl_outer_dispatch! {
+	pub enum Call for Test where origin: Origin {
+		pallet_staking::StakingModule,
+		pallet_rewards::RewardsModule,
+	}
+}
+
+pub struct PalletInfo;
+
+impl frame_support::traits::PalletInfo for PalletInfo {
+	fn index<P: 'static>() -> Option<usize> {
+		return Some(0)
+	}
+
+	fn name<P: 'static>() -> Option<&'static str> {
+		return Some("TestName")
+	}
+}
+
+#[derive(Clone, Eq, PartialEq)]
+pub struct Test;
+parameter_types! {
+	pub const BlockHashCount: u64 = 250;
+}
+impl frame_system::Config for Test {
+	type BaseCallFilter = ();
+	type BlockWeights = ();
+	type BlockLength = ();
+	type DbWeight = RocksDbWeight;
+	type Origin = Origin;
+	type Index = u64;
+	type BlockNumber = u64;
+	type Hash = H256;
+	type Call = Call;
+	type Hashing = BlakeTwo256;
+	type AccountId = AccountId32;
+	type Lookup = IdentityLookup<Self::AccountId>;
+	type Header = Header;
+	type Event = ();
+	type BlockHashCount = BlockHashCount;
+	type Version = ();
+	type PalletInfo = PalletInfo;
+	type AccountData = pallet_balances::AccountData<u128>;
+	type OnNewAccount = ();
+	type OnKilledAccount = ();
+	type SystemWeightInfo = ();
+	type SS58Prefix = ();
+}
+
+parameter_types! {
+	pub const ExistentialDeposit: u128 = 1;
+}
+impl pallet_balances::Config for Test {
+	type MaxLocks = ();
+	type Balance = u128;
+	type DustRemoval = ();
+	type Event = ();
+	type ExistentialDeposit = ExistentialDeposit;
+	type AccountStore = System;
+	type WeightInfo = ();
+}
+
+parameter_types! {
+	pub const MinimumPeriod: u64 = 1000;
+}
+impl pallet_timestamp::Config for Test {
+	type Moment = u64;
+	type OnTimestampSet = ();
+	type MinimumPeriod = MinimumPeriod;
+	type WeightInfo = ();
+}
+
+pub struct FixedGasPrice;
+impl FeeCalculator for FixedGasPrice {
+	fn min_gas_price() -> U256 {
+		1.into()
+	}
+}
+
+impl pallet_evm::Config for Test {
+	type FeeCalculator = FixedGasPrice;
+	type GasWeightMapping = ();
+	type CallOrigin = EnsureAddressTruncated;
+	type WithdrawOrigin = EnsureAddressTruncated;
+	type AddressMapping = HashedAddressMapping<BlakeTwo256>;
+	type Currency = Balances;
+	type Event = ();
+	type Precompiles = ();
+	type Runner = pallet_evm::runner::stack::Runner<Self>;
+	type ChainId = ();
+}
+
+pub struct ConvertNumberToBalance;
+impl<BlockNumber, Balance: Bounded + From<BlockNumber>> Convert<BlockNumber, Balance> for ConvertNumberToBalance {
+	fn convert(a: BlockNumber) -> Balance {
+		Balance::saturated_from::<BlockNumber>(a)
+	}
+}
+
+parameter_types! {
+	pub const StakingModuleId: ModuleId = ModuleId(*b"staking_");
+	pub const StakingRewardPerBlock: u128 = 10;
+	pub const AmpFactor: u128 = 1_000_000_000_000;
+}
+impl pallet_staking::Config for Test {
+	type ModuleId = StakingModuleId;
+	type Event = ();
+	type Currency = Balances;
+	type RewardPerBlock = StakingRewardPerBlock;
+	type Id = u32;
+	type AmpFactor = AmpFactor;
+	type ConvertNumberToBalance = ConvertNumberToBalance;
+	type WeightInfo = ();
+}
+
+pub struct AccountIdOf;
+impl<T> Convert<T, Option<T>> for AccountIdOf {
+	fn convert(a: T) -> Option<T> {
+		Some(a)
+	}
+}
+
+parameter_types! {
+	pub const RewardThreshold: u128 = 1_000;
+	pub const MiningRewardPerBlock: u128 = 8;
+	pub const BlocksPerYear: u32 = 1_000;
+	pub const MiningCap: u128 = 1_000_000;
+}
+impl pallet_rewards::Config for Test {
+	type AccountIdOf = AccountIdOf;
+	type Balance = u128;
+	type Currency = Balances;
+	type RewardThreshold = RewardThreshold;
+	type RewardPerBlock = MiningRewardPerBlock;
+	type BlocksPerYear = BlocksPerYear;
+	type MiningCap = MiningCap;
+	type Event = ();
+	type WeightInfo = ();
+}
+
+pub type System = frame_system::Module<Test>;
+pub type Balances = pallet_balances::Module<Test>;
+pub type StakingModule = pallet_staking::Module<Test>;
+pub type RewardsModule = pallet_rewards::Module<Test>;
+
+/// Staking pool used by the tests.
+pub const POOL: u32 = 7;
+/// EVM callers, `CHARLIE` has no funds.
+pub const ALICE: H160 = H160([1u8; 20]);
+pub const BOB: H160 = H160([2u8; 20]);
+pub const CHARLIE: H160 = H160([3u8; 20]);
+
+/// Substrate account the precompile signs the calls of `address` with.
+pub fn account_of(address: H160) -> AccountId32 {
+	<Test as pallet_evm::Config>::AddressMapping::into_account_id(address)
+}
+
+/// Precompile input calling `selector` with the given arguments.
+pub fn call_data(selector: u32, arguments: EvmDataWriter) -> Vec<u8> {
+	let mut input = selector.to_be_bytes().to_vec();
+	input.extend_from_slice(&arguments.build());
+	input
+}
+
+/// Execution context of a call made by `caller` to the precompile.
+pub fn context(caller: H160) -> Context {
+	Context {
+		address: H160::from_low_u64_be(1026),
+		caller,
+		apparent_value: U256::zero(),
+	}
+}
+
+pub fn new_test_ext() -> sp_io::TestExternalities {
+	let mut storage = frame_system::GenesisConfig::default().build_storage::<Test>().unwrap();
+
+	pallet_balances::GenesisConfig::<Test> {
+		balances: vec![(account_of(ALICE), 1_000_000), (account_of(BOB), 1_000_000)],
+	}.assimilate_storage(&mut storage).unwrap();
+
+	let mut ext = sp_io::TestExternalities::new(storage);
+	ext.execute_with(|| System::set_block_number(1));
+	ext
+}
@@ -0,0 +1,285 @@
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Minimal Solidity ABI encoding helpers for precompiles that are called
+//! through a Solidity interface (4-byte selector followed by 32-byte words),
+//! and helpers to charge and dispatch runtime calls from a precompile.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use sp_std::vec::Vec;
+use sp_core::{H160, H256, U256};
+use evm::ExitError;
+use frame_support::{
+	dispatch::{Dispatchable, GetDispatchInfo, PostDispatchInfo},
+	traits::Get,
+};
+use pallet_evm::{AddressMapping, GasWeightMapping};
+
+/// Read the 4-byte function selector at the start of the call data.
+pub fn read_selector(input: &[u8]) -> Result<u32, ExitError> {
+	if input.len() < 4 {
+		return Err(ExitError::Other("tried to parse selector out of bounds".into()));
+	}
+
+	let mut buf = [0u8; 4];
+	buf.copy_from_slice(&input[0..4]);
+	Ok(u32::from_be_bytes(buf))
+}
+
+/// Make sure the precompile does not consume more than `target_gas`.
+pub fn ensure_gas(target_gas: Option<u64>, cost: u64) -> Result<u64, ExitError> {
+	if let Some(target_gas) = target_gas {
+		if cost > target_gas {
+			return Err(ExitError::OutOfGas)
+		}
+	}
+
+	Ok(cost)
+}
+
+/// Gas cost of the given number of storage reads and writes.
+pub fn db_cost<T: pallet_evm::Config>(reads: u64, writes: u64) -> u64 {
+	let weight = <T as frame_system::Config>::DbWeight::get().reads_writes(reads, writes);
+	T::GasWeightMapping::weight_to_gas(weight)
+}
+
+/// Dispatch `call` signed by the account mapped from the EVM `caller`.
+/// Returns the gas cost of the actual weight consumed by the call.
+pub fn dispatch<T>(
+	caller: H160,
+	call: T::Call,
+	target_gas: Option<u64>,
+) -> Result<u64, ExitError> where
+	T: pallet_evm::Config,
+	T::Call: Dispatchable<PostInfo=PostDispatchInfo> + GetDispatchInfo,
+	<T::Call as Dispatchable>::Origin: From<Option<T::AccountId>>,
+{
+	let info = call.get_dispatch_info();
+
+	if let Some(gas) = target_gas {
+		if info.weight > T::GasWeightMapping::gas_to_weight(gas) {
+			return Err(ExitError::OutOfGas)
+		}
+	}
+
+	let origin = T::AddressMapping::into_account_id(caller);
+
+	match call.dispatch(Some(origin).into()) {
+		Ok(post_info) => Ok(
+			T::GasWeightMapping::weight_to_gas(post_info.actual_weight.unwrap_or(info.weight))
+		),
+		Err(e) => Err(ExitError::Other(Into::<&'static str>::into(e.error).into())),
+	}
+}
+
+/// Reader of ABI encoded arguments, without the selector.
+pub struct EvmDataReader<'a> {
+	input: &'a [u8],
+	cursor: usize,
+}
+
+impl<'a> EvmDataReader<'a> {
+	/// Create a reader over the arguments of a call, skipping the selector.
+	pub fn new(input: &'a [u8]) -> Self {
+		Self {
+			input: if input.len() >= 4 { &input[4..] } else { &[] },
+			cursor: 0,
+		}
+	}
+
+	fn word_at(&self, offset: usize) -> Result<&'a [u8], ExitError> {
+		let end = offset.checked_add(32)
+			.ok_or(ExitError::Other("tried to parse word out of bounds".into()))?;
+		if end > self.input.len() {
+			return Err(ExitError::Other("tried to parse word out of bounds".into()));
+		}
+		Ok(&self.input[offset..end])
+	}
+
+	fn read_word(&mut self) -> Result<&'a [u8], ExitError> {
+		let word = self.word_at(self.cursor)?;
+		self.cursor += 32;
+		Ok(word)
+	}
+
+	/// Read a `uint256`.
+	pub fn read_u256(&mut self) -> Result<U256, ExitError> {
+		Ok(U256::from_big_endian(self.read_word()?))
+	}
+
+	/// Read a `uint256` that must fit into `u128`.
+	pub fn read_u128(&mut self) -> Result<u128, ExitError> {
+		let value = self.read_u256()?;
+		if value > U256::from(u128::max_value()) {
+			return Err(ExitError::Other("value does not fit into u128".into()));
+		}
+		Ok(value.low_u128())
+	}
+
+	/// Read a `uint256` that must fit into `u64`.
+	pub fn read_u64(&mut self) -> Result<u64, ExitError> {
+		let value = self.read_u256()?;
+		if value > U256::from(u64::max_value()) {
+			return Err(ExitError::Other("value does not fit into u64".into()));
+		}
+		Ok(value.low_u64())
+	}
+
+	/// Read a `uint256` that must fit into `u32`.
+	pub fn read_u32(&mut self) -> Result<u32, ExitError> {
+		let value = self.read_u256()?;
+		if value > U256::from(u32::max_value()) {
+			return Err(ExitError::Other("value does not fit into u32".into()));
+		}
+		Ok(value.low_u32())
+	}
+
+	/// Read a `bytes32`.
+	pub fn read_h256(&mut self) -> Result<H256, ExitError> {
+		Ok(H256::from_slice(self.read_word()?))
+	}
+
+	/// Read an `address`.
+	pub fn read_address(&mut self) -> Result<H160, ExitError> {
+		Ok(H160::from_slice(&self.read_word()?[12..32]))
+	}
+
+	/// Read a `bool`.
+	pub fn read_bool(&mut self) -> Result<bool, ExitError> {
+		Ok(self.read_u256()? != U256::zero())
+	}
+
+	/// Read a dynamic `bytes` (or `string`) argument.
+	pub fn read_bytes(&mut self) -> Result<Vec<u8>, ExitError> {
+		let offset = self.read_u256()?;
+		if offset > U256::from(self.input.len()) {
+			return Err(ExitError::Other("bytes offset out of bounds".into()));
+		}
+		let offset = offset.low_u64() as usize;
+
+		let len = U256::from_big_endian(self.word_at(offset)?);
+		let start = offset + 32;
+		if len > U256::from(self.input.len().saturating_sub(start)) {
+			return Err(ExitError::Other("bytes length out of bounds".into()));
+		}
+		let len = len.low_u64() as usize;
+
+		Ok(self.input[start..start + len].to_vec())
+	}
+}
+
+enum Token {
+	Word([u8; 32]),
+	Bytes(Vec<u8>),
+}
+
+/// Writer of ABI encoded return values.
+#[derive(Default)]
+pub struct EvmDataWriter {
+	tokens: Vec<Token>,
+}
+
+impl EvmDataWriter {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Write a `uint256`.
+	pub fn write_u256<V: Into<U256>>(mut self, value: V) -> Self {
+		let mut word = [0u8; 32];
+		value.into().to_big_endian(&mut word);
+		self.tokens.push(Token::Word(word));
+		self
+	}
+
+	/// Write a `bytes32`.
+	pub fn write_h256(mut self, value: H256) -> Self {
+		self.tokens.push(Token::Word(value.to_fixed_bytes()));
+		self
+	}
+
+	/// Write an `address`.
+	pub fn write_address(self, value: H160) -> Self {
+		self.write_h256(value.into())
+	}
+
+	/// Write a `bool`.
+	pub fn write_bool(self, value: bool) -> Self {
+		self.write_u256(if value { 1u8 } else { 0u8 })
+	}
+
+	/// Write a dynamic `bytes` (or `string`).
+	pub fn write_bytes(mut self, value: Vec<u8>) -> Self {
+		self.tokens.push(Token::Bytes(value));
+		self
+	}
+
+	/// Build the encoded output, with dynamic data placed after the head.
+	pub fn build(self) -> Vec<u8> {
+		let head_len = self.tokens.len() * 32;
+		let mut head = Vec::with_capacity(head_len);
+		let mut tail = Vec::new();
+
+		for token in self.tokens {
+			match token {
+				Token::Word(word) => head.extend_from_slice(&word),
+				Token::Bytes(bytes) => {
+					let mut word = [0u8; 32];
+					U256::from(head_len + tail.len()).to_big_endian(&mut word);
+					head.extend_from_slice(&word);
+
+					U256::from(bytes.len()).to_big_endian(&mut word);
+					tail.extend_from_slice(&word);
+					tail.extend_from_slice(&bytes);
+					let padding = (32 - bytes.len() % 32) % 32;
+					tail.resize(tail.len() + padding, 0);
+				},
+			}
+		}
+
+		head.append(&mut tail);
+		head
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_bytes_round_trip() {
+		let output = EvmDataWriter::new()
+			.write_u256(7u8)
+			.write_bytes(b"uniarts".to_vec())
+			.write_address(H160::repeat_byte(0x11))
+			.build();
+		assert_eq!(output.len(), 32 * 5);
+
+		let mut input = vec![0u8; 4];
+		input.extend_from_slice(&output);
+		let mut reader = EvmDataReader::new(&input);
+		assert_eq!(reader.read_u256().unwrap(), U256::from(7));
+		assert_eq!(reader.read_bytes().unwrap(), b"uniarts".to_vec());
+		assert_eq!(reader.read_address().unwrap(), H160::repeat_byte(0x11));
+	}
+
+	#[test]
+	fn test_out_of_bounds() {
+		let input = [0u8; 20];
+		let mut reader = EvmDataReader::new(&input);
+		assert!(reader.read_u256().is_err());
+		assert!(read_selector(&input[0..3]).is_err());
+	}
+}
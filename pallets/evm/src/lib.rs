@@ -59,9 +59,9 @@ pub mod runner;
 pub use crate::runner::Runner;
 pub use fp_evm::{
 	Account, Log, Vicinity, ExecutionInfo, CallInfo, CreateInfo, Precompile,
-	PrecompileSet, LinearCostPrecompile,
+	PrecompileSet, LinearCostPrecompile, CallTrace, CallType,
 };
-pub use evm::{ExitReason, ExitSucceed, ExitError, ExitRevert, ExitFatal};
+pub use evm::{ExitReason, ExitSucceed, ExitError, ExitRevert, ExitFatal, Context};
 
 use sp_std::vec::Vec;
 #[cfg(feature = "std")]
@@ -502,6 +502,62 @@ decl_module! {
 	}
 }
 
+environmental::environmental!(precompile_revert: Option<(H160, Vec<u8>)>);
+
+/// Record the output of a failing precompile. Precompiles can not revert in
+/// this EVM version: the runner keeps the output with the call frame of the
+/// precompile and passes it up the frames reverting after it, and a call that
+/// fails without output of its own reverts with it.
+pub fn set_precompile_revert(address: H160, output: Vec<u8>) {
+	precompile_revert::with(|revert| *revert = Some((address, output)));
+}
+
+/// Run `f`, returning the output recorded by the last failing precompile, if any.
+pub fn catch_precompile_revert<R>(f: impl FnOnce() -> R) -> (R, Option<(H160, Vec<u8>)>) {
+	let mut revert = None;
+	let result = precompile_revert::using(&mut revert, f);
+	(result, revert)
+}
+
+/// Take the output recorded by the last failing precompile, if any.
+pub(crate) fn take_precompile_revert() -> Option<(H160, Vec<u8>)> {
+	precompile_revert::with(|revert| revert.take()).flatten()
+}
+
+environmental::environmental!(reentrancy_depth: u32);
+
+/// Run `f` one level deeper in the executions re-entering the runtime from
+/// the EVM (runtime call dispatches, calls into other virtual machines), or
+/// return `None` without running it if `max_depth` levels are in progress.
+pub fn with_reentrancy_guard<R>(max_depth: u32, f: impl FnOnce() -> R) -> Option<R> {
+	let depth = reentrancy_depth::with(|depth| *depth).unwrap_or(0);
+	if depth >= max_depth {
+		return None
+	}
+
+	let mut nested = depth + 1;
+	Some(reentrancy_depth::using(&mut nested, f))
+}
+
+environmental::environmental!(call_traces: Vec<CallTrace>);
+
+/// Run `f`, returning the call frames of the executions it makes.
+pub fn trace_calls<R>(f: impl FnOnce() -> R) -> (R, Vec<CallTrace>) {
+	let mut traces = Vec::new();
+	let result = call_traces::using(&mut traces, f);
+	(result, traces)
+}
+
+/// Whether the call frames of executions are being recorded.
+pub(crate) fn is_tracing() -> bool {
+	call_traces::with(|_| ()).is_some()
+}
+
+/// Record the call frames of an execution, if tracing.
+pub(crate) fn record_call_traces(traces: Vec<CallTrace>) {
+	call_traces::with(|recorded| recorded.extend(traces));
+}
+
 impl<T: Config> Module<T> {
 	/// Check whether an account is empty.
 	pub fn is_account_empty(address: &H160) -> bool {
@@ -17,7 +17,7 @@
 
 //! EVM stack-based runner.
 
-use sp_std::{marker::PhantomData, vec::Vec, boxed::Box, mem, collections::btree_set::BTreeSet};
+use sp_std::{marker::PhantomData, vec::Vec, boxed::Box, mem, cell::RefCell, collections::btree_set::BTreeSet};
 use sp_core::{U256, H256, H160};
 use sp_runtime::traits::UniqueSaturatedInto;
 use frame_support::{
@@ -25,8 +25,8 @@ use frame_support::{
 	storage::{StorageMap, StorageDoubleMap},
 };
 use sha3::{Keccak256, Digest};
-use fp_evm::{ExecutionInfo, CallInfo, CreateInfo, Log, Vicinity};
-use evm::{ExitReason, ExitError, Transfer};
+use fp_evm::{ExecutionInfo, CallInfo, CreateInfo, Log, Vicinity, CallTrace, CallType};
+use evm::{ExitReason, ExitError, ExitRevert, Transfer};
 use evm::backend::Backend as BackendT;
 use evm::executor::{StackExecutor, StackSubstateMetadata, StackState as StackStateT};
 use crate::{
@@ -68,7 +68,10 @@ impl<T: Config> Runner<T> {
 		};
 
 		let metadata = StackSubstateMetadata::new(gas_limit, &config);
-		let state = SubstrateStackState::new(&vicinity, metadata);
+		let mut state = SubstrateStackState::new(&vicinity, metadata);
+		if crate::is_tracing() {
+			state.tracer = Some(RefCell::new(CallTracer::default()));
+		}
 		let mut executor = StackExecutor::new_with_precompile(
 			state,
 			config,
@@ -87,7 +90,7 @@ impl<T: Config> Runner<T> {
 			ensure!(source_account.nonce == nonce, Error::<T>::InvalidNonce);
 		}
 
-		let (reason, retv) = f(&mut executor);
+		let ((reason, retv), _) = crate::catch_precompile_revert(|| f(&mut executor));
 
 		let used_gas = U256::from(executor.used_gas());
 		let actual_fee = executor.fee(gas_price);
@@ -105,6 +108,10 @@ impl<T: Config> Runner<T> {
 
 		let state = executor.into_state();
 
+		if let Some(tracer) = state.tracer {
+			crate::record_call_traces(tracer.into_inner().traces);
+		}
+
 		for address in state.substate.deletes {
 			debug::debug!(
 				target: "evm",
@@ -160,13 +167,25 @@ impl<T: Config> RunnerT<T> for Runner<T> {
 			gas_price,
 			nonce,
 			config,
-			|executor| executor.transact_call(
-				source,
-				target,
-				value,
-				input,
-				gas_limit,
-			),
+			|executor| {
+				let (reason, output) = executor.transact_call(
+					source,
+					target,
+					value,
+					input,
+					gas_limit,
+				);
+				// The output of a failed precompile reaches the call when the
+				// precompile is its target, or when the frames calling it
+				// reverted without output of their own.
+				match (reason, executor.state_mut().take_precompile_revert()) {
+					(ExitReason::Error(_), Some((_, revert))) =>
+						(ExitReason::Revert(ExitRevert::Reverted), revert),
+					(ExitReason::Revert(exit), Some((_, revert))) if output.is_empty() =>
+						(ExitReason::Revert(exit), revert),
+					(reason, _) => (reason, output),
+				}
+			},
 		)
 	}
 
@@ -238,6 +257,9 @@ struct SubstrateStackSubstate<'config> {
 	metadata: StackSubstateMetadata<'config>,
 	deletes: BTreeSet<H160>,
 	logs: Vec<Log>,
+	/// Output of the failed precompile the last frame exiting into this one
+	/// failed with, if it failed because of one.
+	revert: Option<(H160, Vec<u8>)>,
 	parent: Option<Box<SubstrateStackSubstate<'config>>>,
 }
 
@@ -256,6 +278,7 @@ impl<'config> SubstrateStackSubstate<'config> {
 			parent: None,
 			deletes: BTreeSet::new(),
 			logs: Vec::new(),
+			revert: None,
 		};
 		mem::swap(&mut entering, self);
 
@@ -282,6 +305,7 @@ impl<'config> SubstrateStackSubstate<'config> {
 
 		self.metadata.swallow_revert(exited.metadata)?;
 		self.logs.append(&mut exited.logs);
+		self.revert = exited.revert;
 
 		sp_io::storage::rollback_transaction();
 		Ok(())
@@ -293,6 +317,7 @@ impl<'config> SubstrateStackSubstate<'config> {
 
 		self.metadata.swallow_discard(exited.metadata)?;
 		self.logs.append(&mut exited.logs);
+		self.revert = exited.revert;
 
 		sp_io::storage::rollback_transaction();
 		Ok(())
@@ -321,10 +346,120 @@ impl<'config> SubstrateStackSubstate<'config> {
 	}
 }
 
+/// Recorder of the call frames entered during an execution.
+#[derive(Default)]
+struct CallTracer {
+	traces: Vec<CallTrace>,
+	/// Frames being executed, innermost last.
+	stack: Vec<TracedFrame>,
+}
+
+/// Frame being executed, whose kind is only known once it exits: the stack
+/// state sees the account a frame runs in, not the call opcode.
+struct TracedFrame {
+	/// Index of the frame in `CallTracer::traces`.
+	index: usize,
+	is_static: bool,
+	/// Whether value was transferred by the frame, which `CALL` and `CALLCODE` do.
+	transferred: bool,
+	/// Account whose code is run by the frame, unless it is a precompile.
+	code_address: Option<H160>,
+}
+
+impl CallTracer {
+	fn enter(&mut self, origin: H160, gas_limit: u64, is_static: bool) {
+		let (from, trace_address) = match self.stack.last() {
+			Some(frame) => {
+				let parent = &mut self.traces[frame.index];
+				let mut trace_address = parent.trace_address.clone();
+				trace_address.push(parent.subtraces);
+				parent.subtraces += 1;
+				(parent.to, trace_address)
+			},
+			None => (origin, Vec::new()),
+		};
+
+		self.stack.push(TracedFrame {
+			index: self.traces.len(),
+			is_static,
+			transferred: false,
+			code_address: None,
+		});
+		self.traces.push(CallTrace {
+			call_type: CallType::Call,
+			from,
+			to: H160::default(),
+			value: U256::zero(),
+			gas: U256::from(gas_limit),
+			gas_used: U256::zero(),
+			success: false,
+			trace_address,
+			subtraces: 0,
+		});
+	}
+
+	fn current(&mut self) -> Option<&mut CallTrace> {
+		let index = self.stack.last()?.index;
+		self.traces.get_mut(index)
+	}
+
+	fn transfer(&mut self, transfer: &Transfer) {
+		if let Some(frame) = self.stack.last_mut() {
+			frame.transferred = true;
+		}
+		if let Some(trace) = self.current() {
+			trace.from = transfer.source;
+			trace.to = transfer.target;
+			trace.value = transfer.value;
+		}
+	}
+
+	/// Record the account whose code is run by the current frame, the first
+	/// account whose code it loads.
+	fn load_code(&mut self, address: H160) {
+		if let Some(frame) = self.stack.last_mut() {
+			if frame.code_address.is_none() {
+				frame.code_address = Some(address);
+			}
+		}
+	}
+
+	fn exit(&mut self, success: bool, gas_used: u64) {
+		let frame = match self.stack.pop() {
+			Some(frame) => frame,
+			None => return,
+		};
+		let trace = &mut self.traces[frame.index];
+		trace.success = success;
+		trace.gas_used = U256::from(gas_used);
+		if trace.call_type == CallType::Create {
+			return;
+		}
+
+		// `DELEGATECALL` and `CALLCODE` run the code of the callee in the
+		// account of the caller, the only frames running code of another account.
+		let foreign_code = frame.code_address.filter(|code_address| *code_address != trace.to);
+		let (call_type, code_address) = match (frame.transferred, foreign_code) {
+			(true, Some(code_address)) => (CallType::CallCode, Some(code_address)),
+			(true, None) => (CallType::Call, None),
+			(false, Some(code_address)) => (CallType::DelegateCall, Some(code_address)),
+			(false, None) if frame.is_static => (CallType::StaticCall, None),
+			// A delegate call to a precompile, which has no code to load.
+			(false, None) => (CallType::DelegateCall, None),
+		};
+		trace.call_type = call_type;
+		if let Some(code_address) = code_address {
+			trace.to = code_address;
+		}
+	}
+}
+
 /// Substrate backend for EVM.
 pub struct SubstrateStackState<'vicinity, 'config, T> {
 	vicinity: &'vicinity Vicinity,
 	substate: SubstrateStackSubstate<'config>,
+	/// Set when tracing. Code is loaded through a shared reference.
+	tracer: Option<RefCell<CallTracer>>,
 	_marker: PhantomData<T>,
 }
 
@@ -335,8 +470,25 @@ impl<'vicinity, 'config, T: Config> SubstrateStackState<'vicinity, 'config, T> {
 			metadata,
 			deletes: BTreeSet::new(),
 			logs: Vec::new(),
+			revert: None,
 			parent: None,
-		}, _marker: PhantomData }
+		}, tracer: None, _marker: PhantomData }
+	}
+
+	/// Take the output of the failed precompile the last call failed with.
+	fn take_precompile_revert(&mut self) -> Option<(H160, Vec<u8>)> {
+		self.substate.revert.take()
+	}
+
+	fn tracer(&mut self) -> Option<&mut CallTracer> {
+		self.tracer.as_mut().map(RefCell::get_mut)
+	}
+
+	fn trace_exit(&mut self, success: bool) {
+		let gas_used = self.substate.metadata().gasometer().total_used_gas();
+		if let Some(tracer) = self.tracer() {
+			tracer.exit(success, gas_used);
+		}
 	}
 }
 
@@ -393,6 +545,9 @@ impl<'vicinity, 'config, T: Config> BackendT for SubstrateStackState<'vicinity,
 	}
 
 	fn code(&self, address: H160) -> Vec<u8> {
+		if let Some(tracer) = &self.tracer {
+			tracer.borrow_mut().load_code(address);
+		}
 		AccountCodes::get(&address)
 	}
 
@@ -415,18 +570,29 @@ impl<'vicinity, 'config, T: Config> StackStateT<'config> for SubstrateStackState
 	}
 
 	fn enter(&mut self, gas_limit: u64, is_static: bool) {
+		let origin = self.vicinity.origin;
+		if let Some(tracer) = self.tracer() {
+			tracer.enter(origin, gas_limit, is_static);
+		}
 		self.substate.enter(gas_limit, is_static)
 	}
 
 	fn exit_commit(&mut self) -> Result<(), ExitError> {
+		self.trace_exit(true);
+		crate::take_precompile_revert();
 		self.substate.exit_commit()
 	}
 
 	fn exit_revert(&mut self) -> Result<(), ExitError> {
+		self.trace_exit(false);
 		self.substate.exit_revert()
 	}
 
 	fn exit_discard(&mut self) -> Result<(), ExitError> {
+		self.trace_exit(false);
+		// Failed precompiles exit their frame here, right after recording
+		// their output. Other frames failing on their own carry none.
+		self.substate.revert = crate::take_precompile_revert();
 		self.substate.exit_discard()
 	}
 
@@ -465,6 +631,11 @@ impl<'vicinity, 'config, T: Config> StackStateT<'config> for SubstrateStackState
 	}
 
 	fn reset_storage(&mut self, address: H160) {
+		// Storage is only reset on the account of a contract being created.
+		if let Some(trace) = self.tracer().and_then(CallTracer::current) {
+			trace.call_type = CallType::Create;
+			trace.to = address;
+		}
 		AccountStorages::remove_prefix(address);
 	}
 
@@ -487,6 +658,10 @@ impl<'vicinity, 'config, T: Config> StackStateT<'config> for SubstrateStackState
 	}
 
 	fn transfer(&mut self, transfer: Transfer) -> Result<(), ExitError> {
+		if let Some(tracer) = self.tracer() {
+			tracer.transfer(&transfer);
+		}
+
 		let source = T::AddressMapping::into_account_id(transfer.source);
 		let target = T::AddressMapping::into_account_id(transfer.target);
 
@@ -506,8 +681,15 @@ impl<'vicinity, 'config, T: Config> StackStateT<'config> for SubstrateStackState
 		// issurance to be reduced. We do not need to replicate this.
 	}
 
-	fn touch(&mut self, _address: H160) {
-		// Do nothing on touch in Substrate.
+	fn touch(&mut self, address: H160) {
+		// The callee of a frame is touched as soon as it is entered.
+		if let Some(trace) = self.tracer().and_then(CallTracer::current) {
+			if trace.to == H160::default() {
+				trace.to = address;
+			}
+		}
+
+		// Nothing else to do on touch in Substrate.
 		//
 		// EVM pallet considers all accounts to exist, and distinguish
 		// only empty and non-empty accounts. This avoids many of the
@@ -128,10 +128,28 @@ impl Config for Test {
 	type Runner = crate::runner::stack::Runner<Self>;
 
 	type Event = Event<Test>;
-	type Precompiles = ();
+	type Precompiles = RevertPrecompile;
 	type ChainId = ();
 }
 
+/// Precompile at address `0xff`, failing with its input as revert data.
+pub struct RevertPrecompile;
+impl PrecompileSet for RevertPrecompile {
+	fn execute(
+		address: H160,
+		input: &[u8],
+		_target_gas: Option<u64>,
+		context: &Context,
+	) -> Option<Result<(ExitSucceed, Vec<u8>, u64), ExitError>> {
+		if address != H160::from_low_u64_be(0xff) {
+			return None
+		}
+
+		set_precompile_revert(context.address, input.to_vec());
+		Some(Err(ExitError::Other("reverted".into())))
+	}
+}
+
 type System = frame_system::Module<Test>;
 type Balances = pallet_balances::Module<Test>;
 type EVM = Module<Test>;
@@ -194,3 +212,125 @@ fn fail_call_return_ok() {
 		));
 	});
 }
+
+#[test]
+fn call_frames_are_traced_while_tracing() {
+	new_test_ext().execute_with(|| {
+		let stop = H160::from_str("1000000000000000000000000000000000000001").unwrap();
+		let invalid = H160::from_str("1000000000000000000000000000000000000002").unwrap();
+
+		let (_, traces) = crate::trace_calls(|| {
+			for target in vec![stop, invalid] {
+				assert_ok!(EVM::call(
+					Origin::root(),
+					H160::default(),
+					target,
+					Vec::new(),
+					U256::default(),
+					1000000,
+					U256::default(),
+					None,
+				));
+			}
+		});
+
+		assert!(!crate::is_tracing());
+		assert_eq!(traces.len(), 2);
+		assert_eq!(traces[0].call_type, CallType::Call);
+		assert_eq!((traces[0].from, traces[0].to), (H160::default(), stop));
+		assert!(traces[0].success);
+		assert_eq!(traces[0].trace_address, Vec::<u32>::new());
+		assert_eq!(traces[1].to, invalid);
+		assert!(!traces[1].success);
+	});
+}
+
+#[test]
+fn delegate_call_and_call_code_frames_are_traced_with_their_code_account() {
+	new_test_ext().execute_with(|| {
+		let stop = H160::from_str("1000000000000000000000000000000000000001").unwrap();
+		let delegator = H160::from_str("1000000000000000000000000000000000000003").unwrap();
+		let call_coder = H160::from_str("1000000000000000000000000000000000000004").unwrap();
+
+		// PUSH1 0 (x4 or x5) PUSH20 stop GAS DELEGATECALL|CALLCODE STOP
+		let forward = |pushes: usize, opcode: u8| {
+			let mut code = [0x60, 0x00].repeat(pushes);
+			code.push(0x73);
+			code.extend_from_slice(stop.as_bytes());
+			code.extend_from_slice(&[0x5a, opcode, 0x00]);
+			code
+		};
+		AccountCodes::insert(delegator, forward(4, 0xf4));
+		AccountCodes::insert(call_coder, forward(5, 0xf2));
+
+		let (_, traces) = crate::trace_calls(|| {
+			for target in vec![delegator, call_coder] {
+				assert_ok!(EVM::call(
+					Origin::root(),
+					H160::default(),
+					target,
+					Vec::new(),
+					U256::default(),
+					1000000,
+					U256::default(),
+					None,
+				));
+			}
+		});
+
+		assert_eq!(traces.len(), 4);
+		assert_eq!(traces[0].call_type, CallType::Call);
+		assert_eq!((traces[0].from, traces[0].to), (H160::default(), delegator));
+		assert_eq!(traces[0].subtraces, 1);
+		assert_eq!(traces[1].call_type, CallType::DelegateCall);
+		assert_eq!((traces[1].from, traces[1].to), (delegator, stop));
+		assert_eq!(traces[1].trace_address, vec![0]);
+		assert_eq!(traces[2].call_type, CallType::Call);
+		assert_eq!(traces[3].call_type, CallType::CallCode);
+		assert_eq!((traces[3].from, traces[3].to), (call_coder, stop));
+	});
+}
+
+#[test]
+fn precompile_revert_reaches_the_callers_of_contracts_calling_it() {
+	new_test_ext().execute_with(|| {
+		let precompile = H160::from_low_u64_be(0xff);
+		let reverter = H160::from_str("1000000000000000000000000000000000000005").unwrap();
+		let catcher = H160::from_str("1000000000000000000000000000000000000006").unwrap();
+
+		// MSTORE 0xbeef, CALL precompile with it as input
+		let mut call = vec![0x61, 0xbe, 0xef, 0x60, 0x00, 0x52];
+		call.extend_from_slice(&[0x60, 0x00, 0x60, 0x00, 0x60, 0x02, 0x60, 0x1e, 0x60, 0x00, 0x60, 0xff, 0x5a, 0xf1]);
+		// PUSH1 28 JUMPI REVERT(0, 0) JUMPDEST STOP
+		let mut reverting = call.clone();
+		reverting.extend_from_slice(&[0x60, 0x1c, 0x57, 0x60, 0x00, 0x60, 0x00, 0xfd, 0x5b, 0x00]);
+		// POP STOP
+		let mut catching = call;
+		catching.extend_from_slice(&[0x50, 0x00]);
+		AccountCodes::insert(reverter, reverting);
+		AccountCodes::insert(catcher, catching);
+
+		let call = |target: H160, input: Vec<u8>| <Test as Config>::Runner::call(
+			H160::default(),
+			target,
+			input,
+			U256::default(),
+			1000000,
+			None,
+			None,
+			<Test as Config>::config(),
+		).unwrap();
+
+		let info = call(precompile, vec![0xca, 0xfe]);
+		assert_eq!(info.exit_reason, ExitReason::Revert(ExitRevert::Reverted));
+		assert_eq!(info.value, vec![0xca, 0xfe]);
+
+		let info = call(reverter, Vec::new());
+		assert_eq!(info.exit_reason, ExitReason::Revert(ExitRevert::Reverted));
+		assert_eq!(info.value, vec![0xbe, 0xef]);
+
+		let info = call(catcher, Vec::new());
+		assert_eq!(info.exit_reason, ExitReason::Succeed(ExitSucceed::Stopped));
+		assert!(info.value.is_empty());
+	});
+}
@@ -57,3 +57,45 @@ pub enum CallOrCreateInfo {
 	Call(CallInfo),
 	Create(CreateInfo),
 }
+
+#[derive(Clone, Copy, Eq, PartialEq, Encode, Decode)]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+/// Kind of a traced call frame.
+pub enum CallType {
+	/// Message call.
+	Call,
+	/// Message call which can't modify state.
+	StaticCall,
+	/// Call running the code of the callee in the context of the caller.
+	DelegateCall,
+	/// Call running the code of the callee on the storage of the caller.
+	CallCode,
+	/// Contract creation.
+	Create,
+}
+
+#[derive(Clone, Eq, PartialEq, Encode, Decode)]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+/// Call frame entered during an execution, including internal calls and
+/// value transfers made by contracts.
+pub struct CallTrace {
+	/// Kind of the frame.
+	pub call_type: CallType,
+	/// Caller of the frame.
+	pub from: H160,
+	/// Callee of the frame, that is the account whose code runs, or the
+	/// created contract.
+	pub to: H160,
+	/// Value transferred to the callee.
+	pub value: U256,
+	/// Gas limit of the frame.
+	pub gas: U256,
+	/// Gas used by the frame, including its subcalls.
+	pub gas_used: U256,
+	/// Whether the frame succeeded.
+	pub success: bool,
+	/// Indices of the subcalls leading from the top-level call to this frame.
+	pub trace_address: Vec<u32>,
+	/// Number of direct subcalls of this frame.
+	pub subtraces: u32,
+}
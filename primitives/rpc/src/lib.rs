@@ -18,10 +18,11 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 
 use sp_core::{H160, H256, U256};
-use ethereum::{Log, Block as EthereumBlock};
+use ethereum::{Log, Block as EthereumBlock, Transaction as EthereumTransaction};
 use ethereum_types::Bloom;
-use codec::{Encode, Decode};
+use codec::{Codec, Encode, Decode};
 use sp_std::vec::Vec;
+use sp_runtime::traits::Block as BlockT;
 
 #[derive(Eq, PartialEq, Clone, Encode, Decode, sp_runtime::RuntimeDebug)]
 pub struct TransactionStatus {
@@ -48,8 +49,59 @@ impl Default for TransactionStatus {
 	}
 }
 
+/// Post-Byzantium (EIP-658) receipt of an Ethereum transaction.
+#[derive(Eq, PartialEq, Clone, Encode, Decode, sp_runtime::RuntimeDebug)]
+pub struct TransactionReceipt {
+	/// `1` if the transaction succeeded, `0` otherwise.
+	pub status_code: u8,
+	/// Gas used by the transaction and the ones before it in the block.
+	pub cumulative_gas_used: U256,
+	/// Gas used by the transaction alone.
+	pub used_gas: U256,
+	/// Price paid per unit of gas.
+	pub effective_gas_price: U256,
+	/// Bloom filter of the transaction logs.
+	pub logs_bloom: Bloom,
+	/// Logs of the transaction, empty if it failed.
+	pub logs: Vec<Log>,
+}
+
+impl TransactionReceipt {
+	/// Convert the receipts of `block` stored as `ethereum::Receipt`, before
+	/// version 2 of `EthereumRuntimeRPCApi`: their `state_root` holds the
+	/// status code and their `used_gas` the gas used by the transaction alone.
+	pub fn from_legacy(block: &EthereumBlock, receipts: Vec<ethereum::Receipt>) -> Vec<Self> {
+		let mut cumulative_gas_used = U256::zero();
+		receipts.into_iter().enumerate().map(|(index, receipt)| {
+			cumulative_gas_used = cumulative_gas_used.saturating_add(receipt.used_gas);
+			TransactionReceipt {
+				status_code: receipt.state_root.to_low_u64_be() as u8,
+				cumulative_gas_used,
+				used_gas: receipt.used_gas,
+				effective_gas_price: block.transactions.get(index)
+					.map(|transaction| transaction.gas_price)
+					.unwrap_or_default(),
+				logs_bloom: receipt.logs_bloom,
+				logs: receipt.logs,
+			}
+		}).collect()
+	}
+}
+
+impl rlp::Encodable for TransactionReceipt {
+	/// Only the consensus fields are encoded, as in the receipts trie.
+	fn rlp_append(&self, s: &mut rlp::RlpStream) {
+		s.begin_list(4);
+		s.append(&self.status_code);
+		s.append(&self.cumulative_gas_used);
+		s.append(&self.logs_bloom);
+		s.append_list(&self.logs);
+	}
+}
+
 sp_api::decl_runtime_apis! {
 	/// API necessary for Ethereum-compatibility layer.
+	#[api_version(6)]
 	pub trait EthereumRuntimeRPCApi {
 		/// Returns runtime defined pallet_evm::ChainId.
 		fn chain_id() -> u64;
@@ -87,17 +139,62 @@ sp_api::decl_runtime_apis! {
 		/// Return the current block.
 		fn current_block() -> Option<EthereumBlock>;
 		/// Return the current receipt.
+		#[changed_in(2)]
 		fn current_receipts() -> Option<Vec<ethereum::Receipt>>;
+		/// Return the current receipts.
+		fn current_receipts() -> Option<Vec<TransactionReceipt>>;
 		/// Return the current transaction status.
 		fn current_transaction_statuses() -> Option<Vec<TransactionStatus>>;
 		/// Return all the current data for a block in a single runtime call.
+		#[changed_in(2)]
 		fn current_all() -> (
 			Option<EthereumBlock>,
 			Option<Vec<ethereum::Receipt>>,
 			Option<Vec<TransactionStatus>>
 		);
+		/// Return all the current data for a block in a single runtime call.
+		fn current_all() -> (
+			Option<EthereumBlock>,
+			Option<Vec<TransactionReceipt>>,
+			Option<Vec<TransactionStatus>>
+		);
 		/// Return the current block gas limit
 		fn current_block_gas_limit() -> U256;
+		/// Return the Ethereum transactions among the given extrinsics.
+		fn extrinsic_filter(xts: Vec<<Block as BlockT>::Extrinsic>) -> Vec<EthereumTransaction>;
+		/// Replay a block on top of its parent and return the call frames of each of
+		/// its Ethereum transactions, in block order. Must be called at the parent.
+		#[changed_in(6)]
+		#[skip_initialize_block]
+		fn trace_block(
+			header: <Block as BlockT>::Header,
+			xts: Vec<<Block as BlockT>::Extrinsic>,
+		) -> Vec<Vec<fp_evm::CallTrace>>;
+		/// Replay a block on top of its parent and return the hash and call frames
+		/// of each of its Ethereum transactions, in block order. Must be called at
+		/// the parent.
+		#[skip_initialize_block]
+		fn trace_block(
+			header: <Block as BlockT>::Header,
+			xts: Vec<<Block as BlockT>::Extrinsic>,
+		) -> Vec<(H256, Vec<fp_evm::CallTrace>)>;
+		/// Returns the storage key of the `System::Account` entry holding the
+		/// balance and nonce of an address, following pallet_evm::Config::AddressMapping.
+		fn account_basic_key(address: H160) -> Vec<u8>;
+	}
+
+	/// Mapping between the EVM addresses and the Substrate accounts of the runtime.
+	pub trait EvmAccountApi<AccountId> where
+		AccountId: Codec,
+	{
+		/// Returns the Substrate account holding the balance of an EVM address,
+		/// following pallet_evm::Config::AddressMapping.
+		fn account_id(address: H160) -> AccountId;
+		/// Returns the EVM address a Substrate account can call and withdraw
+		/// from, following pallet_evm::Config::WithdrawOrigin, if any.
+		fn evm_address(account: AccountId) -> Option<H160>;
+		/// Returns the free balance of a Substrate account.
+		fn free_balance(account: AccountId) -> U256;
 	}
 }
 
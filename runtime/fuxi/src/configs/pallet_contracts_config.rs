@@ -9,6 +9,78 @@ pub use crate::constants::time::*;
 
 use crate::*;
 use crate::frame_system_config::RuntimeBlockWeights;
+use codec::{Decode, Encode};
+use sp_core::H160;
+use sp_runtime::DispatchError;
+use pallet_contracts::chain_extension::{
+    ChainExtension, Environment, Ext, InitState, RetVal, SysConfig, UncheckedFrom,
+};
+use pallet_evm::{ExitReason, FeeCalculator, GasWeightMapping, Runner};
+use crate::pallet_evm_config::MaxReentrancyDepth;
+
+/// Chain extension function calling an EVM contract: `(target, input, gas_limit)`
+/// is read from the input buffer and `(output, used_gas)` is written back.
+pub const EVM_CALL_FUNC_ID: u32 = 0x0001_0001;
+
+/// Lets ink! contracts call EVM contracts through `pallet_evm::Runner::call`.
+///
+/// The EVM caller is the contract account truncated to 20 bytes, the same
+/// mapping as `EnsureAddressTruncated`. The EVM fee is paid at the minimum gas
+/// price by the account mapped from that caller, like any EVM call, and the
+/// gas limit is charged to the contract as weight, with unused gas refunded.
+/// Calls count against the depth of the executions re-entering the runtime
+/// from the EVM. Returns `0` on success, `1` if the EVM call reverted and `2`
+/// on any other EVM error, with the output data written in every case.
+pub struct EvmExtension;
+
+impl ChainExtension for EvmExtension {
+    fn call<E: Ext>(func_id: u32, env: Environment<E, InitState>) -> Result<RetVal, DispatchError>
+    where
+        <E::T as SysConfig>::AccountId: UncheckedFrom<<E::T as SysConfig>::Hash> + AsRef<[u8]>,
+    {
+        if func_id != EVM_CALL_FUNC_ID {
+            return Err(DispatchError::Other("unknown chain extension function"));
+        }
+
+        let mut env = env.buf_in_buf_out();
+        let input = env.read(MaxValueSize::get())?;
+        let (target, data, gas_limit): (H160, Vec<u8>, u64) = Decode::decode(&mut &input[..])
+            .map_err(|_| DispatchError::Other("invalid EVM call input"))?;
+
+        let max_weight = <Runtime as pallet_evm::Config>::GasWeightMapping::gas_to_weight(gas_limit);
+        let charged = env.charge_weight(max_weight)?;
+
+        let source = H160::from_slice(&env.ext().address().as_ref()[0..20]);
+        let gas_price = <Runtime as pallet_evm::Config>::FeeCalculator::min_gas_price();
+        let info = pallet_evm::with_reentrancy_guard(MaxReentrancyDepth::get(), || {
+            <Runtime as pallet_evm::Config>::Runner::call(
+                source,
+                target,
+                data,
+                U256::zero(),
+                gas_limit,
+                Some(gas_price),
+                None,
+                <Runtime as pallet_evm::Config>::config(),
+            )
+        })
+            .ok_or(DispatchError::Other("EVM call depth exceeded"))?
+            .map_err(Into::<DispatchError>::into)?;
+
+        let used_gas = info.used_gas.low_u64();
+        env.adjust_weight(
+            charged,
+            <Runtime as pallet_evm::Config>::GasWeightMapping::gas_to_weight(used_gas),
+        );
+        env.write(&(info.value, used_gas).encode(), false, None)?;
+
+        Ok(RetVal::Converging(match info.exit_reason {
+            ExitReason::Succeed(_) => 0,
+            ExitReason::Revert(_) => 1,
+            _ => 2,
+        }))
+    }
+}
 
 
 frame_support::parameter_types! {
@@ -53,7 +125,7 @@ impl Config for Runtime {
     type MaxValueSize = MaxValueSize;
     type WeightPrice = pallet_transaction_payment::Module<Self>;
     type WeightInfo = pallet_contracts::weights::SubstrateWeight<Self>;
-    type ChainExtension = ();
+    type ChainExtension = EvmExtension;
     type DeletionQueueDepth = DeletionQueueDepth;
     type DeletionWeightLimit = DeletionWeightLimit;
     type MaxCodeSize = MaxCodeSize;
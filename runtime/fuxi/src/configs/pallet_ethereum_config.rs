@@ -20,6 +20,7 @@ impl<F: FindAuthor<u32>> FindAuthor<H160> for EthereumFindAuthor<F>
 
 frame_support::parameter_types! {
 	pub BlockGasLimit: U256 = U256::from(u32::max_value());
+	pub const EthereumTransactionLongevity: u64 = 256;
 }
 
 impl Config for Runtime {
@@ -27,4 +28,5 @@ impl Config for Runtime {
     type FindAuthor = EthereumFindAuthor<Aura>;
     type StateRoot = pallet_ethereum::IntermediateStateRoot;
     type BlockGasLimit = BlockGasLimit;
+    type TransactionLongevity = EthereumTransactionLongevity;
 }
\ No newline at end of file
@@ -1,10 +1,20 @@
 use pallet_evm::Config;
 use crate::*;
 
-use sp_core::U256;
+use sp_core::{H160, U256};
+use sp_std::{marker::PhantomData, vec::Vec};
 use pallet_evm::{
-    HashedAddressMapping, EnsureAddressTruncated,
+    HashedAddressMapping, EnsureAddressTruncated, GasWeightMapping, Precompile, PrecompileSet,
+    Context, ExitSucceed, ExitError,
 };
+use pallet_evm_precompile_simple::{ECRecover, Sha256, Ripemd160, Identity};
+use pallet_evm_precompile_names::Names;
+use pallet_evm_precompile_dispatch::Dispatch;
+use pallet_evm_precompile_staking::Staking as StakingPrecompile;
+use pallet_evm_precompile_randomness::RandomnessSource;
+use pallet_evm_precompile_blindbox::BlindBox as BlindBoxPrecompile;
+use pallet_evm_precompile_contracts::InkContracts;
+use frame_support::traits::Filter;
 
 /// Fixed gas price of `1`.
 pub struct FixedGasPrice;
@@ -16,8 +26,112 @@ impl FeeCalculator for FixedGasPrice {
     }
 }
 
+/// Ethereum precompiles at addresses 1-4, followed by the Uni-Arts specific
+/// precompiles starting at address 1024.
+pub struct FuxiPrecompiles<R>(PhantomData<R>);
+
+impl<R> PrecompileSet for FuxiPrecompiles<R> where
+    Names<R>: Precompile,
+    R: pallet_evm_precompile_dispatch::Config,
+    Dispatch<R>: Precompile,
+    StakingPrecompile<R>: Precompile,
+    RandomnessSource<R, RandomnessCollectiveFlip>: Precompile,
+    BlindBoxPrecompile<R>: Precompile,
+    InkContracts<R>: Precompile,
+{
+    fn execute(
+        address: H160,
+        input: &[u8],
+        target_gas: Option<u64>,
+        context: &Context,
+    ) -> Option<Result<(ExitSucceed, Vec<u8>, u64), ExitError>> {
+        if let Some(result) = <(ECRecover, Sha256, Ripemd160, Identity) as PrecompileSet>::execute(
+            address, input, target_gas, context,
+        ) {
+            return Some(result);
+        }
+
+        match address {
+            a if a == H160::from_low_u64_be(1024) => Some(Names::<R>::execute(input, target_gas, context)),
+            a if a == H160::from_low_u64_be(1025) => Some(Dispatch::<R>::execute(input, target_gas, context)),
+            a if a == H160::from_low_u64_be(1026) => Some(StakingPrecompile::<R>::execute(input, target_gas, context)),
+            a if a == H160::from_low_u64_be(1027) => Some(
+                RandomnessSource::<R, RandomnessCollectiveFlip>::execute(input, target_gas, context)
+            ),
+            a if a == H160::from_low_u64_be(1028) => Some(BlindBoxPrecompile::<R>::execute(input, target_gas, context)),
+            a if a == H160::from_low_u64_be(1029) => Some(InkContracts::<R>::execute(input, target_gas, context)),
+            _ => None,
+        }
+    }
+}
+
+/// Calls contracts may dispatch through the dispatch precompile: governance
+/// and staking only.
+pub struct ContractDispatchFilter;
+
+impl Filter<Call> for ContractDispatchFilter {
+    fn filter(c: &Call) -> bool {
+        matches!(
+            c,
+            Call::Staking(..) |
+            Call::Council(..) |
+            Call::TechnicalCommittee(..) |
+            Call::ElectionsPhragmen(..) |
+            Call::Treasury(..)
+        )
+    }
+}
+
+/// Calls which can execute EVM code and would re-enter the EVM from a
+/// precompile: `EVM`, `Ethereum` and `Contracts` (through the ink! chain
+/// extension), wrappers able to carry any call (`Utility`, `Proxy`,
+/// `Multisig`, `Sudo`, `Scheduler`), and collective proposals carrying one of
+/// these.
+pub struct EvmCalls;
+
+impl Filter<Call> for EvmCalls {
+    fn filter(c: &Call) -> bool {
+        match c {
+            Call::Council(pallet_collective::Call::execute(proposal, _)) |
+            Call::Council(pallet_collective::Call::propose(_, proposal, _)) |
+            Call::TechnicalCommittee(pallet_collective::Call::execute(proposal, _)) |
+            Call::TechnicalCommittee(pallet_collective::Call::propose(_, proposal, _)) =>
+                Self::filter(proposal),
+            _ => matches!(
+                c,
+                Call::EVM(..) |
+                Call::Ethereum(..) |
+                Call::Contracts(..) |
+                Call::Utility(..) |
+                Call::Proxy(..) |
+                Call::Multisig(..) |
+                Call::Sudo(..) |
+                Call::Scheduler(..)
+            ),
+        }
+    }
+}
+
 frame_support::parameter_types! {
 	pub const ChainId: u64 = 1981;
+	/// Nested executions re-entering the runtime from the EVM: dispatches,
+	/// ink! contract calls and EVM calls from ink! contracts.
+	pub const MaxReentrancyDepth: u32 = 2;
+	/// Gas given at most to an ink! contract called from the EVM.
+	pub MaxInkCallGas: u64 = <Runtime as Config>::GasWeightMapping::weight_to_gas(
+		crate::frame_system_config::RuntimeBlockWeights::get().max_block / 4
+	);
+}
+
+impl pallet_evm_precompile_dispatch::Config for Runtime {
+    type CallFilter = ContractDispatchFilter;
+    type EvmCalls = EvmCalls;
+    type MaxDispatchDepth = MaxReentrancyDepth;
+}
+
+impl pallet_evm_precompile_contracts::Config for Runtime {
+    type MaxGas = MaxInkCallGas;
+    type MaxCallDepth = MaxReentrancyDepth;
 }
 
 impl Config for Runtime {
@@ -29,11 +143,6 @@ impl Config for Runtime {
     type Currency = Balances;
     type Event = Event;
     type Runner = pallet_evm::runner::stack::Runner<Self>;
-    type Precompiles = (
-        pallet_evm_precompile_simple::ECRecover,
-        pallet_evm_precompile_simple::Sha256,
-        pallet_evm_precompile_simple::Ripemd160,
-        pallet_evm_precompile_simple::Identity,
-    );
+    type Precompiles = FuxiPrecompiles<Self>;
     type ChainId = ChainId;
 }
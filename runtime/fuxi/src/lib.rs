@@ -55,7 +55,7 @@ type Uart = Balances;
 
 // EVM
 use pallet_evm::{
-	Account as EVMAccount, FeeCalculator, Runner,
+	Account as EVMAccount, AddressMapping, EnsureAddressOrigin, FeeCalculator, Runner,
 };
 use fp_rpc::TransactionStatus;
 
@@ -71,7 +71,7 @@ pub use pallet_timestamp::Call as TimestampCall;
 pub use sp_runtime::{Permill, Perbill, Percent, ModuleId};
 
 pub use frame_support::{
-	construct_runtime, parameter_types, StorageValue, ConsensusEngineId,
+	construct_runtime, parameter_types, StorageValue, StorageMap, ConsensusEngineId,
 	traits::{OnUnbalanced, ChangeMembers, KeyOwnerProofSystem, Randomness, StorageMapShim, Currency, Imbalance,
 			 Contains, ContainsLengthBound, InstanceFilter, LockIdentifier, SplitTwoWays, FindAuthor
 	},
@@ -129,7 +129,7 @@ pub const VERSION: RuntimeVersion = RuntimeVersion {
 	spec_name: create_runtime_str!("uart"),
 	impl_name: create_runtime_str!("uart"),
 	authoring_version: 1,
-	spec_version: 55,
+	spec_version: 58,
 	impl_version: 1,
 	apis: RUNTIME_API_VERSIONS,
 	transaction_version: 2,
@@ -493,13 +493,13 @@ impl_runtime_apis! {
 			Ethereum::current_block()
 		}
 
-		fn current_receipts() -> Option<Vec<pallet_ethereum::Receipt>> {
+		fn current_receipts() -> Option<Vec<pallet_ethereum::TransactionReceipt>> {
 			Ethereum::current_receipts()
 		}
 
 		fn current_all() -> (
 			Option<pallet_ethereum::Block>,
-			Option<Vec<pallet_ethereum::Receipt>>,
+			Option<Vec<pallet_ethereum::TransactionReceipt>>,
 			Option<Vec<TransactionStatus>>
 		) {
 			(
@@ -512,6 +512,58 @@ impl_runtime_apis! {
 		fn current_block_gas_limit() -> U256 {
 			<Runtime as pallet_ethereum::Config>::BlockGasLimit::get()
 		}
+
+		fn extrinsic_filter(xts: Vec<<Block as BlockT>::Extrinsic>) -> Vec<pallet_ethereum::Transaction> {
+			xts.into_iter().filter_map(|xt| match xt.function {
+				Call::Ethereum(pallet_ethereum::Call::transact(transaction)) => Some(transaction),
+				_ => None,
+			}).collect()
+		}
+
+		fn trace_block(
+			header: <Block as BlockT>::Header,
+			xts: Vec<<Block as BlockT>::Extrinsic>,
+		) -> Vec<(H256, Vec<pallet_evm::CallTrace>)> {
+			Executive::initialize_block(&header);
+
+			// Every extrinsic is applied so that each transaction sees the state it
+			// was executed against, but only Ethereum transactions are reported.
+			xts.into_iter().filter_map(|xt| {
+				let transaction_hash = match &xt.function {
+					Call::Ethereum(pallet_ethereum::Call::transact(transaction)) =>
+						Some(Ethereum::transaction_hash(transaction)),
+					_ => None,
+				};
+				let (_, traces) = pallet_evm::trace_calls(|| Executive::apply_extrinsic(xt));
+				transaction_hash.map(|hash| (hash, traces))
+			}).collect()
+		}
+
+		fn account_basic_key(address: H160) -> Vec<u8> {
+			frame_system::Account::<Runtime>::hashed_key_for(
+				<Runtime as pallet_evm::Config>::AddressMapping::into_account_id(address)
+			)
+		}
+	}
+
+	impl fp_rpc::EvmAccountApi<Block, AccountId> for Runtime {
+		fn account_id(address: H160) -> AccountId {
+			<Runtime as pallet_evm::Config>::AddressMapping::into_account_id(address)
+		}
+
+		fn evm_address(account: AccountId) -> Option<H160> {
+			// The address made of the first 20 bytes of the account, reported only
+			// if `WithdrawOrigin` lets the account act as it.
+			let address = H160::from_slice(&AsRef::<[u8; 32]>::as_ref(&account)[0..20]);
+			<Runtime as pallet_evm::Config>::WithdrawOrigin::ensure_address_origin(
+				&address,
+				frame_system::RawOrigin::Signed(account).into(),
+			).ok().map(|_| address)
+		}
+
+		fn free_balance(account: AccountId) -> U256 {
+			Balances::free_balance(&account).into()
+		}
 	}
 
 	impl pallet_transaction_payment_rpc_runtime_api::TransactionPaymentApi<
@@ -603,3 +655,44 @@ impl_runtime_apis! {
 		}
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use frame_support::traits::Currency;
+	use fp_rpc::runtime_decl_for_EthereumRuntimeRPCApi::EthereumRuntimeRPCApi;
+	use fp_rpc::runtime_decl_for_EvmAccountApi::EvmAccountApi;
+
+	fn new_test_ext() -> sp_io::TestExternalities {
+		frame_system::GenesisConfig::default().build_storage::<Runtime>().unwrap().into()
+	}
+
+	#[test]
+	fn evm_address_maps_back_to_the_account_holding_its_evm_balance() {
+		new_test_ext().execute_with(|| {
+			let account = AccountId::from([7u8; 32]);
+			Balances::make_free_balance_be(&account, 1_000);
+
+			let address = <Runtime as EvmAccountApi<Block, AccountId>>::evm_address(account.clone())
+				.expect("WithdrawOrigin lets an account act as its truncated address");
+			let mapped = <Runtime as EvmAccountApi<Block, AccountId>>::account_id(address);
+			assert_eq!(mapped, <Runtime as pallet_evm::Config>::AddressMapping::into_account_id(address));
+			Balances::make_free_balance_be(&mapped, 2_000);
+
+			// `uniarts_evm_balances` reads the Substrate balance of the account and
+			// the EVM balance of its address, held by the mapped account.
+			assert_eq!(
+				<Runtime as EvmAccountApi<Block, AccountId>>::free_balance(account),
+				U256::from(1_000),
+			);
+			assert_eq!(
+				<Runtime as EthereumRuntimeRPCApi<Block>>::account_basic(address).balance,
+				U256::from(Balances::free_balance(&mapped)),
+			);
+			assert_eq!(
+				<Runtime as EthereumRuntimeRPCApi<Block>>::account_basic(address).balance,
+				U256::from(2_000),
+			);
+		});
+	}
+}